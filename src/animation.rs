@@ -0,0 +1,175 @@
+use std::time::{Duration, Instant};
+
+use latency::micros;
+use rect::Rect;
+
+/// Interpolate between two values
+pub fn lerp(from: i32, to: i32, t: f32) -> i32 {
+    from + ((to - from) as f32 * t) as i32
+}
+
+/// Progress of a timed transition
+pub struct Animation {
+    start: Instant,
+    duration: Duration,
+}
+
+impl Animation {
+    pub fn new(duration: Duration) -> Animation {
+        Animation {
+            start: Instant::now(),
+            duration: duration
+        }
+    }
+
+    /// Linear progress from 0 to 1
+    pub fn linear(&self) -> f32 {
+        let duration = micros(self.duration);
+        if duration == 0 {
+            1.0
+        } else {
+            let elapsed = micros(self.start.elapsed());
+            if elapsed >= duration {
+                1.0
+            } else {
+                elapsed as f32 / duration as f32
+            }
+        }
+    }
+
+    /// Progress from 0 to 1, decelerating towards the end
+    pub fn ease_out(&self) -> f32 {
+        let t = 1.0 - self.linear();
+        1.0 - t * t * t
+    }
+
+    /// Repeating progress from 0 to 1 and back, once per duration
+    pub fn pulse(&self) -> f32 {
+        let duration = micros(self.duration);
+        if duration == 0 {
+            1.0
+        } else {
+            let t = (micros(self.start.elapsed()) % duration) as f32 / duration as f32;
+            0.5 - 0.5 * (t * 2.0 * ::std::f32::consts::PI).cos()
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.start.elapsed() >= self.duration
+    }
+}
+
+/// Animated move of a window to a target position
+pub struct MoveAnimation {
+    pub id: usize,
+    /// Size the client is asked to resize to once the move is done
+    pub resize: Option<(i32, i32)>,
+    from_x: i32,
+    from_y: i32,
+    to_x: i32,
+    to_y: i32,
+    animation: Animation,
+}
+
+impl MoveAnimation {
+    pub fn new(id: usize, from_x: i32, from_y: i32, to_x: i32, to_y: i32, duration: Duration) -> MoveAnimation {
+        MoveAnimation {
+            id: id,
+            resize: None,
+            from_x: from_x,
+            from_y: from_y,
+            to_x: to_x,
+            to_y: to_y,
+            animation: Animation::new(duration)
+        }
+    }
+
+    /// Current position of the window
+    pub fn position(&self) -> (i32, i32) {
+        let t = self.animation.ease_out();
+        (lerp(self.from_x, self.to_x, t), lerp(self.from_y, self.to_y, t))
+    }
+
+    pub fn done(&self) -> bool {
+        self.animation.done()
+    }
+}
+
+/// Milliseconds a window takes to open, close, or minimize
+const WINDOW_ANIMATION_DURATION: u64 = 150;
+
+/// What a window animation shows
+#[derive(Clone, Copy, PartialEq)]
+pub enum WindowAnimationKind {
+    /// Fading in while growing to its size
+    Open,
+    /// Fading out while shrinking, drawn from the window kept after it closed
+    Close,
+    /// Shrinking towards its task bar button
+    Minimize,
+}
+
+/// Animated opening, closing, or minimizing of a window, drawn scaled over the other windows
+pub struct WindowAnimation {
+    pub id: usize,
+    pub kind: WindowAnimationKind,
+    from: Rect,
+    to: Rect,
+    animation: Animation,
+}
+
+/// A rect scaled around its center
+fn scale(rect: &Rect, percent: i32) -> Rect {
+    let w = rect.width() * percent / 100;
+    let h = rect.height() * percent / 100;
+    Rect::new(rect.left() + (rect.width() - w)/2, rect.top() + (rect.height() - h)/2, w, h)
+}
+
+impl WindowAnimation {
+    fn new(id: usize, kind: WindowAnimationKind, from: Rect, to: Rect) -> WindowAnimation {
+        WindowAnimation {
+            id: id,
+            kind: kind,
+            from: from,
+            to: to,
+            animation: Animation::new(Duration::from_millis(WINDOW_ANIMATION_DURATION))
+        }
+    }
+
+    pub fn open(id: usize, rect: Rect) -> WindowAnimation {
+        WindowAnimation::new(id, WindowAnimationKind::Open, scale(&rect, 80), rect)
+    }
+
+    pub fn close(id: usize, rect: Rect) -> WindowAnimation {
+        WindowAnimation::new(id, WindowAnimationKind::Close, rect, scale(&rect, 90))
+    }
+
+    pub fn minimize(id: usize, rect: Rect, target: Rect) -> WindowAnimation {
+        WindowAnimation::new(id, WindowAnimationKind::Minimize, rect, target)
+    }
+
+    /// Area the window is drawn in
+    pub fn rect(&self) -> Rect {
+        let t = self.animation.ease_out();
+        Rect::new(lerp(self.from.left(), self.to.left(), t), lerp(self.from.top(), self.to.top(), t),
+                  lerp(self.from.width(), self.to.width(), t), lerp(self.from.height(), self.to.height(), t))
+    }
+
+    /// Area covered at any point of the animation, to be redrawn while it runs
+    pub fn bounds(&self) -> Rect {
+        self.from.container(&self.to)
+    }
+
+    pub fn opacity(&self) -> u8 {
+        let t = self.animation.ease_out();
+        match self.kind {
+            WindowAnimationKind::Open => (t * 255.0) as u8,
+            WindowAnimationKind::Close => ((1.0 - t) * 255.0) as u8,
+            WindowAnimationKind::Minimize => (255.0 - t * 160.0) as u8
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.animation.done()
+    }
+}
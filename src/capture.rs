@@ -0,0 +1,82 @@
+use std::cmp::min;
+use syscall::error::{Error, Result, EAGAIN};
+
+use image::Image;
+
+/// Pixels of an image as `width,height` on the first line, followed by 32-bit BGRA
+pub fn raw(image: &Image) -> Vec<u8> {
+    let header = format!("{},{}\n", image.width(), image.height());
+    let mut data = Vec::with_capacity(header.len() + image.data().len() * 4);
+    data.extend_from_slice(header.as_bytes());
+    for pixel in image.data().iter() {
+        data.push(pixel.data as u8);
+        data.push((pixel.data >> 8) as u8);
+        data.push((pixel.data >> 16) as u8);
+        data.push((pixel.data >> 24) as u8);
+    }
+    data
+}
+
+/// A handle to a screenshot, opened with `orbital:capture` or `orbital:capture/<window id>`,
+/// which only root may open
+///
+/// The screen is captured at the next composite, or with a window id, at the first composite
+/// after that window's next fsync, when its client has finished drawing a frame. Reads wait for
+/// the capture, then return it in the format of `raw`. If the window closes first, reads return
+/// end of file.
+pub struct Capture {
+    /// Window whose fsync triggers the capture
+    pub window: Option<usize>,
+    /// Capture the next composite, set once the window has synced
+    pub armed: bool,
+    data: Option<Vec<u8>>,
+    offset: usize,
+}
+
+impl Capture {
+    pub fn new(window: Option<usize>) -> Capture {
+        Capture {
+            window: window,
+            armed: window.is_none(),
+            data: None,
+            offset: 0
+        }
+    }
+
+    /// Is the capture still waiting for a composite
+    pub fn pending(&self) -> bool {
+        self.data.is_none()
+    }
+
+    /// Copy the composited screen
+    pub fn take(&mut self, image: &Image) {
+        self.data = Some(raw(image));
+    }
+
+    /// Give up on the capture, when its window closed before syncing
+    pub fn cancel(&mut self) {
+        if self.data.is_none() {
+            self.data = Some(Vec::new());
+        }
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self.data {
+            Some(ref data) => {
+                let count = min(buf.len(), data.len() - self.offset);
+                buf[..count].copy_from_slice(&data[self.offset .. self.offset + count]);
+                self.offset += count;
+                Ok(count)
+            },
+            None => Err(Error::new(EAGAIN))
+        }
+    }
+
+    /// Path of the handle, without the scheme
+    pub fn path(&self) -> String {
+        match self.window {
+            Some(window) => format!("capture/{}", window),
+            None => "capture".to_string()
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::cmp::min;
+use std::mem;
+
+/// Copied data, owned by the compositor so it outlives the window it came from
+pub struct Clipboard {
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+impl Clipboard {
+    pub fn new() -> Clipboard {
+        Clipboard {
+            mime: "text/plain".to_string(),
+            data: Vec::new()
+        }
+    }
+
+    /// Bytes used by the copied data
+    pub fn bytes(&self) -> usize {
+        self.data.len()
+    }
+}
+
+/// A handle to the clipboard endpoint, opened with `orbital:clipboard`
+///
+/// Reading returns the MIME type on the first line, followed by the data. Writing the same
+/// format replaces the clipboard when the handle is synced or closed.
+pub struct ClipboardHandle {
+    data: Vec<u8>,
+    offset: usize,
+    written: Vec<u8>,
+}
+
+impl ClipboardHandle {
+    pub fn new(clipboard: &Clipboard) -> ClipboardHandle {
+        let mut data = Vec::with_capacity(clipboard.mime.len() + 1 + clipboard.data.len());
+        data.extend_from_slice(clipboard.mime.as_bytes());
+        data.push(b'\n');
+        data.extend_from_slice(&clipboard.data);
+
+        ClipboardHandle {
+            data: data,
+            offset: 0,
+            written: Vec::new()
+        }
+    }
+
+    /// Read the clipboard contents captured when the handle was opened
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let count = min(buf.len(), self.data.len() - self.offset);
+        buf[..count].copy_from_slice(&self.data[self.offset .. self.offset + count]);
+        self.offset += count;
+        count
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> usize {
+        self.written.extend_from_slice(buf);
+        buf.len()
+    }
+
+    /// Take the data written since the last commit, if any
+    pub fn commit(&mut self) -> Option<Clipboard> {
+        if self.written.is_empty() {
+            return None;
+        }
+
+        let mut data = Vec::new();
+        mem::swap(&mut data, &mut self.written);
+
+        // Data without a MIME type line is treated as plain text
+        let clipboard = match data.iter().position(|&b| b == b'\n') {
+            Some(i) => Clipboard {
+                mime: String::from_utf8_lossy(&data[..i]).into_owned(),
+                data: data[i + 1 ..].to_vec()
+            },
+            None => Clipboard {
+                mime: "text/plain".to_string(),
+                data: data
+            }
+        };
+
+        Some(clipboard)
+    }
+}
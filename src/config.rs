@@ -2,12 +2,121 @@ use std::fs::File;
 use std::io::Read;
 
 pub struct Config {
+    /// Path the config was read from, to reload it
+    pub path: String,
     pub background: Vec<String>,
     pub background_mode: String,
+    /// Backgrounds shown instead of `background` with the light or dark theme, see `ThemeSchedule`
+    pub background_light: Vec<String>,
+    pub background_dark: Vec<String>,
+    /// Times of day the light and dark themes start, as `HH:MM` in local time
+    pub light_time: String,
+    pub dark_time: String,
+    /// Offset of local time from UTC, as `+HH:MM` or `-HH:MM`
+    pub utc_offset: String,
     pub cursor: String,
+    pub cursor_horizontal: String,
+    pub cursor_vertical: String,
+    pub cursor_diagonal: String,
+    pub cursor_antidiagonal: String,
+    pub cursor_move: String,
+    /// Cursor shown while a launched app has not opened its window yet
+    pub cursor_busy: String,
     pub window_close: String,
     pub window_close_unfocused: String,
      pub window_minimize: String,
+    pub window_maximize: String,
+    /// Use built-in images for the cursor and title bar buttons that fail to load, instead of
+    /// leaving them invisible
+    pub fallback_images: bool,
+    pub window_close_mode: String,
+    pub window_close_timeout: u64,
+    pub focus_after_close: String,
+    pub desktop_scroll: String,
+    /// Which windows scrolling over a title bar cycles through, see `TitleScroll`
+    pub title_scroll: String,
+    /// What middle clicking a title bar does, see `ClickAction`
+    pub title_middle_click: String,
+    /// What right clicking a title bar does
+    pub title_right_click: String,
+    /// What Win+middle clicking a window does, nothing unless set
+    pub win_middle_click: String,
+    pub quality_policy: String,
+    /// Frames composited per second at most, 60 if unset
+    pub frame_rate: u32,
+    pub frame_budget: u64,
+    pub text_cache_size: usize,
+    pub hooks: Vec<(String, String)>,
+    pub hook_notify: String,
+    /// Seconds without input before the screen blanks, disabled when zero
+    pub idle_timeout: u64,
+    /// Milliseconds the screen fades out for before the idle timeout, disabled when zero
+    pub dim_duration: u64,
+    /// Milliseconds notifications are shown for, unless they set their own timeout
+    pub notification_timeout: u64,
+    /// Directory Win+PrintScreen saves screenshots and Win+Shift+R saves recordings to
+    pub screenshot_dir: String,
+    /// File the last geometry of windows is saved to and restored from, disabled if unset
+    pub session_file: String,
+    pub fling_speed: u32,
+    pub fling_duration: u64,
+    pub unfocused_opacity: u8,
+    pub opacity_duration: u64,
+    /// Animate windows opening, closing, and minimizing
+    pub window_animations: bool,
+    /// Turn off all animations, fades and cursor shaking at once, and ask clients to do the same
+    pub reduce_motion: bool,
+    /// Show the performance HUD from startup, it is still toggled with the `perf_hud` key
+    pub perf_hud: bool,
+    pub blur_radius: i32,
+    /// Copy a window covering a whole display straight to it, instead of only a window covering
+    /// every display
+    pub bypass: bool,
+    /// Raise windows clicked inside, otherwise they are only focused and Win+click or Win+Up
+    /// raises them
+    pub raise_on_click: bool,
+    /// How keyboard focus follows the pointer, `click` or `follow`
+    pub focus_mode: String,
+    /// Milliseconds the pointer rests in a window before it takes focus in the follow mode
+    pub focus_delay: u64,
+    /// Move the pointer to the middle of a window focused with the keyboard, by the window
+    /// switcher or a workspace key
+    pub warp_pointer: bool,
+    pub decoration_style: String,
+    pub window_policy: String,
+    /// Where windows without a position open, `cascade`, `center`, or `smart` by default
+    pub window_placement: String,
+    pub touch_mode: String,
+    /// Edge of the first display the task bar is drawn along, `top` or `bottom`, none if unset
+    pub taskbar: String,
+    pub touch_title_height: i32,
+    pub shadow_size: i32,
+    pub shake_cursor_scale: i32,
+    pub shake_duration: u64,
+    pub input_dir: String,
+    pub input_scan_interval: u64,
+    pub kiosk_title: String,
+    pub kiosk_others: String,
+    pub kiosk_cursor: String,
+    /// Areas of the screen where presses are ignored, as `x,y,w,h`
+    pub dead_zone: Vec<String>,
+    /// Extra seats with their own cursor and focus, as `name:device,device`, see `Seat`
+    pub seat: Vec<String>,
+    /// Invert scrolling, see `ScrollDirection`
+    pub natural_scrolling: bool,
+    /// Natural scrolling for a device, as `name:true` or `name:false`
+    pub natural_scrolling_device: Vec<String>,
+    /// Action names and key combinations from the `[keys]` section
+    pub keys: Vec<(String, String)>,
+    /// Color names and values from the `[theme]` section
+    pub theme: Vec<(String, String)>,
+    /// Colors of the light and dark themes, from the `[theme_light]` and `[theme_dark]` sections
+    pub theme_light: Vec<(String, String)>,
+    pub theme_dark: Vec<(String, String)>,
+    /// Switcher action names and gamepad buttons from the `[gamepad]` section
+    pub gamepad: Vec<(String, String)>,
+    /// Window titles and the actions applied when they open, from the `[rules]` section
+    pub rules: Vec<(String, String)>,
 }
 
 impl Config {
@@ -22,30 +131,165 @@ impl Config {
             Err(err) => println!("orbital: failed to open config '{}': {}", path, err)
         }
 
-        Config::from_str(&string)
+        let mut config = Config::from_str(&string);
+        config.path = path.to_string();
+        config
     }
 
     pub fn from_str(string: &str) -> Config {
         let mut config = Config {
+            path: String::new(),
             background: Vec::new(),
             background_mode: String::new(),
+            background_light: Vec::new(),
+            background_dark: Vec::new(),
+            light_time: String::new(),
+            dark_time: String::new(),
+            utc_offset: String::new(),
             cursor: String::new(),
+            cursor_horizontal: String::new(),
+            cursor_vertical: String::new(),
+            cursor_diagonal: String::new(),
+            cursor_antidiagonal: String::new(),
+            cursor_move: String::new(),
+            cursor_busy: String::new(),
             window_close: String::new(),
             window_close_unfocused: String::new(),
             window_minimize: String::new(),
+            window_maximize: String::new(),
+            fallback_images: true,
+            window_close_mode: String::new(),
+            window_close_timeout: 500,
+            focus_after_close: String::new(),
+            desktop_scroll: String::new(),
+            title_scroll: "stacked".to_string(),
+            title_middle_click: "lower".to_string(),
+            title_right_click: "none".to_string(),
+            win_middle_click: "none".to_string(),
+            quality_policy: String::new(),
+            frame_rate: 0,
+            frame_budget: 16,
+            text_cache_size: 1024,
+            hooks: Vec::new(),
+            hook_notify: String::new(),
+            idle_timeout: 0,
+            dim_duration: 0,
+            notification_timeout: 5000,
+            screenshot_dir: "/tmp".to_string(),
+            session_file: String::new(),
+            fling_speed: 2000,
+            fling_duration: 150,
+            unfocused_opacity: 255,
+            opacity_duration: 150,
+            window_animations: true,
+            reduce_motion: false,
+            perf_hud: false,
+            blur_radius: 0,
+            bypass: true,
+            raise_on_click: true,
+            focus_mode: String::new(),
+            focus_delay: 200,
+            warp_pointer: false,
+            decoration_style: String::new(),
+            window_policy: String::new(),
+            window_placement: String::new(),
+            touch_mode: String::new(),
+            taskbar: String::new(),
+            touch_title_height: 40,
+            shadow_size: 16,
+            shake_cursor_scale: 3,
+            shake_duration: 1000,
+            input_dir: String::new(),
+            input_scan_interval: 1000,
+            kiosk_title: String::new(),
+            kiosk_others: String::new(),
+            kiosk_cursor: String::new(),
+            dead_zone: Vec::new(),
+            seat: Vec::new(),
+            natural_scrolling: false,
+            natural_scrolling_device: Vec::new(),
+            keys: Vec::new(),
+            theme: Vec::new(),
+            theme_light: Vec::new(),
+            theme_dark: Vec::new(),
+            gamepad: Vec::new(),
+            rules: Vec::new(),
         };
 
+        let mut section = String::new();
         for line_original in string.lines() {
             let line = line_original.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1 .. line.len() - 1].to_string();
+                continue;
+            }
+            if section == "keys" || section == "theme" || section == "theme_light" || section == "theme_dark"
+            || section == "gamepad" || section == "rules" {
+                let mut parts = line.splitn(2, '=');
+                let name = parts.next().unwrap_or("").trim().to_string();
+                let value = parts.next().unwrap_or("").trim().to_string();
+                if ! name.is_empty() {
+                    if section == "keys" {
+                        config.keys.push((name, value));
+                    } else if section == "gamepad" {
+                        config.gamepad.push((name, value));
+                    } else if section == "rules" {
+                        config.rules.push((name, value));
+                    } else if section == "theme_light" {
+                        config.theme_light.push((name, value));
+                    } else if section == "theme_dark" {
+                        config.theme_dark.push((name, value));
+                    } else {
+                        config.theme.push((name, value));
+                    }
+                }
+                continue;
+            }
             if line.starts_with("background=") {
                 config.background.push(line[11..].to_string());
             }
             if line.starts_with("background_mode=") {
                 config.background_mode = line[16..].to_string();
             }
+            if line.starts_with("background_light=") {
+                config.background_light.push(line[17..].to_string());
+            }
+            if line.starts_with("background_dark=") {
+                config.background_dark.push(line[16..].to_string());
+            }
+            if line.starts_with("light_time=") {
+                config.light_time = line[11..].to_string();
+            }
+            if line.starts_with("dark_time=") {
+                config.dark_time = line[10..].to_string();
+            }
+            if line.starts_with("utc_offset=") {
+                config.utc_offset = line[11..].to_string();
+            }
             if line.starts_with("cursor=") {
                 config.cursor = line[7..].to_string();
             }
+            if line.starts_with("cursor_horizontal=") {
+                config.cursor_horizontal = line[18..].to_string();
+            }
+            if line.starts_with("cursor_vertical=") {
+                config.cursor_vertical = line[16..].to_string();
+            }
+            if line.starts_with("cursor_diagonal=") {
+                config.cursor_diagonal = line[16..].to_string();
+            }
+            if line.starts_with("cursor_antidiagonal=") {
+                config.cursor_antidiagonal = line[20..].to_string();
+            }
+            if line.starts_with("cursor_move=") {
+                config.cursor_move = line[12..].to_string();
+            }
+            if line.starts_with("cursor_busy=") {
+                config.cursor_busy = line[12..].to_string();
+            }
             if line.starts_with("window_close=") {
                 config.window_close = line[13..].to_string();
             }
@@ -55,6 +299,165 @@ impl Config {
             if line.starts_with("window_minimize=") {
                 config.window_minimize = line[16..].to_string();
             }
+            if line.starts_with("window_maximize=") {
+                config.window_maximize = line[16..].to_string();
+            }
+            if line.starts_with("fallback_images=") {
+                config.fallback_images = line[16..].parse::<bool>().unwrap_or(config.fallback_images);
+            }
+            if line.starts_with("window_close_mode=") {
+                config.window_close_mode = line[18..].to_string();
+            }
+            if line.starts_with("window_close_timeout=") {
+                config.window_close_timeout = line[21..].parse::<u64>().unwrap_or(config.window_close_timeout);
+            }
+            if line.starts_with("focus_after_close=") {
+                config.focus_after_close = line[18..].to_string();
+            }
+            if line.starts_with("desktop_scroll=") {
+                config.desktop_scroll = line[15..].to_string();
+            }
+            if line.starts_with("title_scroll=") {
+                config.title_scroll = line[13..].to_string();
+            }
+            if line.starts_with("title_middle_click=") {
+                config.title_middle_click = line[19..].to_string();
+            }
+            if line.starts_with("title_right_click=") {
+                config.title_right_click = line[18..].to_string();
+            }
+            if line.starts_with("win_middle_click=") {
+                config.win_middle_click = line[17..].to_string();
+            }
+            if line.starts_with("frame_rate=") {
+                config.frame_rate = line[11..].parse::<u32>().unwrap_or(config.frame_rate);
+            }
+            if line.starts_with("quality_policy=") {
+                config.quality_policy = line[15..].to_string();
+            }
+            if line.starts_with("frame_budget=") {
+                config.frame_budget = line[13..].parse::<u64>().unwrap_or(config.frame_budget);
+            }
+            if line.starts_with("text_cache_size=") {
+                config.text_cache_size = line[16..].parse::<usize>().unwrap_or(config.text_cache_size);
+            }
+            if line.starts_with("hook=") {
+                let mut parts = line[5..].splitn(2, ' ');
+                let action = parts.next().unwrap_or("").to_string();
+                let command = parts.next().unwrap_or("").trim().to_string();
+                config.hooks.push((action, command));
+            }
+            if line.starts_with("hook_notify=") {
+                config.hook_notify = line[12..].to_string();
+            }
+            if line.starts_with("idle_timeout=") {
+                config.idle_timeout = line[13..].parse::<u64>().unwrap_or(config.idle_timeout);
+            }
+            if line.starts_with("dim_duration=") {
+                config.dim_duration = line[13..].parse::<u64>().unwrap_or(config.dim_duration);
+            }
+            if line.starts_with("notification_timeout=") {
+                config.notification_timeout = line[21..].parse::<u64>().unwrap_or(config.notification_timeout);
+            }
+            if line.starts_with("screenshot_dir=") {
+                config.screenshot_dir = line[15..].to_string();
+            }
+            if line.starts_with("session_file=") {
+                config.session_file = line[13..].to_string();
+            }
+            if line.starts_with("fling_speed=") {
+                config.fling_speed = line[12..].parse::<u32>().unwrap_or(config.fling_speed);
+            }
+            if line.starts_with("fling_duration=") {
+                config.fling_duration = line[15..].parse::<u64>().unwrap_or(config.fling_duration);
+            }
+            if line.starts_with("unfocused_opacity=") {
+                config.unfocused_opacity = line[18..].parse::<u8>().unwrap_or(config.unfocused_opacity);
+            }
+            if line.starts_with("opacity_duration=") {
+                config.opacity_duration = line[17..].parse::<u64>().unwrap_or(config.opacity_duration);
+            }
+            if line.starts_with("window_animations=") {
+                config.window_animations = line[18..].parse::<bool>().unwrap_or(config.window_animations);
+            }
+            if line.starts_with("reduce_motion=") {
+                config.reduce_motion = line[14..].parse::<bool>().unwrap_or(config.reduce_motion);
+            }
+            if line.starts_with("perf_hud=") {
+                config.perf_hud = line[9..].parse::<bool>().unwrap_or(config.perf_hud);
+            }
+            if line.starts_with("blur_radius=") {
+                config.blur_radius = line[12..].parse::<i32>().unwrap_or(config.blur_radius);
+            }
+            if line.starts_with("decoration_style=") {
+                config.decoration_style = line[17..].to_string();
+            }
+            if line.starts_with("window_policy=") {
+                config.window_policy = line[14..].to_string();
+            }
+            if line.starts_with("window_placement=") {
+                config.window_placement = line[17..].to_string();
+            }
+            if line.starts_with("raise_on_click=") {
+                config.raise_on_click = line[15..].parse::<bool>().unwrap_or(config.raise_on_click);
+            }
+            if line.starts_with("focus_mode=") {
+                config.focus_mode = line[11..].to_string();
+            }
+            if line.starts_with("focus_delay=") {
+                config.focus_delay = line[12..].parse::<u64>().unwrap_or(config.focus_delay);
+            }
+            if line.starts_with("warp_pointer=") {
+                config.warp_pointer = line[13..].parse::<bool>().unwrap_or(config.warp_pointer);
+            }
+            if line.starts_with("bypass=") {
+                config.bypass = line[7..].parse::<bool>().unwrap_or(config.bypass);
+            }
+            if line.starts_with("taskbar=") {
+                config.taskbar = line[8..].to_string();
+            }
+            if line.starts_with("touch_mode=") {
+                config.touch_mode = line[11..].to_string();
+            }
+            if line.starts_with("touch_title_height=") {
+                config.touch_title_height = line[19..].parse::<i32>().unwrap_or(config.touch_title_height);
+            }
+            if line.starts_with("shadow_size=") {
+                config.shadow_size = line[12..].parse::<i32>().unwrap_or(config.shadow_size);
+            }
+            if line.starts_with("shake_cursor_scale=") {
+                config.shake_cursor_scale = line[19..].parse::<i32>().unwrap_or(config.shake_cursor_scale);
+            }
+            if line.starts_with("shake_duration=") {
+                config.shake_duration = line[15..].parse::<u64>().unwrap_or(config.shake_duration);
+            }
+            if line.starts_with("input_dir=") {
+                config.input_dir = line[10..].to_string();
+            }
+            if line.starts_with("input_scan_interval=") {
+                config.input_scan_interval = line[20..].parse::<u64>().unwrap_or(config.input_scan_interval);
+            }
+            if line.starts_with("kiosk_title=") {
+                config.kiosk_title = line[12..].to_string();
+            }
+            if line.starts_with("kiosk_others=") {
+                config.kiosk_others = line[13..].to_string();
+            }
+            if line.starts_with("kiosk_cursor=") {
+                config.kiosk_cursor = line[13..].to_string();
+            }
+            if line.starts_with("dead_zone=") {
+                config.dead_zone.push(line[10..].to_string());
+            }
+            if line.starts_with("seat=") {
+                config.seat.push(line[5..].to_string());
+            }
+            if line.starts_with("natural_scrolling=") {
+                config.natural_scrolling = line[18..].parse::<bool>().unwrap_or(config.natural_scrolling);
+            }
+            if line.starts_with("natural_scrolling_device=") {
+                config.natural_scrolling_device.push(line[25..].to_string());
+            }
         }
 
         config
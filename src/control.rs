@@ -0,0 +1,49 @@
+use std::cmp::min;
+
+/// A handle to a report endpoint, opened with `orbital:control`, `orbital:status` or `orbital:screenshot`
+///
+/// Reading `orbital:control` returns a report of compositor state, including the window picked with
+/// the Win+I inspector. Writing `reload` to it reads the config again, writing `record` starts or
+/// stops recording the screen, writing `preview` shows a window preview, as described for
+/// `Preview`, writing `startup` announces an app being launched, as described for `Startup`,
+/// writing `layout export <path>` or `layout apply <path>` saves or restores the window
+/// arrangement, as described for `Layout`, writing `theme light`, `theme dark` or `theme auto`
+/// picks a theme variant, as described for `ThemeSchedule`, all of which only root may do except
+/// `preview` and `startup`, and writing
+/// `natural_scrolling` changes the scroll direction, as described for `ScrollDirection`. Reading
+/// `orbital:status` returns the startup phase and health. Reading `orbital:screenshot`, which only
+/// root may open, returns a PNG of the screen, or of the focused window with
+/// `orbital:screenshot/window`. Reading `orbital:contents/<window id>`, which only root may open,
+/// returns the current contents of any window in the format of `capture::raw`. Commands written to
+/// `orbital:manage`, which only root may open, focus, minimize, close or move any window.
+pub struct Control {
+    /// Path the handle was opened with, without the scheme
+    pub path: &'static str,
+    /// User that opened the handle
+    pub uid: u32,
+    data: Vec<u8>,
+    offset: usize,
+}
+
+impl Control {
+    pub fn new(path: &'static str, uid: u32, report: String) -> Control {
+        Control::from_data(path, uid, report.into_bytes())
+    }
+
+    pub fn from_data(path: &'static str, uid: u32, data: Vec<u8>) -> Control {
+        Control {
+            path: path,
+            uid: uid,
+            data: data,
+            offset: 0
+        }
+    }
+
+    /// Read the report captured when the handle was opened
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let count = min(buf.len(), self.data.len() - self.offset);
+        buf[..count].copy_from_slice(&self.data[self.offset .. self.offset + count]);
+        self.offset += count;
+        count
+    }
+}
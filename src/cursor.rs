@@ -0,0 +1,73 @@
+use config::Config;
+use fallback;
+use image::Image;
+
+/// Shape of the cursor, chosen by the window border under it, what is being dragged, or apps
+/// being launched
+#[derive(Clone, Copy, PartialEq)]
+pub enum CursorShape {
+    Default,
+    /// Left and right borders
+    Horizontal,
+    /// Top and bottom borders
+    Vertical,
+    /// Top left and bottom right corners
+    Diagonal,
+    /// Top right and bottom left corners
+    Antidiagonal,
+    /// Dragging a title bar
+    Move,
+    /// Waiting for a launched app to open its window
+    Busy,
+}
+
+/// Cursor images for each shape other than the default, from `cursor_*` in the config
+///
+/// Shaped cursors are centered on the pointer, unlike the default cursor which points from its
+/// top left corner. A shape without an image shows the default cursor, except the busy cursor,
+/// which has a built-in hourglass like the other theme images.
+pub struct Cursors {
+    horizontal: Image,
+    vertical: Image,
+    diagonal: Image,
+    antidiagonal: Image,
+    moving: Image,
+    busy: Image,
+}
+
+impl Cursors {
+    pub fn new(config: &Config) -> Cursors {
+        Cursors {
+            horizontal: Image::from_path(&config.cursor_horizontal).unwrap_or(Image::new(0, 0)),
+            vertical: Image::from_path(&config.cursor_vertical).unwrap_or(Image::new(0, 0)),
+            diagonal: Image::from_path(&config.cursor_diagonal).unwrap_or(Image::new(0, 0)),
+            antidiagonal: Image::from_path(&config.cursor_antidiagonal).unwrap_or(Image::new(0, 0)),
+            moving: Image::from_path(&config.cursor_move).unwrap_or(Image::new(0, 0)),
+            busy: fallback::load(&config.cursor_busy, "busy cursor", config.fallback_images, fallback::busy)
+        }
+    }
+
+    /// Image of a shape, or None to show the default cursor
+    pub fn get(&self, shape: CursorShape) -> Option<&Image> {
+        let image = match shape {
+            CursorShape::Default => return None,
+            CursorShape::Horizontal => &self.horizontal,
+            CursorShape::Vertical => &self.vertical,
+            CursorShape::Diagonal => &self.diagonal,
+            CursorShape::Antidiagonal => &self.antidiagonal,
+            CursorShape::Move => &self.moving,
+            CursorShape::Busy => &self.busy
+        };
+        if image.width() > 0 && image.height() > 0 {
+            Some(image)
+        } else {
+            None
+        }
+    }
+
+    /// Bytes used by the images
+    pub fn bytes(&self) -> usize {
+        self.horizontal.bytes() + self.vertical.bytes() + self.diagonal.bytes()
+        + self.antidiagonal.bytes() + self.moving.bytes() + self.busy.bytes()
+    }
+}
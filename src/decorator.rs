@@ -0,0 +1,156 @@
+use orbclient::Color;
+use std::cmp::min;
+
+use image::{Image, ImageRef};
+use rect::Rect;
+use window::{Decorations, Window};
+
+/// Images of the title bar buttons
+pub struct Buttons<'a> {
+    pub close: &'a Image,
+    pub maximize: &'a Image,
+    pub minimize: &'a Image,
+}
+
+/// Draws the title bar of windows, selected with `decoration_style` in the config
+pub trait Decorator {
+    /// Sizes of the title bar, buttons and resize borders
+    fn decorations(&self, touch: bool, touch_title: i32) -> Decorations {
+        Decorations::new(touch, touch_title)
+    }
+
+    /// Draw the part of a window's title bar inside a rect
+    fn draw_title(&self, window: &Window, image: &mut ImageRef, rect: &Rect, focused: bool, buttons: &Buttons, close_armed: bool);
+}
+
+/// Select a decorator by name, using the default look for unknown names
+pub fn from_str(string: &str) -> Box<Decorator + Send> {
+    match string {
+        "flat" => Box::new(FlatDecorator),
+        "compact" => Box::new(CompactDecorator),
+        "classic" => Box::new(ClassicDecorator),
+        _ => Box::new(DefaultDecorator)
+    }
+}
+
+fn fill(image: &mut ImageRef, rect: &Rect, color: Color) {
+    if ! rect.is_empty() {
+        image.roi_mut(rect).fill(color);
+    }
+}
+
+/// Draw a button image centered in a title bar button rect
+fn draw_button(window: &Window, image: &mut ImageRef, rect: &Rect, i: i32, button_image: &Image, armed: bool) {
+    let button_rect = window.button_rect(i);
+    if button_rect.width() < window.decorations.button {
+        return;
+    }
+
+    let image_rect = Rect::new(button_rect.left() + (button_rect.width() - button_image.width())/2,
+                               button_rect.top() + (button_rect.height() - button_image.height())/2,
+                               button_image.width(), button_image.height());
+    let image_intersect = rect.intersection(&image_rect);
+    if ! image_intersect.is_empty() {
+        if armed {
+            fill(image, &image_intersect, window.theme.close_armed);
+        }
+        image.roi_mut(&image_intersect).blend(&button_image.roi(&image_intersect.offset(-image_rect.left(), -image_rect.top())));
+    }
+}
+
+/// Draw the title text and buttons over a filled title bar
+fn draw_contents(window: &Window, image: &mut ImageRef, rect: &Rect, focused: bool, buttons: &Buttons, close_armed: bool) {
+    let title_rect = window.title_rect();
+
+    let x = window.x + 6;
+    let w = window.button_rect(2).left() - x;
+    if w > 0 {
+        let title_image = window.title_image(focused);
+        let image_rect = Rect::new(x, title_rect.top() + (title_rect.height() - title_image.height())/2,
+                                   min(w, title_image.width()), title_image.height());
+        let image_intersect = rect.intersection(&image_rect);
+        if ! image_intersect.is_empty() {
+            image.roi_mut(&image_intersect).blend(&title_image.roi(&image_intersect.offset(-image_rect.left(), -image_rect.top())));
+        }
+    }
+
+    draw_button(window, image, rect, 0, buttons.close, close_armed);
+    if window.resizable {
+        draw_button(window, image, rect, 1, buttons.maximize, false);
+    }
+    draw_button(window, image, rect, 2, buttons.minimize, false);
+
+    if let Some(ref attention) = window.attention {
+        image.roi_mut(&rect.intersection(&title_rect)).tint(window.theme.attention, (attention.pulse() * 160.0) as u8);
+    }
+}
+
+/// The standard look, with the focused title bar highlighted
+pub struct DefaultDecorator;
+
+impl Decorator for DefaultDecorator {
+    fn draw_title(&self, window: &Window, image: &mut ImageRef, rect: &Rect, focused: bool, buttons: &Buttons, close_armed: bool) {
+        let title_intersect = rect.intersection(&window.title_rect());
+        if ! title_intersect.is_empty() {
+            fill(image, &title_intersect, if focused { window.theme.bar_highlight } else { window.theme.bar });
+            draw_contents(window, image, rect, focused, buttons, close_armed);
+        }
+    }
+}
+
+/// Title bars of one color, with a line under the focused one
+pub struct FlatDecorator;
+
+impl Decorator for FlatDecorator {
+    fn draw_title(&self, window: &Window, image: &mut ImageRef, rect: &Rect, focused: bool, buttons: &Buttons, close_armed: bool) {
+        let title_rect = window.title_rect();
+        let title_intersect = rect.intersection(&title_rect);
+        if ! title_intersect.is_empty() {
+            fill(image, &title_intersect, window.theme.bar);
+            draw_contents(window, image, rect, false, buttons, close_armed);
+            if focused {
+                let line_rect = Rect::new(title_rect.left(), title_rect.bottom() - 2, title_rect.width(), 2);
+                fill(image, &title_intersect.intersection(&line_rect), window.theme.text_highlight);
+            }
+        }
+    }
+}
+
+/// The standard look with smaller title bars and borders, except in touch mode
+pub struct CompactDecorator;
+
+impl Decorator for CompactDecorator {
+    fn decorations(&self, touch: bool, touch_title: i32) -> Decorations {
+        if touch {
+            Decorations::new(touch, touch_title)
+        } else {
+            Decorations {
+                title: 20,
+                button: 14,
+                border: 6
+            }
+        }
+    }
+
+    fn draw_title(&self, window: &Window, image: &mut ImageRef, rect: &Rect, focused: bool, buttons: &Buttons, close_armed: bool) {
+        DefaultDecorator.draw_title(window, image, rect, focused, buttons, close_armed);
+    }
+}
+
+/// Raised title bars with a light top edge and a dark bottom edge
+pub struct ClassicDecorator;
+
+impl Decorator for ClassicDecorator {
+    fn draw_title(&self, window: &Window, image: &mut ImageRef, rect: &Rect, focused: bool, buttons: &Buttons, close_armed: bool) {
+        let title_rect = window.title_rect();
+        let title_intersect = rect.intersection(&title_rect);
+        if ! title_intersect.is_empty() {
+            DefaultDecorator.draw_title(window, image, rect, focused, buttons, close_armed);
+
+            let top_rect = Rect::new(title_rect.left(), title_rect.top(), title_rect.width(), 1);
+            fill(image, &title_intersect.intersection(&top_rect), window.theme.text);
+            let bottom_rect = Rect::new(title_rect.left(), title_rect.bottom() - 1, title_rect.width(), 1);
+            fill(image, &title_intersect.intersection(&bottom_rect), window.theme.background);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use orbclient::Color;
+use std::io::{Error, Result};
+use std::os::unix::io::AsRawFd;
+use std::slice;
+use std::sync::Arc;
+use syscall;
+
+use image::{ImageRef, ImageRoi};
+use rect::{Rect, Region};
+use socket::Socket;
+
+/// An area of a display to push to the screen, written to the display handle for a partial sync
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct SyncRect {
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+}
+
+/// A display framebuffer, placed in the coordinate space shared by all displays
+pub struct Display {
+    pub x: i32,
+    pub y: i32,
+    pub socket: Arc<Socket>,
+    image: ImageRef<'static>,
+    ptr: usize,
+    /// Areas updated since the last sync, relative to the display
+    damage: Region,
+    /// Does the driver accept partial syncs, asked once when the display is opened
+    partial_sync: bool,
+}
+
+impl Display {
+    /// Open a display and map its framebuffer, placing its top left corner at x, y
+    pub fn open(path: &str, x: i32, y: i32) -> Result<Display> {
+        let socket = Arc::new(try!(Socket::open(path)));
+
+        let mut buf: [u8; 4096] = [0; 4096];
+        let count = try!(syscall::fpath(socket.as_raw_fd() as usize, &mut buf).map_err(|err| Error::from_raw_os_error(err.errno)));
+        let path = unsafe { String::from_utf8_unchecked(Vec::from(&buf[..count])) };
+        let res = path.split(":").nth(1).unwrap_or("");
+        let width = res.split("/").nth(1).unwrap_or("").parse::<i32>().unwrap_or(0);
+        let height = res.split("/").nth(2).unwrap_or("").parse::<i32>().unwrap_or(0);
+
+        println!("orbital: found display {}x{} at {},{}", width, height, x, y);
+
+        let ptr = try!(unsafe { syscall::fmap(socket.as_raw_fd(), 0, (width * height * 4) as usize) }.map_err(|err| Error::from_raw_os_error(err.errno)));
+        let data = unsafe { slice::from_raw_parts_mut(ptr as *mut Color, (width * height) as usize) };
+        println!("orbital: mapped display to {:X}", ptr);
+
+        // Drivers that support partial syncs accept an empty area, which syncs nothing
+        let partial_sync = socket.send_type(&[SyncRect { x: 0, y: 0, w: 0, h: 0 }]).is_ok();
+        if ! partial_sync {
+            println!("orbital: display does not support partial sync, syncing all of it");
+        }
+
+        Ok(Display {
+            x: x,
+            y: y,
+            socket: socket,
+            image: ImageRef::from_data(x, y, width, height, data),
+            ptr: ptr,
+            damage: Region::new(),
+            partial_sync: partial_sync
+        })
+    }
+
+    pub fn width(&self) -> i32 {
+        self.image.width()
+    }
+
+    pub fn height(&self) -> i32 {
+        self.image.height()
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width(), self.height())
+    }
+
+    /// The framebuffer, placed at the position of the display, for compositing straight onto it
+    pub fn image_mut(&mut self) -> &mut ImageRef<'static> {
+        &mut self.image
+    }
+
+    pub fn roi(&self, rect: &Rect) -> ImageRoi {
+        self.image.roi(rect)
+    }
+
+    /// Mark an area drawn since the last sync to be pushed to the screen, darkening it by `dim`
+    /// out of 255, so areas marked in one frame must not overlap
+    pub fn damage(&mut self, rect: &Rect, dim: u8) {
+        let intersect = rect.intersection(&self.rect());
+        if ! intersect.is_empty() {
+            if dim > 0 {
+                self.image.roi_mut(&intersect).tint(Color::rgb(0, 0, 0), dim);
+            }
+            self.damage.add(intersect.offset(-self.x, -self.y));
+        }
+    }
+
+    /// Push the areas updated since the last sync to the screen
+    ///
+    /// Only the updated areas are written to the display handle, as `SyncRect`s, or the whole
+    /// framebuffer is synced for drivers that do not support it.
+    pub fn sync(&mut self) {
+        let rects = self.damage.take();
+        if rects.is_empty() {
+            return;
+        }
+
+        if self.partial_sync {
+            let sync_rects: Vec<SyncRect> = rects.iter().map(|rect| SyncRect {
+                x: rect.left(),
+                y: rect.top(),
+                w: rect.width(),
+                h: rect.height()
+            }).collect();
+            if let Err(err) = self.socket.send_type(&sync_rects) {
+                println!("orbital: failed to sync display: {}", err);
+            }
+        } else {
+            self.socket.sync().unwrap();
+        }
+    }
+}
+
+impl Drop for Display {
+    fn drop(&mut self) {
+        unsafe { let _ = syscall::funmap(self.ptr); }
+    }
+}
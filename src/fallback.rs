@@ -0,0 +1,152 @@
+use orbclient::Color;
+
+use image::Image;
+
+/// Arrow pointing from its top left corner, `#` for the outline and `.` for the fill
+const CURSOR: [&'static str; 18] = [
+    "#",
+    "##",
+    "#.#",
+    "#..#",
+    "#...#",
+    "#....#",
+    "#.....#",
+    "#......#",
+    "#.......#",
+    "#........#",
+    "#.........#",
+    "#......####",
+    "#...#..#",
+    "#..# #..#",
+    "#.#  #..#",
+    "##    #..#",
+    "#     #..#",
+    "       ##",
+];
+
+const CLOSE: [&'static str; 12] = [
+    "            ",
+    " ##      ## ",
+    " #.#    #.# ",
+    "  #.#  #.#  ",
+    "   #.##.#   ",
+    "    #..#    ",
+    "    #..#    ",
+    "   #.##.#   ",
+    "  #.#  #.#  ",
+    " #.#    #.# ",
+    " ##      ## ",
+    "            ",
+];
+
+const MAXIMIZE: [&'static str; 12] = [
+    "            ",
+    " ########## ",
+    " #........# ",
+    " #.######.# ",
+    " #.#    #.# ",
+    " #.#    #.# ",
+    " #.#    #.# ",
+    " #.#    #.# ",
+    " #.######.# ",
+    " #........# ",
+    " ########## ",
+    "            ",
+];
+
+const MINIMIZE: [&'static str; 12] = [
+    "            ",
+    "            ",
+    "            ",
+    "            ",
+    "            ",
+    "            ",
+    "            ",
+    " ########## ",
+    " #........# ",
+    " ########## ",
+    "            ",
+    "            ",
+];
+
+/// Hourglass shown while a launched app starts, centered on the pointer
+const BUSY: [&'static str; 15] = [
+    "###########",
+    "#.........#",
+    " #.......# ",
+    " #.......# ",
+    "  #.....#  ",
+    "   #...#   ",
+    "    #.#    ",
+    "     #     ",
+    "    #.#    ",
+    "   #...#   ",
+    "  #.....#  ",
+    " #.......# ",
+    " #.......# ",
+    "#.........#",
+    "###########",
+];
+
+/// Draw a pattern, with `fill` for `.`, black for `#`, and transparent elsewhere
+fn from_pattern(pattern: &[&str], fill: Color) -> Image {
+    let width = pattern.iter().map(|row| row.len()).max().unwrap_or(0);
+    let height = pattern.len();
+    let mut data = vec![Color::rgba(0, 0, 0, 0); width * height];
+    for (y, row) in pattern.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            data[y * width + x] = match c {
+                '#' => Color::rgb(0, 0, 0),
+                '.' => fill,
+                _ => Color::rgba(0, 0, 0, 0)
+            };
+        }
+    }
+    Image::from_data(width as i32, height as i32, data.into_boxed_slice())
+}
+
+pub fn cursor() -> Image {
+    from_pattern(&CURSOR, Color::rgb(255, 255, 255))
+}
+
+pub fn close() -> Image {
+    from_pattern(&CLOSE, Color::rgb(255, 255, 255))
+}
+
+/// The close button of unfocused windows, which is dimmer
+pub fn close_unfocused() -> Image {
+    from_pattern(&CLOSE, Color::rgb(160, 160, 160))
+}
+
+pub fn maximize() -> Image {
+    from_pattern(&MAXIMIZE, Color::rgb(255, 255, 255))
+}
+
+pub fn minimize() -> Image {
+    from_pattern(&MINIMIZE, Color::rgb(255, 255, 255))
+}
+
+pub fn busy() -> Image {
+    from_pattern(&BUSY, Color::rgb(255, 255, 255))
+}
+
+/// Load a theme image from the config, or the built-in image if it can not be loaded and
+/// `fallback_images` is on, logging which image fell back
+///
+/// Without the fallback a missing image is empty, leaving the cursor or button invisible.
+pub fn load(path: &str, name: &str, fallback: bool, builtin: fn() -> Image) -> Image {
+    let image = if path.is_empty() { None } else { Image::from_path(path) };
+    match image {
+        Some(image) if image.width() > 0 && image.height() > 0 => image,
+        _ => if fallback {
+            if path.is_empty() {
+                println!("orbital: no {} image set, using the built-in image", name);
+            } else {
+                println!("orbital: failed to load {} image '{}', using the built-in image", name, path);
+            }
+            builtin()
+        } else {
+            Image::new(0, 0)
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::cmp::min;
+use std::time::{Duration, Instant};
+
+/// Frames per second without `frame_rate`, as displays do not report their refresh rate
+const DEFAULT_FRAME_RATE: u32 = 60;
+/// Highest frame rate allowed, keeping frames at least a millisecond apart
+const MAX_FRAME_RATE: u32 = 1000;
+
+/// Paces compositing to a frame rate, set with `frame_rate`
+///
+/// Frames fall on a fixed grid of intervals, so the time spent compositing does not push the
+/// following frames back. Damage from input and clients accumulates between frames, and frames
+/// that were missed are skipped instead of run late one after another.
+pub struct FrameClock {
+    interval: Duration,
+    next: Instant,
+}
+
+impl FrameClock {
+    pub fn new(rate: u32) -> FrameClock {
+        let rate = if rate == 0 { DEFAULT_FRAME_RATE } else { min(rate, MAX_FRAME_RATE) };
+        let interval = Duration::new(0, 1_000_000_000 / rate);
+        FrameClock {
+            interval: interval,
+            next: Instant::now() + interval
+        }
+    }
+
+    /// Time left until the next frame
+    pub fn until_next(&self) -> Duration {
+        let now = Instant::now();
+        if self.next > now { self.next - now } else { Duration::from_secs(0) }
+    }
+
+    /// Move on to the frame after the one starting now, skipping any that were missed
+    pub fn advance(&mut self) {
+        let now = Instant::now();
+        self.next += self.interval;
+        while self.next <= now {
+            self.next += self.interval;
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use orbclient::Event;
+
+/// Event code of gamepad events, which orbclient does not define
+///
+/// `a` holds the button number, or the axis number with bit 8 set. `b` is 1 for a pressed
+/// button and 0 for a released one, or the position of an axis.
+pub const EVENT_GAMEPAD: i64 = 0x4750;
+
+/// A gamepad button or axis changing
+#[derive(Clone, Copy)]
+pub struct GamepadEvent {
+    pub control: u8,
+    pub axis: bool,
+    pub value: i64,
+}
+
+impl GamepadEvent {
+    pub fn from_event(event: Event) -> GamepadEvent {
+        GamepadEvent {
+            control: event.a as u8,
+            axis: event.a & 0x100 != 0,
+            value: event.b
+        }
+    }
+
+    /// A button that was just pressed
+    pub fn pressed(&self) -> Option<u8> {
+        if ! self.axis && self.value != 0 {
+            Some(self.control)
+        } else {
+            None
+        }
+    }
+}
+
+/// Window switcher control triggered by a gamepad button
+#[derive(Clone, Copy, PartialEq)]
+pub enum GamepadAction {
+    /// Open the window switcher, or select the next window in it
+    SwitchWindow,
+    /// Select the previous window in the switcher
+    SwitchPrev,
+    /// Focus the selected window and close the switcher
+    SwitchSelect,
+    /// Close the switcher without changing focus
+    SwitchCancel,
+}
+
+impl GamepadAction {
+    fn from_str(string: &str) -> Option<GamepadAction> {
+        match string {
+            "switch_window" => Some(GamepadAction::SwitchWindow),
+            "switch_prev" => Some(GamepadAction::SwitchPrev),
+            "switch_select" => Some(GamepadAction::SwitchSelect),
+            "switch_cancel" => Some(GamepadAction::SwitchCancel),
+            _ => None
+        }
+    }
+}
+
+/// Gamepad buttons that control the window switcher, the defaults replaced by any set in the
+/// `[gamepad]` config section. Other buttons go to windows.
+pub struct GamepadBindings {
+    bindings: Vec<(u8, GamepadAction)>,
+}
+
+impl GamepadBindings {
+    pub fn new(buttons: &[(String, String)]) -> GamepadBindings {
+        let defaults = [
+            ("switch_window", 8),
+            ("switch_prev", 4),
+            ("switch_select", 0),
+            ("switch_cancel", 1),
+        ];
+
+        let mut bindings = Vec::new();
+        for &(action_name, button) in defaults.iter() {
+            if buttons.iter().any(|&(ref name, _)| name == action_name) {
+                continue;
+            }
+            if let Some(action) = GamepadAction::from_str(action_name) {
+                bindings.push((button, action));
+            }
+        }
+
+        for &(ref action_name, ref button_str) in buttons.iter() {
+            match GamepadAction::from_str(action_name) {
+                Some(action) => if button_str == "none" || button_str.is_empty() {
+                    // Disabled
+                } else if let Ok(button) = button_str.parse::<u8>() {
+                    bindings.push((button, action));
+                } else {
+                    println!("orbital: invalid gamepad button '{}' for '{}'", button_str, action_name);
+                },
+                None => println!("orbital: unknown gamepad action '{}'", action_name)
+            }
+        }
+
+        GamepadBindings {
+            bindings: bindings
+        }
+    }
+
+    /// Find the action bound to a button
+    pub fn action(&self, button: u8) -> Option<GamepadAction> {
+        self.bindings.iter().find(|&&(binding, _)| binding == button).map(|&(_, action)| action)
+    }
+}
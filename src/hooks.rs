@@ -0,0 +1,96 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::{Child, Command};
+use std::thread;
+
+/// External commands and notifications triggered by window manager actions
+pub struct Hooks {
+    /// Action name and the command line run when it happens
+    commands: Vec<(String, String)>,
+    /// Path the action name is written to, such as a sound daemon
+    notify: String,
+    /// Commands still running, waited for once they exit
+    children: Vec<Child>,
+}
+
+impl Hooks {
+    pub fn new(commands: &[(String, String)], notify: &str) -> Hooks {
+        Hooks {
+            commands: commands.to_vec(),
+            notify: notify.to_string(),
+            children: Vec::new()
+        }
+    }
+
+    /// Run the hooks for an action, passing the affected window in the environment
+    pub fn run(&mut self, action: &str, window: Option<(usize, &str)>) {
+        let mut env = Vec::new();
+        if let Some((id, title)) = window {
+            env.push(("ORBITAL_WINDOW_ID", format!("{}", id)));
+            env.push(("ORBITAL_WINDOW_TITLE", title.to_string()));
+        }
+        self.run_env(action, &env);
+    }
+
+    /// Run the hooks for an input device being plugged in or removed, passing its name in the environment
+    pub fn run_device(&mut self, action: &str, device: &str) {
+        self.run_env(action, &[("ORBITAL_DEVICE", device.to_string())]);
+    }
+
+    /// Run the hooks for an action, passing extra variables in the environment
+    ///
+    /// Commands are spawned and the notify path written without waiting for them, so slow hooks
+    /// do not block the compositor.
+    pub fn run_env(&mut self, action: &str, env: &[(&str, String)]) {
+        for &(ref hook_action, ref command_line) in self.commands.iter() {
+            if hook_action != action {
+                continue;
+            }
+
+            let mut args = command_line.split_whitespace();
+            if let Some(program) = args.next() {
+                let mut command = Command::new(program);
+                command.args(&args.collect::<Vec<&str>>());
+                command.env("ORBITAL_ACTION", action);
+                for &(key, ref value) in env.iter() {
+                    command.env(key, value);
+                }
+                match command.spawn() {
+                    Ok(child) => self.children.push(child),
+                    Err(err) => println!("orbital: failed to run hook '{}': {}", command_line, err)
+                }
+            }
+        }
+
+        if ! self.notify.is_empty() {
+            // Opening a daemon's path can wait on the daemon, so write from another thread
+            let notify = self.notify.clone();
+            let line = format!("{}\n", action);
+            thread::spawn(move || {
+                match OpenOptions::new().write(true).open(&notify) {
+                    Ok(mut file) => if let Err(err) = file.write(line.as_bytes()) {
+                        println!("orbital: failed to notify '{}': {}", notify, err);
+                    },
+                    Err(err) => println!("orbital: failed to open '{}': {}", notify, err)
+                }
+            });
+        }
+    }
+
+    /// Wait for commands that have exited, so they do not linger as zombies
+    pub fn reap(&mut self) {
+        let mut i = 0;
+        while i < self.children.len() {
+            match self.children[i].try_wait() {
+                Ok(None) => i += 1,
+                Ok(Some(_)) => {
+                    self.children.swap_remove(i);
+                },
+                Err(err) => {
+                    println!("orbital: failed to wait for hook: {}", err);
+                    self.children.swap_remove(i);
+                }
+            }
+        }
+    }
+}
@@ -19,7 +19,7 @@ pub unsafe fn fast_copy(dst: *mut u8, src: *const u8, len: usize) {
 
 pub struct ImageRoiRows<'a> {
     rect: Rect,
-    w: i32,
+    stride: i32,
     data: &'a [Color],
     i: i32,
 }
@@ -28,10 +28,10 @@ impl<'a> Iterator for ImageRoiRows<'a> {
     type Item = &'a [Color];
     fn next(&mut self) -> Option<Self::Item> {
         if self.i < self.rect.height() {
-            let start = (self.rect.top() + self.i) * self.w + self.rect.left();
+            let start = (self.rect.top() + self.i) * self.stride + self.rect.left();
             let end = start + self.rect.width();
             self.i += 1;
-            Some(unsafe { mem::transmute(& self.data[start as usize .. end as usize]) })
+            Some(&self.data[start as usize .. end as usize])
         } else {
             None
         }
@@ -40,7 +40,7 @@ impl<'a> Iterator for ImageRoiRows<'a> {
 
 pub struct ImageRoiRowsMut<'a> {
     rect: Rect,
-    w: i32,
+    stride: i32,
     data: &'a mut [Color],
     i: i32,
 }
@@ -49,7 +49,7 @@ impl<'a> Iterator for ImageRoiRowsMut<'a> {
     type Item = &'a mut [Color];
     fn next(&mut self) -> Option<Self::Item> {
         if self.i < self.rect.height() {
-            let start = (self.rect.top() + self.i) * self.w + self.rect.left();
+            let start = (self.rect.top() + self.i) * self.stride + self.rect.left();
             let end = start + self.rect.width();
             self.i += 1;
             // it does not appear to be possible to do this in safe rust
@@ -60,77 +60,240 @@ impl<'a> Iterator for ImageRoiRowsMut<'a> {
     }
 }
 
+/// Blur a line of pixels with a running box sum, reading from `src` and writing to `dst` at a step
+fn box_blur_line(src: &[Color], dst: &mut [Color], start: usize, step: usize, len: i32, radius: i32) {
+    let pixel = |i: i32| -> u32 {
+        let clamped = if i < 0 { 0 } else if i >= len { len - 1 } else { i };
+        src[start + clamped as usize * step].data
+    };
+
+    let mut r = 0;
+    let mut g = 0;
+    let mut b = 0;
+    for i in -radius .. radius + 1 {
+        let data = pixel(i);
+        r += (data >> 16) & 0xFF;
+        g += (data >> 8) & 0xFF;
+        b += data & 0xFF;
+    }
+
+    let count = (radius * 2 + 1) as u32;
+    for x in 0..len {
+        dst[start + x as usize * step].data = 0xFF000000 | ((r / count) << 16) | ((g / count) << 8) | (b / count);
+
+        let old = pixel(x - radius);
+        let new = pixel(x + radius + 1);
+        r = r + ((new >> 16) & 0xFF) - ((old >> 16) & 0xFF);
+        g = g + ((new >> 8) & 0xFF) - ((old >> 8) & 0xFF);
+        b = b + (new & 0xFF) - (old & 0xFF);
+    }
+}
+
+/// Clip a rect given relative to a view to the view bounds, returning it in image coordinates
+fn clip(view: &Rect, rect: &Rect) -> Rect {
+    let clipped = rect.offset(view.left(), view.top()).intersection(view);
+    if clipped.is_empty() {
+        Rect::new(view.left(), view.top(), 0, 0)
+    } else {
+        clipped
+    }
+}
+
+/// A read only view of a rectangle within an image, rows are addressed using the image stride
+#[derive(Clone, Copy)]
 pub struct ImageRoi<'a> {
     rect: Rect,
-    w: i32,
-    data: &'a mut [Color]
+    stride: i32,
+    data: &'a [Color]
 }
 
 impl<'a> ImageRoi<'a> {
-    pub fn rows(&'a self) -> ImageRoiRows<'a> {
+    /// The view without some columns and rows at its left and top
+    fn skip(&self, columns: i32, rows: i32) -> ImageRoi<'a> {
+        ImageRoi {
+            rect: Rect::new(self.rect.left() + columns, self.rect.top() + rows,
+                            self.rect.width() - columns, self.rect.height() - rows),
+            stride: self.stride,
+            data: self.data
+        }
+    }
+
+    pub fn rows(&self) -> ImageRoiRows<'a> {
         ImageRoiRows {
             rect: self.rect,
-            w: self.w,
+            stride: self.stride,
             data: self.data,
             i: 0
         }
     }
+}
+
+/// A mutable view of a rectangle within an image, rows are addressed using the image stride
+pub struct ImageRoiMut<'a> {
+    rect: Rect,
+    /// Columns and rows clipped off the left and top of the rect asked for, which are skipped in
+    /// views copied onto this one so they stay lined up
+    skip: (i32, i32),
+    stride: i32,
+    data: &'a mut [Color]
+}
+
+impl<'a> ImageRoiMut<'a> {
+    fn new(view: &Rect, rect: &Rect, stride: i32, data: &'a mut [Color]) -> ImageRoiMut<'a> {
+        let clipped = clip(view, rect);
+        ImageRoiMut {
+            rect: clipped,
+            skip: (cmp::max(0, clipped.left() - view.left() - rect.left()), cmp::max(0, clipped.top() - view.top() - rect.top())),
+            stride: stride,
+            data: data
+        }
+    }
 
-    pub fn rows_mut(&'a mut self) -> ImageRoiRowsMut<'a> {
+    /// A view of a rectangle relative to this view, clipped to its bounds
+    pub fn roi(&mut self, rect: &Rect) -> ImageRoiMut {
+        ImageRoiMut::new(&self.rect, rect, self.stride, self.data)
+    }
+
+    pub fn rows_mut(&mut self) -> ImageRoiRowsMut {
         ImageRoiRowsMut {
             rect: self.rect,
-            w: self.w,
+            stride: self.stride,
             data: self.data,
             i: 0
         }
     }
 
-    pub fn blend(&'a mut self, other: &ImageRoi) {
+    pub fn blend(&mut self, other: &ImageRoi) {
+        self.blend_opacity(other, 255);
+    }
+
+    /// Blend another view over this one, scaling its alpha by an opacity
+    pub fn blend_opacity(&mut self, other: &ImageRoi, opacity: u8) {
+        let opacity = opacity as u32;
+        let other = other.skip(self.skip.0, self.skip.1);
         for (mut self_row, other_row) in self.rows_mut().zip(other.rows()) {
             for(mut old, new) in self_row.iter_mut().zip(other_row.iter()) {
-                let alpha = (new.data >> 24) & 0xFF;
-                if alpha >= 255 {
-                    old.data = new.data;
-                } else if alpha > 0 {
-                    let n_r = (((new.data >> 16) & 0xFF) * alpha) >> 8;
-                    let n_g = (((new.data >> 8) & 0xFF) * alpha) >> 8;
-                    let n_b = ((new.data & 0xFF) * alpha) >> 8;
-
-                    let n_alpha = 255 - alpha;
-
-                    let o_r = (((old.data >> 16) & 0xFF) * n_alpha) >> 8;
-                    let o_g = (((old.data >> 8) & 0xFF) * n_alpha) >> 8;
-                    let o_b = ((old.data & 0xFF) * n_alpha) >> 8;
-
-                    old.data = ((o_r << 16) | (o_g << 8) | o_b) + ((n_r << 16) | (n_g << 8) | n_b);
-                }
+                let alpha = if opacity >= 255 {
+                    (new.data >> 24) & 0xFF
+                } else {
+                    (((new.data >> 24) & 0xFF) * opacity) / 255
+                };
+                blend_pixel(old, *new, alpha);
             }
         }
     }
 
-    pub fn blit(&'a mut self, other: &ImageRoi) {
+    /// Blend another view over this one at a constant opacity, ignoring its alpha channel
+    pub fn fade(&mut self, other: &ImageRoi, opacity: u8) {
+        let alpha = opacity as u32;
+        let other = other.skip(self.skip.0, self.skip.1);
+        for (mut self_row, other_row) in self.rows_mut().zip(other.rows()) {
+            for(mut old, new) in self_row.iter_mut().zip(other_row.iter()) {
+                blend_pixel(old, *new, alpha);
+            }
+        }
+    }
+
+    pub fn blit(&mut self, other: &ImageRoi) {
+        let other = other.skip(self.skip.0, self.skip.1);
         for (mut self_row, other_row) in self.rows_mut().zip(other.rows()) {
             let len = cmp::min(self_row.len(), other_row.len());
             unsafe { fast_copy(self_row.as_mut_ptr() as *mut u8, other_row.as_ptr() as *const u8, len * 4); }
         }
     }
+
+    /// Blur the view with a box filter, sampling only pixels inside the view
+    pub fn box_blur(&mut self, radius: i32) {
+        let w = self.rect.width();
+        let h = self.rect.height();
+        if radius <= 0 || w <= 0 || h <= 0 {
+            return;
+        }
+
+        let mut pixels = Vec::with_capacity((w * h) as usize);
+        for row in self.rows_mut() {
+            pixels.extend_from_slice(row);
+        }
+
+        let mut blurred = pixels.clone();
+        for y in 0..h {
+            box_blur_line(&pixels, &mut blurred, (y * w) as usize, 1, w, radius);
+        }
+        for x in 0..w {
+            box_blur_line(&blurred, &mut pixels, x as usize, w as usize, h, radius);
+        }
+
+        for (mut row, blurred_row) in self.rows_mut().zip(pixels.chunks(w as usize)) {
+            row.copy_from_slice(blurred_row);
+        }
+    }
+
+    /// Blend a color over the view at a constant opacity
+    pub fn tint(&mut self, color: Color, opacity: u8) {
+        let alpha = opacity as u32;
+        for mut row in self.rows_mut() {
+            for mut pixel in row.iter_mut() {
+                blend_pixel(pixel, color, alpha);
+            }
+        }
+    }
+
+    /// Fill the view with a color, without blending
+    pub fn fill(&mut self, color: Color) {
+        for mut row in self.rows_mut() {
+            for mut pixel in row.iter_mut() {
+                *pixel = color;
+            }
+        }
+    }
+}
+
+/// Blend a color over a pixel with an alpha from 0 to 255
+pub fn blend_pixel(old: &mut Color, new: Color, alpha: u32) {
+    if alpha >= 255 {
+        old.data = new.data;
+    } else if alpha > 0 {
+        let n_r = (((new.data >> 16) & 0xFF) * alpha) >> 8;
+        let n_g = (((new.data >> 8) & 0xFF) * alpha) >> 8;
+        let n_b = ((new.data & 0xFF) * alpha) >> 8;
+
+        let n_alpha = 255 - alpha;
+
+        let o_r = (((old.data >> 16) & 0xFF) * n_alpha) >> 8;
+        let o_g = (((old.data >> 8) & 0xFF) * n_alpha) >> 8;
+        let o_b = ((old.data & 0xFF) * n_alpha) >> 8;
+
+        old.data = ((o_r << 16) | (o_g << 8) | o_b) + ((n_r << 16) | (n_g << 8) | n_b);
+    }
 }
 
+/// An image in memory owned elsewhere, such as a display framebuffer, placed with its top left
+/// corner at a point, so views of it are asked for in the coordinates it is placed in
 pub struct ImageRef<'a> {
+    x: i32,
+    y: i32,
     w: i32,
     h: i32,
     data: &'a mut [Color]
 }
 
 impl<'a> ImageRef<'a> {
-    pub fn from_data(width: i32, height: i32, data: &'a mut [Color]) -> ImageRef {
+    pub fn from_data(x: i32, y: i32, width: i32, height: i32, data: &'a mut [Color]) -> ImageRef {
         ImageRef {
+            x: x,
+            y: y,
             w: width,
             h: height,
             data: data
         }
     }
 
+    /// An image with no pixels
+    pub fn empty() -> ImageRef<'a> {
+        ImageRef::from_data(0, 0, 0, 0, &mut [])
+    }
+
+    /// Distance between rows in pixels
     pub fn width(&self) -> i32 {
         self.w
     }
@@ -139,38 +302,25 @@ impl<'a> ImageRef<'a> {
         self.h
     }
 
-    pub fn roi(&mut self, rect: &Rect) -> ImageRoi {
-        ImageRoi {
-            rect: *rect,
-            w: self.w,
-            data: self.data
-        }
+    pub fn bounds(&self) -> Rect {
+        Rect::new(self.x, self.y, self.w, self.h)
     }
-}
 
-impl<'a> Renderer for ImageRef<'a> {
-    /// Get the width of the image in pixels
-    fn width(&self) -> u32 {
-        self.w as u32
-    }
-
-    /// Get the height of the image in pixels
-    fn height(&self) -> u32 {
-        self.h as u32
-    }
-
-    /// Return a reference to a slice of colors making up the image
-    fn data(&self) -> &[Color] {
-        &self.data[..]
+    /// Pixels, from the top left corner of the bounds
+    pub fn data_mut(&mut self) -> &mut [Color] {
+        &mut self.data[..]
     }
 
-    /// Return a mutable reference to a slice of colors making up the image
-    fn data_mut(&mut self) -> &mut [Color] {
-        &mut self.data[..]
+    pub fn roi(&self, rect: &Rect) -> ImageRoi {
+        ImageRoi {
+            rect: clip(&Rect::new(0, 0, self.w, self.h), &rect.offset(-self.x, -self.y)),
+            stride: self.w,
+            data: &self.data
+        }
     }
 
-    fn sync(&mut self) -> bool {
-        true
+    pub fn roi_mut(&mut self, rect: &Rect) -> ImageRoiMut {
+        ImageRoiMut::new(&Rect::new(0, 0, self.w, self.h), &rect.offset(-self.x, -self.y), self.w, self.data)
     }
 }
 
@@ -228,13 +378,26 @@ impl Image {
         &mut self.data
     }
 
-    pub fn roi(&mut self, rect: &Rect) -> ImageRoi {
+    /// Size of the pixel data in bytes
+    pub fn bytes(&self) -> usize {
+        self.data.len() * mem::size_of::<Color>()
+    }
+
+    pub fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.w, self.h)
+    }
+
+    pub fn roi(&self, rect: &Rect) -> ImageRoi {
         ImageRoi {
-            rect: *rect,
-            w: self.w,
-            data: &mut self.data
+            rect: clip(&self.bounds(), rect),
+            stride: self.w,
+            data: &self.data
         }
     }
+
+    pub fn roi_mut(&mut self, rect: &Rect) -> ImageRoiMut {
+        ImageRoiMut::new(&self.bounds(), rect, self.w, &mut self.data)
+    }
 }
 
 impl Renderer for Image {
@@ -262,3 +425,45 @@ impl Renderer for Image {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use orbclient::Color;
+
+    use rect::Rect;
+    use super::{Image, ImageRef};
+
+    /// An image where each pixel holds its own position
+    fn numbered(width: i32, height: i32) -> Image {
+        let data: Vec<Color> = (0..width * height).map(|i| Color { data: i as u32 }).collect();
+        Image::from_data(width, height, data.into_boxed_slice())
+    }
+
+    #[test]
+    fn placed_images_use_their_coordinates() {
+        let mut data = vec![Color { data: 0 }; 4 * 3];
+        {
+            let mut image = ImageRef::from_data(100, 50, 4, 3, &mut data);
+            assert_eq!(image.bounds(), Rect::new(100, 50, 4, 3));
+            image.roi_mut(&Rect::new(101, 51, 2, 1)).fill(Color { data: 7 });
+        }
+        let filled: Vec<usize> = data.iter().enumerate().filter(|&(_, color)| color.data == 7).map(|(i, _)| i).collect();
+        assert_eq!(filled, vec![5, 6]);
+    }
+
+    #[test]
+    fn clipped_copies_stay_lined_up() {
+        let source = numbered(4, 4);
+        let mut data = vec![Color { data: 0xFFFFFFFF }; 4 * 4];
+        {
+            // Placed so only the bottom right quarter of the copy lands on the image
+            let mut image = ImageRef::from_data(10, 10, 4, 4, &mut data);
+            image.roi_mut(&Rect::new(8, 8, 4, 4)).blit(&source.roi(&source.bounds()));
+        }
+        assert_eq!(data[0].data, 10);
+        assert_eq!(data[1].data, 11);
+        assert_eq!(data[4].data, 14);
+        assert_eq!(data[5].data, 15);
+        assert_eq!(data[2].data, 0xFFFFFFFF);
+    }
+}
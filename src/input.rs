@@ -0,0 +1,28 @@
+use orbclient::Event;
+
+/// Event code sent to windows when the kind of device used for input changes
+///
+/// The `a` field holds the input source: 0 for mouse, 1 for keyboard, 2 for touch
+pub const EVENT_INPUT_SOURCE: i64 = 0x100;
+
+/// Kind of device that produced the most recent input
+#[derive(Clone, Copy, PartialEq)]
+pub enum InputSource {
+    Mouse,
+    Keyboard,
+    Touch,
+}
+
+impl InputSource {
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: EVENT_INPUT_SOURCE,
+            a: match *self {
+                InputSource::Mouse => 0,
+                InputSource::Keyboard => 1,
+                InputSource::Touch => 2
+            },
+            b: 0
+        }
+    }
+}
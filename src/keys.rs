@@ -0,0 +1,257 @@
+use orbclient;
+
+/// Compositor action triggered by a key combination
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Ask the focused window to close
+    Close,
+    /// Open the window switcher, or select the next window in it
+    SwitchWindow,
+    /// Show the next background
+    Background,
+    /// Toggle the performance HUD
+    PerfHud,
+    /// Print compositor state to the log
+    Dump,
+    /// Open the run command prompt
+    Run,
+    /// Save a screenshot of the screen to the screenshot directory
+    Screenshot,
+    /// Save a screenshot of the focused window to the screenshot directory
+    ScreenshotWindow,
+    /// Start or stop recording the screen to the screenshot directory
+    Record,
+    /// Raise the focused window, when it was focused without raising it
+    Raise,
+    /// Magnify the contents of the focused window
+    ZoomIn,
+    /// Reduce the magnification of the focused window
+    ZoomOut,
+    /// Show every window on the workspace side by side, or return to the normal layout
+    Overview,
+    /// Pick a window to show its metadata and outline its areas, or stop inspecting
+    Inspect,
+    /// Show a workspace, numbered from 0
+    Workspace(usize),
+    /// Send the focused window to a workspace, numbered from 0
+    MoveToWorkspace(usize),
+}
+
+impl Action {
+    fn from_str(string: &str) -> Option<Action> {
+        match string {
+            "close" => Some(Action::Close),
+            "switch_window" => Some(Action::SwitchWindow),
+            "background" => Some(Action::Background),
+            "perf_hud" => Some(Action::PerfHud),
+            "dump" => Some(Action::Dump),
+            "run" => Some(Action::Run),
+            "screenshot" => Some(Action::Screenshot),
+            "screenshot_window" => Some(Action::ScreenshotWindow),
+            "record" => Some(Action::Record),
+            "raise" => Some(Action::Raise),
+            "zoom_in" => Some(Action::ZoomIn),
+            "zoom_out" => Some(Action::ZoomOut),
+            "overview" => Some(Action::Overview),
+            "inspect" => Some(Action::Inspect),
+            _ => if string.starts_with("workspace_") {
+                workspace(&string[10..]).map(Action::Workspace)
+            } else if string.starts_with("move_to_workspace_") {
+                workspace(&string[18..]).map(Action::MoveToWorkspace)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Parse a workspace number, counted from 1 in the config
+fn workspace(string: &str) -> Option<usize> {
+    match string.parse::<usize>() {
+        Ok(number) if number > 0 => Some(number - 1),
+        _ => None
+    }
+}
+
+/// Print Screen, which keyboards report as SysRq, as its own scancode 0x37 is keypad `*`
+const K_PRINT: u8 = 0x54;
+
+/// Scancode of a key name, or of a hexadecimal scancode such as 0x1C
+fn scancode(name: &str) -> Option<u8> {
+    if name.starts_with("0x") {
+        return u8::from_str_radix(&name[2..], 16).ok();
+    }
+
+    let scancode = match name {
+        "a" => orbclient::K_A, "b" => orbclient::K_B, "c" => orbclient::K_C, "d" => orbclient::K_D,
+        "e" => orbclient::K_E, "f" => orbclient::K_F, "g" => orbclient::K_G, "h" => orbclient::K_H,
+        "i" => orbclient::K_I, "j" => orbclient::K_J, "k" => orbclient::K_K, "l" => orbclient::K_L,
+        "m" => orbclient::K_M, "n" => orbclient::K_N, "o" => orbclient::K_O, "p" => orbclient::K_P,
+        "q" => orbclient::K_Q, "r" => orbclient::K_R, "s" => orbclient::K_S, "t" => orbclient::K_T,
+        "u" => orbclient::K_U, "v" => orbclient::K_V, "w" => orbclient::K_W, "x" => orbclient::K_X,
+        "y" => orbclient::K_Y, "z" => orbclient::K_Z,
+        "0" => orbclient::K_0, "1" => orbclient::K_1, "2" => orbclient::K_2, "3" => orbclient::K_3,
+        "4" => orbclient::K_4, "5" => orbclient::K_5, "6" => orbclient::K_6, "7" => orbclient::K_7,
+        "8" => orbclient::K_8, "9" => orbclient::K_9,
+        "f1" => orbclient::K_F1, "f2" => orbclient::K_F2, "f3" => orbclient::K_F3, "f4" => orbclient::K_F4,
+        "f5" => orbclient::K_F5, "f6" => orbclient::K_F6, "f7" => orbclient::K_F7, "f8" => orbclient::K_F8,
+        "f9" => orbclient::K_F9, "f10" => orbclient::K_F10, "f11" => orbclient::K_F11, "f12" => orbclient::K_F12,
+        "esc" => orbclient::K_ESC,
+        "tab" => orbclient::K_TAB,
+        "backspace" => orbclient::K_BKSP,
+        "enter" => orbclient::K_ENTER,
+        "space" => orbclient::K_SPACE,
+        "minus" => orbclient::K_MINUS,
+        "equals" => orbclient::K_EQUALS,
+        "up" => orbclient::K_UP,
+        "down" => orbclient::K_DOWN,
+        "left" => orbclient::K_LEFT,
+        "right" => orbclient::K_RIGHT,
+        "home" => orbclient::K_HOME,
+        "end" => orbclient::K_END,
+        "pgup" => orbclient::K_PGUP,
+        "pgdn" => orbclient::K_PGDN,
+        "del" => orbclient::K_DEL,
+        "print" => K_PRINT,
+        _ => return None
+    };
+    Some(scancode)
+}
+
+/// A key with the modifiers that must be held
+#[derive(Clone, Copy, PartialEq)]
+struct KeyCombo {
+    win: bool,
+    shift: bool,
+    scancode: u8,
+}
+
+impl KeyCombo {
+    /// Parse a combination such as `win+shift+1`
+    fn from_str(string: &str) -> Option<KeyCombo> {
+        let mut combo = KeyCombo {
+            win: false,
+            shift: false,
+            scancode: 0
+        };
+        let mut key = None;
+        for part in string.split('+') {
+            match part.trim() {
+                "win" => combo.win = true,
+                "shift" => combo.shift = true,
+                name => if key.is_none() {
+                    key = scancode(name);
+                    if key.is_none() {
+                        return None;
+                    }
+                } else {
+                    return None;
+                }
+            }
+        }
+        key.map(|scancode| {
+            combo.scancode = scancode;
+            combo
+        })
+    }
+}
+
+/// Table of compositor shortcuts, the defaults replaced by any set in the `[keys]` config section
+pub struct KeyBindings {
+    bindings: Vec<(KeyCombo, Action)>,
+}
+
+impl KeyBindings {
+    pub fn new(keys: &[(String, String)]) -> KeyBindings {
+        let mut defaults = vec![
+            ("close".to_string(), "win+esc".to_string()),
+            ("switch_window".to_string(), "win+tab".to_string()),
+            ("background".to_string(), "win+backspace".to_string()),
+            ("perf_hud".to_string(), "win+p".to_string()),
+            ("dump".to_string(), "win+d".to_string()),
+            ("run".to_string(), "win+r".to_string()),
+            ("screenshot".to_string(), "win+print".to_string()),
+            ("screenshot_window".to_string(), "win+shift+print".to_string()),
+            ("record".to_string(), "win+shift+r".to_string()),
+            ("raise".to_string(), "win+up".to_string()),
+            ("zoom_in".to_string(), "win+equals".to_string()),
+            ("zoom_out".to_string(), "win+minus".to_string()),
+            ("overview".to_string(), "win+e".to_string()),
+            ("inspect".to_string(), "win+i".to_string()),
+        ];
+        for number in 1..10 {
+            defaults.push((format!("workspace_{}", number), format!("win+{}", number)));
+            defaults.push((format!("move_to_workspace_{}", number), format!("win+shift+{}", number)));
+        }
+
+        let mut bindings = Vec::new();
+        for &(ref action_name, ref combo_str) in defaults.iter() {
+            // Defaults for actions set in the config are replaced
+            if keys.iter().any(|&(ref name, _)| name == action_name) {
+                continue;
+            }
+            if let (Some(action), Some(combo)) = (Action::from_str(action_name), KeyCombo::from_str(combo_str)) {
+                bindings.push((combo, action));
+            }
+        }
+
+        for &(ref action_name, ref combo_str) in keys.iter() {
+            match Action::from_str(action_name) {
+                Some(action) => if combo_str == "none" || combo_str.is_empty() {
+                    // Disabled
+                } else if let Some(combo) = KeyCombo::from_str(combo_str) {
+                    bindings.push((combo, action));
+                } else {
+                    println!("orbital: invalid key combination '{}' for '{}'", combo_str, action_name);
+                },
+                None => println!("orbital: unknown key action '{}'", action_name)
+            }
+        }
+
+        KeyBindings {
+            bindings: bindings
+        }
+    }
+
+    /// No shortcuts, for kiosk mode
+    pub fn none() -> KeyBindings {
+        KeyBindings {
+            bindings: Vec::new()
+        }
+    }
+
+    /// Find the action bound to a key with the current modifiers
+    ///
+    /// A binding matches when the modifiers it names are held, whatever other modifiers are, and
+    /// the binding naming the most held modifiers wins, so `win+shift+print` is told apart from
+    /// `win+print`.
+    pub fn action(&self, win: bool, shift: bool, scancode: u8) -> Option<Action> {
+        self.bindings.iter()
+            .filter(|&&(binding, _)| binding.scancode == scancode && (win || ! binding.win) && (shift || ! binding.shift))
+            .max_by_key(|&&(binding, _)| binding.win as u8 + binding.shift as u8)
+            .map(|&(_, action)| action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, KeyBindings, K_PRINT};
+
+    fn keys(bindings: &[(&str, &str)]) -> Vec<(String, String)> {
+        bindings.iter().map(|&(action, combo)| (action.to_string(), combo.to_string())).collect()
+    }
+
+    #[test]
+    fn most_specific_binding_wins() {
+        let bindings = KeyBindings::new(&[]);
+        assert!(bindings.action(true, false, K_PRINT) == Some(Action::Screenshot));
+        assert!(bindings.action(true, true, K_PRINT) == Some(Action::ScreenshotWindow));
+    }
+
+    #[test]
+    fn extra_modifiers_are_ignored() {
+        let bindings = KeyBindings::new(&keys(&[("screenshot_window", "none")]));
+        assert!(bindings.action(true, true, K_PRINT) == Some(Action::Screenshot));
+        assert!(bindings.action(false, true, K_PRINT) == None);
+    }
+}
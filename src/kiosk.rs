@@ -0,0 +1,90 @@
+use config::Config;
+use window::Window;
+
+/// What happens to top level windows of other apps in kiosk mode, set with `kiosk_others`
+#[derive(Clone, Copy, PartialEq)]
+pub enum KioskOthers {
+    /// Opening them fails
+    Reject,
+    /// They open minimized, and can not be restored as window switching is disabled
+    Minimize,
+}
+
+impl KioskOthers {
+    fn from_str(string: &str) -> KioskOthers {
+        match string {
+            "reject" => KioskOthers::Reject,
+            _ => KioskOthers::Minimize
+        }
+    }
+}
+
+/// How the cursor behaves in kiosk mode, set with `kiosk_cursor`
+#[derive(Clone, Copy, PartialEq)]
+pub enum KioskCursor {
+    Show,
+    Hide,
+    /// Kept inside the kiosk app's window
+    Confine,
+}
+
+impl KioskCursor {
+    fn from_str(string: &str) -> KioskCursor {
+        match string {
+            "hide" => KioskCursor::Hide,
+            "confine" => KioskCursor::Confine,
+            _ => KioskCursor::Show
+        }
+    }
+}
+
+/// Kiosk mode, enabled at startup by setting `kiosk_title` in the config
+///
+/// The window with the kiosk title is fullscreened and keeps focus, except to its own dialogs.
+/// Dialogs and popups of other processes are treated like their other windows.
+/// Compositor shortcuts and window switching are disabled, so every key goes to the app.
+pub struct Kiosk {
+    title: String,
+    pub others: KioskOthers,
+    pub cursor: KioskCursor,
+    /// The kiosk app's window, while it is open
+    pub window: Option<usize>,
+    /// Process that opened the kiosk app's window, whose dialogs and popups are allowed
+    pub owner: Option<usize>,
+}
+
+impl Kiosk {
+    pub fn new(config: &Config) -> Option<Kiosk> {
+        if config.kiosk_title.is_empty() {
+            return None;
+        }
+
+        Some(Kiosk {
+            title: config.kiosk_title.clone(),
+            others: KioskOthers::from_str(&config.kiosk_others),
+            cursor: KioskCursor::from_str(&config.kiosk_cursor),
+            window: None,
+            owner: None
+        })
+    }
+
+    /// Is a window the kiosk app
+    pub fn matches(&self, window: &Window) -> bool {
+        window.title == self.title
+    }
+
+    /// Was a window opened by the kiosk app
+    fn owns(&self, window: &Window) -> bool {
+        self.owner == Some(window.owner)
+    }
+
+    /// May a new window open normally, as the kiosk app, or a dialog or popup of it
+    pub fn allows(&self, window: &Window) -> bool {
+        self.matches(window) || ((window.parent.is_some() || window.popup) && self.owns(window))
+    }
+
+    /// May a window take focus from the kiosk app, only allowed for its dialogs
+    pub fn allows_focus(&self, id: usize, window: &Window) -> bool {
+        self.window.is_none() || self.window == Some(id) || (window.parent.is_some() && self.owns(window))
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+pub fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1000000 + (duration.subsec_nanos() / 1000) as u64
+}
+
+/// Tracks the time from input delivery to the display sync showing the response
+pub struct Latency {
+    /// Arrival time of the oldest input event not yet answered by an fsync
+    input: Option<Instant>,
+    /// Arrival time of the input answered by an fsync, waiting to be presented
+    response: Option<Instant>,
+    pub last: u64,
+    pub max: u64,
+    total: u64,
+    count: u64,
+}
+
+impl Latency {
+    pub fn new() -> Latency {
+        Latency {
+            input: None,
+            response: None,
+            last: 0,
+            max: 0,
+            total: 0,
+            count: 0
+        }
+    }
+
+    /// An input event was delivered to the window
+    pub fn input(&mut self) {
+        if self.input.is_none() {
+            self.input = Some(Instant::now());
+        }
+    }
+
+    /// The window synced new contents
+    pub fn response(&mut self) {
+        if let Some(input) = self.input.take() {
+            if self.response.is_none() {
+                self.response = Some(input);
+            }
+        }
+    }
+
+    /// The display was synced, presenting any pending response
+    pub fn present(&mut self) {
+        if let Some(input) = self.response.take() {
+            let latency = micros(input.elapsed());
+            self.last = latency;
+            if latency > self.max {
+                self.max = latency;
+            }
+            self.total += latency;
+            self.count += 1;
+        }
+    }
+
+    /// Average latency in microseconds
+    pub fn average(&self) -> u64 {
+        if self.count > 0 {
+            self.total / self.count
+        } else {
+            0
+        }
+    }
+}
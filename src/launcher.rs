@@ -0,0 +1,119 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+
+/// Number of commands remembered in the history
+const HISTORY_SIZE: usize = 64;
+
+/// Built-in run command prompt, opened with Win+R
+pub struct Launcher {
+    pub active: bool,
+    pub text: String,
+    history: Vec<String>,
+    /// Position in the history, equal to its length when editing a new command
+    history_i: usize,
+}
+
+impl Launcher {
+    pub fn new() -> Launcher {
+        Launcher {
+            active: false,
+            text: String::new(),
+            history: Vec::new(),
+            history_i: 0
+        }
+    }
+
+    pub fn show(&mut self) {
+        self.active = true;
+        self.text.clear();
+        self.history_i = self.history.len();
+    }
+
+    pub fn hide(&mut self) {
+        self.active = false;
+    }
+
+    /// Run the entered command and hide the prompt, passing the startup id in the environment,
+    /// returns true if the command started
+    pub fn submit(&mut self, startup_id: &str) -> bool {
+        self.hide();
+
+        let mut started = false;
+        let command_line = self.text.trim().to_string();
+        let mut args = command_line.split_whitespace();
+        if let Some(program) = args.next() {
+            match Command::new(program).args(&args.collect::<Vec<&str>>()).env("ORBITAL_STARTUP_ID", startup_id).spawn() {
+                Ok(_) => started = true,
+                Err(err) => println!("orbital: failed to run '{}': {}", command_line, err)
+            }
+
+            if self.history.last() != Some(&command_line) {
+                self.history.push(command_line.clone());
+                if self.history.len() > HISTORY_SIZE {
+                    self.history.remove(0);
+                }
+            }
+        }
+        started
+    }
+
+    /// Show the previous command in the history
+    pub fn history_prev(&mut self) {
+        if self.history_i > 0 {
+            self.history_i -= 1;
+            self.text = self.history[self.history_i].clone();
+        }
+    }
+
+    /// Show the next command in the history, or an empty line after the last one
+    pub fn history_next(&mut self) {
+        if self.history_i < self.history.len() {
+            self.history_i += 1;
+            self.text = match self.history.get(self.history_i) {
+                Some(command_line) => command_line.clone(),
+                None => String::new()
+            };
+        }
+    }
+
+    /// Complete the program name from the executables in $PATH
+    pub fn complete(&mut self) {
+        if self.text.contains(' ') {
+            return;
+        }
+
+        let mut matches = Vec::new();
+        if let Some(paths) = env::var_os("PATH") {
+            for dir in env::split_paths(&paths) {
+                if let Ok(entries) = fs::read_dir(dir) {
+                    for entry_res in entries {
+                        if let Ok(entry) = entry_res {
+                            if let Ok(name) = entry.file_name().into_string() {
+                                if name.starts_with(&self.text) {
+                                    matches.push(name);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Extend the text to the longest prefix shared by all matches
+        if let Some(first) = matches.first() {
+            let mut prefix = first.clone();
+            for name in matches.iter().skip(1) {
+                while ! name.starts_with(&prefix) {
+                    prefix.pop();
+                }
+            }
+            if matches.iter().all(|name| name == first) {
+                prefix.push(' ');
+            }
+            if prefix.len() > self.text.len() {
+                self.text = prefix;
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs::File;
+use std::io::{Read, Write};
+
+use rect::Rect;
+
+/// How a window is shown in a layout
+#[derive(Clone, Copy, PartialEq)]
+pub enum LayoutState {
+    Normal,
+    Maximized,
+    Minimized,
+}
+
+impl LayoutState {
+    fn from_str(string: &str) -> Option<LayoutState> {
+        match string {
+            "normal" => Some(LayoutState::Normal),
+            "maximized" => Some(LayoutState::Maximized),
+            "minimized" => Some(LayoutState::Minimized),
+            _ => None
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match *self {
+            LayoutState::Normal => "normal",
+            LayoutState::Maximized => "maximized",
+            LayoutState::Minimized => "minimized"
+        }
+    }
+}
+
+/// Where a window with a title goes in a layout
+pub struct LayoutWindow {
+    pub title: String,
+    /// Workspace, counted from 0
+    pub workspace: usize,
+    /// Display the geometry is relative to
+    pub display: usize,
+    /// Geometry of the window, before it was maximized
+    pub rect: Rect,
+    pub state: LayoutState,
+}
+
+/// An arrangement of top level windows, exported and applied by writing `layout export <path>`
+/// and `layout apply <path>` to `orbital:control`
+///
+/// Each line of the text is either `display <index> <x> <y> <width> <height>`, recording the
+/// displays at the time of the export, or `window <workspace> <display> <x> <y> <width> <height>
+/// <state> <title>`, with workspaces counted from 1, the geometry relative to the display, and a
+/// state of `normal`, `maximized` or `minimized`. Lines starting with `#` are comments. Applying a
+/// layout moves each open window with a matching title into place, windows sharing a title taking
+/// the lines in order. Only root may export or apply a layout.
+pub struct Layout {
+    pub displays: Vec<Rect>,
+    pub windows: Vec<LayoutWindow>,
+}
+
+impl Layout {
+    /// Parse the text of a layout, skipping lines that are not understood
+    pub fn parse(string: &str) -> Layout {
+        let mut layout = Layout {
+            displays: Vec::new(),
+            windows: Vec::new()
+        };
+
+        for (i, line) in string.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with("display ") {
+                let numbers: Vec<i32> = line[8..].split_whitespace().filter_map(|part| part.parse::<i32>().ok()).collect();
+                if numbers.len() == 5 && numbers[0] as usize == layout.displays.len() {
+                    layout.displays.push(Rect::new(numbers[1], numbers[2], numbers[3], numbers[4]));
+                    continue;
+                }
+            } else if line.starts_with("window ") {
+                if let Some(window) = parse_window(&line[7..]) {
+                    layout.windows.push(window);
+                    continue;
+                }
+            }
+            // Only the line number, as the file may not be a layout at all
+            println!("orbital: invalid layout line {}", i + 1);
+        }
+
+        layout
+    }
+
+    pub fn load(path: &str) -> Option<Layout> {
+        let mut string = String::new();
+        match File::open(path).and_then(|mut file| file.read_to_string(&mut string)) {
+            Ok(_) => Some(Layout::parse(&string)),
+            Err(err) => {
+                println!("orbital: failed to read layout '{}': {}", path, err);
+                None
+            }
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        let mut string = String::new();
+        for (i, rect) in self.displays.iter().enumerate() {
+            string.push_str(&format!("display {} {} {} {} {}\n", i, rect.left(), rect.top(), rect.width(), rect.height()));
+        }
+        for window in self.windows.iter() {
+            string.push_str(&format!("window {} {} {} {} {} {} {} {}\n", window.workspace + 1, window.display,
+                                     window.rect.left(), window.rect.top(), window.rect.width(), window.rect.height(),
+                                     window.state.as_str(), window.title));
+        }
+        string
+    }
+
+    pub fn save(&self, path: &str) -> bool {
+        match File::create(path).and_then(|mut file| file.write_all(self.to_string().as_bytes())) {
+            Ok(()) => true,
+            Err(err) => {
+                println!("orbital: failed to write layout '{}': {}", path, err);
+                false
+            }
+        }
+    }
+}
+
+/// Parse the arguments of a `window` line
+fn parse_window(string: &str) -> Option<LayoutWindow> {
+    let parts: Vec<&str> = string.splitn(8, ' ').collect();
+    if parts.len() < 8 {
+        return None;
+    }
+    let workspace = parts[0].parse::<usize>().ok();
+    let display = parts[1].parse::<usize>().ok();
+    let (x, y, w, h) = (parts[2].parse::<i32>().ok(), parts[3].parse::<i32>().ok(),
+                        parts[4].parse::<i32>().ok(), parts[5].parse::<i32>().ok());
+    let state = LayoutState::from_str(parts[6]);
+    let title = parts[7];
+
+    match (workspace, display, x, y, w, h, state) {
+        (Some(workspace), Some(display), Some(x), Some(y), Some(w), Some(h), Some(state)) if workspace > 0 && w > 0 && h > 0 && ! title.is_empty() => {
+            Some(LayoutWindow {
+                title: title.to_string(),
+                workspace: workspace - 1,
+                display: display,
+                rect: Rect::new(x, y, w, h),
+                state: state
+            })
+        },
+        _ => None
+    }
+}
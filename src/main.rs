@@ -8,130 +8,288 @@ extern crate orbfont;
 extern crate resize;
 extern crate syscall;
 
-use orbclient::{Color, Event};
-use std::{env, mem, slice, str, thread};
-use std::os::unix::io::AsRawFd;
+use orbclient::{Event, EventOption};
+use std::{env, fs, mem, str, thread};
 use std::process::Command;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use syscall::data::Packet;
+use syscall::error::{Error, EAGAIN};
 use syscall::number::SYS_READ;
-use syscall::scheme::SchemeMut;
 
 use config::Config;
+use display::Display;
 use scheme::OrbitalScheme;
 use socket::Socket;
 
+mod animation;
+mod capture;
+mod clipboard;
 mod config;
+mod control;
+mod cursor;
+mod decorator;
+mod display;
+mod fallback;
+mod frame;
+mod gamepad;
+mod hooks;
 mod image;
+mod input;
+mod keys;
+mod kiosk;
+mod latency;
+mod launcher;
+mod layout;
+mod motion;
+mod notify;
+mod overview;
+mod png;
+mod placement;
+mod policy;
+mod preview;
+mod quality;
+mod recorder;
 mod rect;
+mod rules;
 mod scheme;
+mod scroll;
+mod seat;
+mod session;
+mod shadow;
+mod shake;
 mod socket;
+mod stack;
+mod startup;
+mod status;
+mod stylus;
+mod sync_resize;
+mod taskbar;
+mod text;
 mod theme;
+mod theme_schedule;
 mod window;
+mod window_list;
 
-fn event_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, display: Arc<Socket>, socket: Arc<Socket>){
-    loop {
-        {
-            let mut scheme = scheme_mutex.lock().unwrap();
-            scheme.redraw(&display);
+/// Whether a read must wait for events: only reads of sync windows that found none do
+///
+/// Reads of async windows return their EAGAIN to the client instead.
+fn waits(blocks: bool, result: usize) -> bool {
+    blocks && Error::demux(result) == Err(Error::new(EAGAIN))
+}
+
+/// Handle a packet, returning false if it is a read from a sync window that must wait for events
+fn handle_packet(scheme: &mut OrbitalScheme, packet: &mut Packet) -> bool {
+    let blocks = packet.a == SYS_READ && scheme.read_blocks(packet.b);
+
+    scheme.handle_checked(packet);
+
+    ! waits(blocks, packet.a)
+}
+
+/// Retry reads that were blocked waiting for events
+fn handle_todo(scheme: &mut OrbitalScheme, socket: &Socket) {
+    let mut i = 0;
+    while i < scheme.todo.len() {
+        let mut packet = scheme.todo[i].clone();
+
+        if handle_packet(scheme, &mut packet) {
+            scheme.todo.remove(i);
+            socket.send(&packet).unwrap();
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// Notify clients of windows with pending events
+fn send_events(scheme: &OrbitalScheme, socket: &Socket) {
+    for (id, window) in scheme.windows.iter() {
+        if let Some(packet) = event_packet(*id, window.events.len()) {
+            socket.send(&packet).unwrap();
         }
+    }
+}
+
+/// The fevent packet telling a client that a window has events to read, if it has any
+///
+/// Both sync and async clients get it, so either can wait on the event queue before reading.
+fn event_packet(id: usize, events: usize) -> Option<Packet> {
+    if events > 0 {
+        Some(Packet {
+            id: 0,
+            pid: 0,
+            uid: 0,
+            gid: 0,
+            a: syscall::number::SYS_FEVENT,
+            b: id,
+            c: syscall::flag::EVENT_READ,
+            d: events * mem::size_of::<Event>()
+        })
+    } else {
+        None
+    }
+}
+
+/// Are both events from the mouse, with the same buttons held
+fn same_buttons(a: &Event, b: &Event) -> bool {
+    match (a.to_option(), b.to_option()) {
+        (EventOption::Mouse(a), EventOption::Mouse(b)) => a.left_button == b.left_button
+            && a.middle_button == b.middle_button
+            && a.right_button == b.right_button,
+        _ => false
+    }
+}
+
+/// Replace runs of mouse moves with their last position, keeping every button change
+///
+/// A fast mouse sends many moves between frames, and only the last one is drawn, so handling
+/// each would only slow down dragging. An event is only replaced if it was a move itself, with
+/// the buttons of the mouse event before it, so presses and releases keep their position.
+fn coalesce_mouse(events: &[Event]) -> Vec<Event> {
+    let mut coalesced: Vec<Event> = Vec::with_capacity(events.len());
+    for &event in events.iter() {
+        let replace = {
+            let mut previous = coalesced.iter().rev();
+            match (previous.next(), previous.next()) {
+                (Some(last), Some(before)) => same_buttons(last, &event) && same_buttons(before, last),
+                _ => false
+            }
+        };
+        if replace {
+            coalesced.pop();
+        }
+        coalesced.push(event);
+    }
+    coalesced
+}
 
+/// Handle input events from one display, leaving compositing to the next frame
+fn event_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, display_i: usize, display: Arc<Socket>, socket: Arc<Socket>){
+    loop {
         let mut events = [Event::new(); 128];
         let count = display.receive_type(&mut events).unwrap();
-        {
-            let mut scheme = scheme_mutex.lock().unwrap();
-            for &event in events[.. count].iter() {
-                scheme.event(event);
-            }
 
-            let mut i = 0;
-            while i < scheme.todo.len() {
-                let mut packet = scheme.todo[i].clone();
+        let mut scheme = scheme_mutex.lock().unwrap();
+        for event in coalesce_mouse(&events[.. count]) {
+            scheme.event(display_i, event);
+        }
+        scheme.frame_requested = true;
 
-                let delay = if packet.a == SYS_READ {
-                    if let Some(window) = scheme.windows.get(&packet.b) {
-                        window.async == false
-                    } else {
-                        true
-                    }
-                } else {
-                    false
-                };
+        handle_todo(&mut scheme, &socket);
+        send_events(&scheme, &socket);
+    }
+}
 
-                scheme.handle(&mut packet);
+/// Handle input events from a hot-plugged device until it is removed
+fn device_event_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, name: String, device: Socket, socket: Arc<Socket>, devices: Arc<Mutex<Vec<String>>>) {
+    loop {
+        let mut events = [Event::new(); 128];
+        let count = match device.receive_type(&mut events) {
+            Ok(0) | Err(_) => break,
+            Ok(count) => count
+        };
+
+        let mut scheme = scheme_mutex.lock().unwrap();
+        for event in coalesce_mouse(&events[.. count]) {
+            scheme.device_event(&name, event);
+        }
+        scheme.frame_requested = true;
+
+        handle_todo(&mut scheme, &socket);
+        send_events(&scheme, &socket);
+    }
 
-                if delay && packet.a == 0 {
-                    i += 1;
-                }else{
-                    scheme.todo.remove(i);
-                    socket.send(&packet).unwrap();
+    devices.lock().unwrap().retain(|device_name| device_name != &name);
+    scheme_mutex.lock().unwrap().input_device(&name, false);
+}
+
+/// Watch a directory of input devices, opening devices as they appear
+fn device_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, dir: String, interval: u64, socket: Arc<Socket>) {
+    let devices = Arc::new(Mutex::new(Vec::new()));
+    // Devices that failed to open are retried quietly, and logged again only after they go away
+    let mut failed: Vec<String> = Vec::new();
+    loop {
+        if let Ok(entries) = fs::read_dir(&dir) {
+            let mut present = Vec::new();
+            for entry_res in entries {
+                let entry = match entry_res {
+                    Ok(entry) => entry,
+                    Err(_) => continue
+                };
+                let name = match entry.file_name().into_string() {
+                    Ok(name) => name,
+                    Err(_) => continue
+                };
+
+                present.push(name.clone());
+                if devices.lock().unwrap().contains(&name) {
+                    continue;
                 }
-            }
 
-            for (id, window) in scheme.windows.iter() {
-                if ! window.events.is_empty() {
-                    socket.send(&Packet {
-                        id: 0,
-                        pid: 0,
-                        uid: 0,
-                        gid: 0,
-                        a: syscall::number::SYS_FEVENT,
-                        b: *id,
-                        c: syscall::flag::EVENT_READ,
-                        d: window.events.len() * mem::size_of::<Event>()
-                    }).unwrap();
+                match Socket::open(&entry.path().to_string_lossy()) {
+                    Ok(device) => {
+                        failed.retain(|failed_name| failed_name != &name);
+                        devices.lock().unwrap().push(name.clone());
+                        scheme_mutex.lock().unwrap().input_device(&name, true);
+
+                        let scheme_device = scheme_mutex.clone();
+                        let socket_device = socket.clone();
+                        let devices_device = devices.clone();
+                        thread::spawn(move || {
+                            device_event_loop(scheme_device, name, device, socket_device, devices_device);
+                        });
+                    },
+                    Err(err) => if ! failed.contains(&name) {
+                        scheme_mutex.lock().unwrap().status.error(format!("failed to open input device '{}': {}", name, err));
+                        failed.push(name);
+                    }
                 }
             }
+            failed.retain(|name| present.contains(name));
         }
+
+        thread::sleep(Duration::from_millis(interval));
     }
 }
 
-fn server_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, display: Arc<Socket>, socket: Arc<Socket>){
+/// Run frames at the pace of the frame clock, advancing animations and timers, then compositing
+/// once if they or any input and client requests since the last frame changed anything
+fn tick_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, socket: Arc<Socket>){
     loop {
-        {
-            let mut scheme = scheme_mutex.lock().unwrap();
-            scheme.redraw(&display);
+        let delay = scheme_mutex.lock().unwrap().frame_clock.until_next();
+        thread::sleep(delay);
+
+        let mut scheme = scheme_mutex.lock().unwrap();
+        scheme.frame_clock.advance();
+        let ticked = scheme.tick();
+        if ticked || scheme.frame_requested {
+            scheme.frame_requested = false;
+            scheme.redraw();
+            // Reads of captures wait for a composite
+            handle_todo(&mut scheme, &socket);
+            send_events(&scheme, &socket);
         }
+    }
+}
 
+/// Handle client requests, leaving compositing to the next frame
+fn server_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, socket: Arc<Socket>){
+    loop {
         let mut packets = [Packet::default(); 128];
         let count = socket.receive_type(&mut packets).unwrap();
-        {
-            let mut scheme = scheme_mutex.lock().unwrap();
-            for mut packet in packets[.. count].iter_mut() {
-                let delay = if packet.a == SYS_READ {
-                    if let Some(window) = scheme.windows.get(&packet.b) {
-                        window.async == false
-                    } else {
-                        true
-                    }
-                } else {
-                    false
-                };
 
-                scheme.handle(packet);
-
-                if delay && packet.a == 0 {
-                    scheme.todo.push(*packet);
-                } else {
-                    socket.send(&packet).unwrap();
-                }
-            }
-
-            for (id, window) in scheme.windows.iter() {
-                if ! window.events.is_empty() {
-                    socket.send(&Packet {
-                        id: 0,
-                        pid: 0,
-                        uid: 0,
-                        gid: 0,
-                        a: syscall::number::SYS_FEVENT,
-                        b: *id,
-                        c: syscall::flag::EVENT_READ,
-                        d: window.events.len() * mem::size_of::<Event>()
-                    }).unwrap();
-                }
+        let mut scheme = scheme_mutex.lock().unwrap();
+        for mut packet in packets[.. count].iter_mut() {
+            if handle_packet(&mut scheme, packet) {
+                socket.send(&packet).unwrap();
+            } else {
+                scheme.todo.push(*packet);
             }
         }
+        scheme.frame_requested = true;
+
+        send_events(&scheme, &socket);
     }
 }
 
@@ -140,59 +298,168 @@ fn main() {
     if unsafe { syscall::clone(0).unwrap() } == 0 {
         let mut args = env::args().skip(1);
 
-        let display_path = args.next().expect("orbital: no display argument");
+        // Multiple displays are separated by commas, and placed left to right
+        let display_paths = args.next().expect("orbital: no display argument");
         let login_cmd = args.next().expect("orbital: no login manager argument");
 
         env::set_current_dir("file:").unwrap();
 
-        env::set_var("DISPLAY", &display_path);
+        env::set_var("DISPLAY", display_paths.split(',').next().unwrap_or(""));
 
         match Socket::create(":orbital").map(|socket| Arc::new(socket)) {
-            Ok(socket) => match Socket::open(&display_path).map(|display| Arc::new(display)) {
-                Ok(display) => {
-                    let mut buf: [u8; 4096] = [0; 4096];
-                    let count = syscall::fpath(display.as_raw_fd() as usize, &mut buf).unwrap();
-                    let path = unsafe { String::from_utf8_unchecked(Vec::from(&buf[..count])) };
-                    let res = path.split(":").nth(1).unwrap_or("");
-                    let width = res.split("/").nth(1).unwrap_or("").parse::<i32>().unwrap_or(0);
-                    let height = res.split("/").nth(2).unwrap_or("").parse::<i32>().unwrap_or(0);
+            Ok(socket) => {
+                let mut displays = Vec::new();
+                let mut display_errors = Vec::new();
+                let mut x = 0;
+                for display_path in display_paths.split(',') {
+                    match Display::open(display_path, x, 0) {
+                        Ok(display) => {
+                            x += display.width();
+                            displays.push(display);
+                        },
+                        Err(err) => display_errors.push(format!("no display found at '{}': {}", display_path, err))
+                    }
+                }
 
-                    println!("orbital: found display {}x{}", width, height);
+                // Errors are printed once, here or by the status when they are recorded
+                if displays.is_empty() {
+                    for error in display_errors {
+                        println!("orbital: {}", error);
+                    }
+                    println!("orbital: no display found");
+                    return;
+                }
 
-                    let display_ptr = unsafe { syscall::fmap(display.as_raw_fd(), 0, (width * height * 4) as usize).unwrap() };
-                    let display_slice = unsafe { slice::from_raw_parts_mut(display_ptr as *mut Color, (width * height) as usize) };
-                    println!("orbital: mapped display to {:X}", display_ptr);
+                let display_sockets: Vec<Arc<Socket>> = displays.iter().map(|display| display.socket.clone()).collect();
 
-                    let config = Config::from_path("/ui/orbital.conf");
+                let config = Config::from_path("/ui/orbital.conf");
 
-                    let scheme = Arc::new(Mutex::new(OrbitalScheme::new(width, height, display_slice, &config)));
+                let mut scheme = OrbitalScheme::new(displays, &config);
+                for error in display_errors {
+                    scheme.status.error(error);
+                }
+                let scheme = Arc::new(Mutex::new(scheme));
 
-                    let mut command = Command::new(&login_cmd);
-                    for arg in args {
-                        command.arg(&arg);
-                    }
-                    match command.spawn() {
-                        Ok(_child) => (),
-                        Err(err) => println!("orbital: failed to launch '{}': {}", login_cmd, err)
-                    }
+                // Input devices other than those of the displays are opened as they are plugged in
+                if ! config.input_dir.is_empty() {
+                    let scheme_device = scheme.clone();
+                    let socket_device = socket.clone();
+                    let input_dir = config.input_dir.clone();
+                    let input_scan_interval = config.input_scan_interval;
+                    thread::spawn(move || {
+                        device_loop(scheme_device, input_dir, input_scan_interval, socket_device);
+                    });
+                }
 
+                let mut command = Command::new(&login_cmd);
+                for arg in args {
+                    command.arg(&arg);
+                }
+                match command.spawn() {
+                    Ok(_child) => (),
+                    Err(err) => scheme.lock().unwrap().status.error(format!("failed to launch '{}': {}", login_cmd, err))
+                }
+
+                let mut event_threads = Vec::new();
+                for (display_i, display_socket) in display_sockets.into_iter().enumerate() {
                     let scheme_event = scheme.clone();
-                    let display_event = display.clone();
                     let socket_event = socket.clone();
 
-                    let event_thread = thread::spawn(move || {
-                        event_loop(scheme_event, display_event, socket_event);
-                    });
+                    event_threads.push(thread::spawn(move || {
+                        event_loop(scheme_event, display_i, display_socket, socket_event);
+                    }));
+                }
 
-                    server_loop(scheme, display, socket);
+                let scheme_tick = scheme.clone();
+                let socket_tick = socket.clone();
 
-                    let _ = event_thread.join();
+                let tick_thread = thread::spawn(move || {
+                    tick_loop(scheme_tick, socket_tick);
+                });
 
-                    unsafe { let _ = syscall::funmap(display_ptr); }
-                },
-                Err(err) => println!("orbital: no display found: {}", err)
+                server_loop(scheme, socket);
+
+                for event_thread in event_threads {
+                    let _ = event_thread.join();
+                }
+                let _ = tick_thread.join();
             },
             Err(err) => println!("orbital: could not register orbital: {}", err)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use orbclient::{Event, EventOption, MouseEvent};
+    use std::mem::size_of;
+    use syscall::error::{Error, EAGAIN, EBADF};
+    use syscall::flag::EVENT_READ;
+    use syscall::number::SYS_FEVENT;
+
+    use super::{coalesce_mouse, event_packet, waits};
+
+    fn mouse(x: i32, left_button: bool) -> Event {
+        MouseEvent {
+            x: x,
+            y: 0,
+            left_button: left_button,
+            middle_button: false,
+            right_button: false
+        }.to_event()
+    }
+
+    /// Positions of the mouse events, and whether the left button is held at each
+    fn positions(events: &[Event]) -> Vec<(i32, bool)> {
+        events.iter().filter_map(|event| match event.to_option() {
+            EventOption::Mouse(mouse) => Some((mouse.x, mouse.left_button)),
+            _ => None
+        }).collect()
+    }
+
+    #[test]
+    fn sync_reads_wait_for_events() {
+        assert!(waits(true, Error::mux(Err(Error::new(EAGAIN)))));
+        assert!(! waits(true, Error::mux(Ok(size_of::<Event>()))));
+    }
+
+    #[test]
+    fn async_reads_return_eagain() {
+        assert!(! waits(false, Error::mux(Err(Error::new(EAGAIN)))));
+        assert!(! waits(false, Error::mux(Ok(size_of::<Event>()))));
+    }
+
+    #[test]
+    fn other_errors_never_wait() {
+        assert!(! waits(true, Error::mux(Err(Error::new(EBADF)))));
+    }
+
+    #[test]
+    fn fevent_only_for_pending_events() {
+        assert!(event_packet(3, 0).is_none());
+
+        let packet = event_packet(3, 2).expect("no fevent for pending events");
+        assert_eq!(packet.a, SYS_FEVENT);
+        assert_eq!(packet.b, 3);
+        assert_eq!(packet.c, EVENT_READ);
+        assert_eq!(packet.d, 2 * size_of::<Event>());
+    }
+
+    #[test]
+    fn moves_are_coalesced() {
+        let events = [mouse(1, false), mouse(2, false), mouse(3, false), mouse(4, false)];
+        assert_eq!(positions(&coalesce_mouse(&events)), vec![(1, false), (4, false)]);
+    }
+
+    #[test]
+    fn presses_keep_their_position() {
+        let events = [mouse(1, false), mouse(2, true), mouse(3, true), mouse(4, true)];
+        assert_eq!(positions(&coalesce_mouse(&events)), vec![(1, false), (2, true), (4, true)]);
+    }
+
+    #[test]
+    fn quick_clicks_keep_the_press() {
+        let events = [mouse(2, true), mouse(3, true), mouse(4, false)];
+        assert_eq!(positions(&coalesce_mouse(&events)), vec![(2, true), (3, true), (4, false)]);
+    }
+}
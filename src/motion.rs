@@ -0,0 +1,15 @@
+use orbclient::Event;
+
+/// Event code sent to windows when they open and when `reduce_motion` changes, so toolkits can
+/// turn off their own animations too
+///
+/// The `a` field is 1 while motion is reduced and 0 otherwise
+pub const EVENT_REDUCE_MOTION: i64 = 0x524D;
+
+pub fn reduce_motion_event(reduce_motion: bool) -> Event {
+    Event {
+        code: EVENT_REDUCE_MOTION,
+        a: if reduce_motion { 1 } else { 0 },
+        b: 0
+    }
+}
@@ -0,0 +1,139 @@
+use std::cmp::min;
+use std::mem;
+use std::str;
+use std::time::{Duration, Instant};
+use syscall::error::{Error, Result, EAGAIN, EINVAL};
+
+use image::Image;
+
+/// Directory notification icons are loaded from
+///
+/// Icons are read by the compositor, which runs as root, so clients only name icons in this
+/// directory instead of any file they could not read themselves.
+const ICON_DIR: &'static str = "/ui/icons/";
+
+/// Path of an icon named relative to the icon directory, or by a path inside it, returns None for
+/// names that leave it
+fn icon_path(icon: &str) -> Option<String> {
+    let relative = if icon.starts_with(ICON_DIR) {
+        &icon[ICON_DIR.len()..]
+    } else if icon.starts_with('/') {
+        return None;
+    } else {
+        icon
+    };
+    if relative.split('/').any(|part| part.is_empty() || part == "." || part == ".." || part.contains(':')) {
+        return None;
+    }
+    Some(format!("{}{}", ICON_DIR, relative))
+}
+
+/// A notification shown in a corner of the screen
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+    pub icon: Option<Image>,
+    /// When the notification is removed, or never until it is dismissed
+    pub expires: Option<Instant>,
+}
+
+/// A handle to the notification endpoint, opened with `orbital:notify`
+///
+/// Writing `title\nbody\nicon\ntimeout in milliseconds` posts a notification, replacing the
+/// one posted before with the same handle, where the icon is named inside `/ui/icons`. Lines may
+/// be left out, posting no icon and the default timeout, and a timeout of 0 keeps the notification until it is dismissed. Clicking it removes
+/// it and sends `click`, the right button sends `dismiss`, and timing out sends `expire`, each on
+/// a line of its own. Reads wait for one of these while the notification is shown. Closing the
+/// handle removes its notification.
+pub struct NotifyHandle {
+    pub notification: Option<Notification>,
+    feedback: Vec<u8>,
+}
+
+impl NotifyHandle {
+    pub fn new() -> NotifyHandle {
+        NotifyHandle {
+            notification: None,
+            feedback: Vec::new()
+        }
+    }
+
+    /// Show a notification written to the handle
+    pub fn post(&mut self, buf: &[u8], timeout: Duration) -> Result<usize> {
+        let text = try!(str::from_utf8(buf).or(Err(Error::new(EINVAL))));
+        let mut lines = text.lines();
+        let title = lines.next().unwrap_or("").to_string();
+        let body = lines.next().unwrap_or("").to_string();
+        let icon = match lines.next().map(|path| path.trim()).unwrap_or("") {
+            "" => None,
+            name => Some(try!(icon_path(name).ok_or(Error::new(EINVAL))))
+        };
+        let timeout = match lines.next().map(|timeout| timeout.trim()).unwrap_or("") {
+            "" => timeout,
+            timeout_str => Duration::from_millis(try!(timeout_str.parse::<u64>().or(Err(Error::new(EINVAL)))))
+        };
+
+        self.notification = Some(Notification {
+            title: title,
+            body: body,
+            icon: icon.and_then(|path| Image::from_path(&path)),
+            expires: if timeout == Duration::from_secs(0) { None } else { Some(Instant::now() + timeout) }
+        });
+        Ok(buf.len())
+    }
+
+    /// Remove the notification, telling the client why
+    pub fn close(&mut self, reason: &str) {
+        if self.notification.take().is_some() {
+            self.feedback.extend_from_slice(reason.as_bytes());
+            self.feedback.push(b'\n');
+        }
+    }
+
+    /// Remove the notification if it has timed out, returns true if it did
+    pub fn expire(&mut self) -> bool {
+        let expired = self.notification.as_ref()
+            .and_then(|notification| notification.expires)
+            .map_or(false, |expires| Instant::now() >= expires);
+        if expired {
+            self.close("expire");
+        }
+        expired
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.feedback.is_empty() {
+            return if self.notification.is_some() {
+                Err(Error::new(EAGAIN))
+            } else {
+                Ok(0)
+            };
+        }
+
+        let count = min(buf.len(), self.feedback.len());
+        let rest = self.feedback.split_off(count);
+        buf[..count].copy_from_slice(&mem::replace(&mut self.feedback, rest));
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::icon_path;
+
+    #[test]
+    fn icons_are_named_inside_the_icon_directory() {
+        assert_eq!(icon_path("mail.png"), Some("/ui/icons/mail.png".to_string()));
+        assert_eq!(icon_path("apps/mail.png"), Some("/ui/icons/apps/mail.png".to_string()));
+        assert_eq!(icon_path("/ui/icons/mail.png"), Some("/ui/icons/mail.png".to_string()));
+    }
+
+    #[test]
+    fn icons_outside_the_icon_directory_are_refused() {
+        assert_eq!(icon_path("/etc/shadow"), None);
+        assert_eq!(icon_path("../../etc/shadow"), None);
+        assert_eq!(icon_path("/ui/icons/../../etc/shadow"), None);
+        assert_eq!(icon_path("file:/etc/shadow"), None);
+        assert_eq!(icon_path("apps//mail.png"), None);
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use animation::{lerp, Animation};
+use rect::Rect;
+
+/// Milliseconds the overview takes to open or close
+const OVERVIEW_DURATION: u64 = 200;
+/// Space around each window in the grid
+const OVERVIEW_MARGIN: i32 = 16;
+
+/// Cells of a grid holding a number of windows, as close to square as the area allows
+pub fn grid(count: usize, area: &Rect) -> Vec<Rect> {
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let mut columns = 1;
+    while columns * columns < count {
+        columns += 1;
+    }
+    let rows = (count + columns - 1) / columns;
+    let w = area.width() / columns as i32;
+    let h = area.height() / rows as i32;
+
+    (0..count).map(|i| {
+        let column = (i % columns) as i32;
+        let row = (i / columns) as i32;
+        Rect::new(area.left() + column * w + OVERVIEW_MARGIN, area.top() + row * h + OVERVIEW_MARGIN,
+                  w - OVERVIEW_MARGIN * 2, h - OVERVIEW_MARGIN * 2)
+    }).collect()
+}
+
+/// An overview of the windows on the workspace, toggled with Win+E
+///
+/// Every window is scaled down into a grid cell that does not overlap the others, growing out of
+/// its place on the screen as the overview opens. Clicking a window focuses it, and the windows
+/// then shrink back to where they were.
+pub struct Overview {
+    /// Windows and their grid cells, topmost first
+    pub cells: Vec<(usize, Rect)>,
+    animation: Animation,
+    duration: Duration,
+    /// Returning to the normal layout, after which the overview is removed
+    pub closing: bool,
+}
+
+impl Overview {
+    /// Open the overview, moving windows into place at once with `reduce_motion`
+    pub fn new(windows: Vec<usize>, area: &Rect, reduce_motion: bool) -> Overview {
+        let cells = grid(windows.len(), area);
+        let duration = Duration::from_millis(if reduce_motion { 0 } else { OVERVIEW_DURATION });
+        Overview {
+            cells: windows.into_iter().zip(cells.into_iter()).collect(),
+            animation: Animation::new(duration),
+            duration: duration,
+            closing: false
+        }
+    }
+
+    /// Start returning to the normal layout
+    pub fn close(&mut self) {
+        if ! self.closing {
+            self.closing = true;
+            self.animation = Animation::new(self.duration);
+        }
+    }
+
+    /// Progress from the normal layout at 0 to the grid at 1
+    pub fn progress(&self) -> f32 {
+        let t = self.animation.ease_out();
+        if self.closing { 1.0 - t } else { t }
+    }
+
+    /// Area a window is drawn in, between where it is and its cell
+    pub fn rect(&self, from: &Rect, cell: &Rect) -> Rect {
+        let t = self.progress();
+        Rect::new(lerp(from.left(), cell.left(), t), lerp(from.top(), cell.top(), t),
+                  lerp(from.width(), cell.width(), t), lerp(from.height(), cell.height(), t))
+    }
+
+    pub fn animating(&self) -> bool {
+        ! self.animation.done()
+    }
+
+    /// Has the overview finished closing
+    pub fn done(&self) -> bool {
+        self.closing && self.animation.done()
+    }
+
+    /// Window whose cell contains a point
+    pub fn window_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.cells.iter().find(|&&(_, ref cell)| cell.contains(x, y)).map(|&(id, _)| id)
+    }
+
+    /// Cell containing a point
+    pub fn cell_at(&self, x: i32, y: i32) -> Option<Rect> {
+        self.cells.iter().find(|&&(_, ref cell)| cell.contains(x, y)).map(|&(_, cell)| cell)
+    }
+
+    /// Cell of a window
+    pub fn cell(&self, id: usize) -> Option<Rect> {
+        self.cells.iter().find(|&&(window, _)| window == id).map(|&(_, cell)| cell)
+    }
+}
@@ -0,0 +1,67 @@
+use std::cmp::{max, min};
+
+use rect::Rect;
+
+/// Where top level windows open when they do not ask for a position, set with `window_placement`
+#[derive(Clone, Copy, PartialEq)]
+pub enum Placement {
+    /// Each window a little below and right of the last
+    Cascade,
+    /// Centered on the work area
+    Center,
+    /// Where the window overlaps the fewest other windows
+    Smart,
+}
+
+impl Placement {
+    pub fn from_str(string: &str) -> Placement {
+        match string {
+            "cascade" => Placement::Cascade,
+            "center" => Placement::Center,
+            _ => Placement::Smart
+        }
+    }
+}
+
+/// Top left corner for window bounds of a size centered on the work area
+pub fn center(width: i32, height: i32, work_rect: &Rect) -> (i32, i32) {
+    (work_rect.left() + (work_rect.width() - width)/2, work_rect.top() + (work_rect.height() - height)/2)
+}
+
+/// Top left corner for window bounds of a size inside the work area, covering as little of the
+/// occupied areas as possible
+///
+/// Candidates are the corners of the work area and the positions beside each occupied area, so
+/// a free region big enough for the window is always found if there is one. Ties go to the
+/// highest, then leftmost, position.
+pub fn smart(width: i32, height: i32, work_rect: &Rect, occupied: &[Rect]) -> (i32, i32) {
+    let mut xs = vec![work_rect.left(), work_rect.right() - width];
+    let mut ys = vec![work_rect.top(), work_rect.bottom() - height];
+    for rect in occupied.iter() {
+        xs.push(rect.right());
+        xs.push(rect.left() - width);
+        ys.push(rect.bottom());
+        ys.push(rect.top() - height);
+    }
+
+    // Keep candidates inside the work area, or at its top left if the window is larger
+    let clamp_x = |x: i32| max(work_rect.left(), min(work_rect.right() - width, x));
+    let clamp_y = |y: i32| max(work_rect.top(), min(work_rect.bottom() - height, y));
+
+    let mut best = (clamp_x(work_rect.left()), clamp_y(work_rect.top()));
+    let mut best_overlap = i64::max_value();
+    for &y in ys.iter() {
+        for &x in xs.iter() {
+            let (x, y) = (clamp_x(x), clamp_y(y));
+            let rect = Rect::new(x, y, width, height);
+            let overlap: i64 = occupied.iter()
+                .map(|occupied_rect| rect.intersection(occupied_rect).area() as i64)
+                .sum();
+            if overlap < best_overlap || overlap == best_overlap && (y, x) < (best.1, best.0) {
+                best = (x, y);
+                best_overlap = overlap;
+            }
+        }
+    }
+    best
+}
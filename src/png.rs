@@ -0,0 +1,247 @@
+use orbclient::Color;
+use std::cmp::{max, min};
+
+/// Largest block of a stored, uncompressed deflate stream
+const STORED_BLOCK: usize = 65535;
+
+/// Bytes every PNG file starts with
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+fn crc32(data: &[u8], mut crc: u32) -> u32 {
+    crc = ! crc;
+    for &byte in data.iter() {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+    ! crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+    for &byte in data.iter() {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+pub fn push_u32(out: &mut Vec<u8>, value: u32) {
+    out.push((value >> 24) as u8);
+    out.push((value >> 16) as u8);
+    out.push((value >> 8) as u8);
+    out.push(value as u8);
+}
+
+pub fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    push_u32(out, data.len() as u32);
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    push_u32(out, crc32(data, crc32(kind, 0)));
+}
+
+/// RGBA rows of pixels, each starting with filter type 0, no filtering
+fn rows(width: i32, pixels: &[Color], opaque: bool) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(pixels.len() * 4 + pixels.len() / max(width as usize, 1));
+    if width > 0 {
+        for row in pixels.chunks(width as usize) {
+            raw.push(0);
+            for pixel in row.iter() {
+                raw.push((pixel.data >> 16) as u8);
+                raw.push((pixel.data >> 8) as u8);
+                raw.push(pixel.data as u8);
+                raw.push(if opaque { 0xFF } else { (pixel.data >> 24) as u8 });
+            }
+        }
+    }
+    raw
+}
+
+/// Image data of pixels as a zlib stream of RGBA rows, ignoring their alpha if opaque
+pub fn compress(width: i32, pixels: &[Color], opaque: bool) -> Vec<u8> {
+    let raw = rows(width, pixels, opaque);
+
+    // A zlib stream of stored deflate blocks
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(if last { 1 } else { 0 });
+        zlib.push(len as u8);
+        zlib.push((len >> 8) as u8);
+        zlib.push(! len as u8);
+        zlib.push((! len >> 8) as u8);
+        zlib.extend_from_slice(block);
+    }
+    push_u32(&mut zlib, adler32(&raw));
+    zlib
+}
+
+/// Furthest back a deflate match may start
+const WINDOW: usize = 32768;
+/// Shortest and longest deflate matches
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// Bits of the hash of the bytes starting a match
+const HASH_BITS: usize = 15;
+
+const LENGTH_BASE: [usize; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+                                  131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u32; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [usize; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025,
+                                    1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u32; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12,
+                                   13, 13];
+
+/// Bits written to a deflate stream, least significant first
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u32) {
+        self.bits |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Write a Huffman code, which deflate stores most significant bit first
+    fn code(&mut self, code: u32, count: u32) {
+        let mut reversed = 0;
+        for i in 0..count {
+            reversed |= ((code >> i) & 1) << (count - 1 - i);
+        }
+        self.bits(reversed, count);
+    }
+
+    /// Write a literal byte, a length or the end of block with the fixed Huffman codes
+    fn symbol(&mut self, symbol: usize) {
+        if symbol < 144 {
+            self.code(0x30 + symbol as u32, 8);
+        } else if symbol < 256 {
+            self.code(0x190 + (symbol - 144) as u32, 9);
+        } else if symbol < 280 {
+            self.code((symbol - 256) as u32, 7);
+        } else {
+            self.code(0xC0 + (symbol - 280) as u32, 8);
+        }
+    }
+
+    fn copy(&mut self, length: usize, distance: usize) {
+        let i = LENGTH_BASE.iter().rposition(|&base| base <= length).unwrap_or(0);
+        self.symbol(257 + i);
+        self.bits((length - LENGTH_BASE[i]) as u32, LENGTH_EXTRA[i]);
+
+        let i = DISTANCE_BASE.iter().rposition(|&base| base <= distance).unwrap_or(0);
+        self.code(i as u32, 5);
+        self.bits((distance - DISTANCE_BASE[i]) as u32, DISTANCE_EXTRA[i]);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+fn hash(data: &[u8]) -> usize {
+    let value = (data[0] as usize) << 16 | (data[1] as usize) << 8 | data[2] as usize;
+    (value.wrapping_mul(2654435761) >> 8) & ((1 << HASH_BITS) - 1)
+}
+
+/// Image data of pixels as a zlib stream of RGBA rows, ignoring their alpha if opaque
+///
+/// Repeated bytes are replaced by copies of earlier ones and coded with the fixed Huffman
+/// codes. This is slower than `compress`, but makes recordings of mostly unchanging screens a
+/// fraction of the size.
+pub fn deflate(width: i32, pixels: &[Color], opaque: bool) -> Vec<u8> {
+    let raw = rows(width, pixels, opaque);
+
+    let mut writer = BitWriter {
+        out: vec![0x78, 0x01],
+        bits: 0,
+        count: 0
+    };
+    // A single final block with fixed codes
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    // Last position of each hash plus one, zero if not seen
+    let mut last = vec![0usize; 1 << HASH_BITS];
+    let mut i = 0;
+    while i < raw.len() {
+        let mut length = 0;
+        let mut distance = 0;
+        if i + MIN_MATCH <= raw.len() {
+            let h = hash(&raw[i..]);
+            let candidate = last[h];
+            last[h] = i + 1;
+            if candidate > 0 && i - (candidate - 1) <= WINDOW {
+                let start = candidate - 1;
+                let limit = min(MAX_MATCH, raw.len() - i);
+                while length < limit && raw[start + length] == raw[i + length] {
+                    length += 1;
+                }
+                distance = i - start;
+            }
+        }
+
+        if length >= MIN_MATCH {
+            writer.copy(length, distance);
+            // Remember the positions inside the match, so later matches can start there
+            for j in i + 1 .. i + length {
+                if j + MIN_MATCH <= raw.len() {
+                    last[hash(&raw[j..])] = j + 1;
+                }
+            }
+            i += length;
+        } else {
+            writer.symbol(raw[i] as usize);
+            i += 1;
+        }
+    }
+    writer.symbol(256);
+
+    let mut zlib = writer.finish();
+    if zlib.len() > raw.len() {
+        // Noisy images are smaller stored
+        return compress(width, pixels, opaque);
+    }
+    push_u32(&mut zlib, adler32(&raw));
+    zlib
+}
+
+/// Contents of the IHDR chunk of an RGBA image
+pub fn header(width: i32, height: i32) -> Vec<u8> {
+    let mut header = Vec::new();
+    push_u32(&mut header, width as u32);
+    push_u32(&mut header, height as u32);
+    // 8 bits per channel, RGBA, default compression, filtering and no interlacing
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    header
+}
+
+/// Encode pixels as an RGBA PNG, ignoring their alpha if opaque
+///
+/// The image data is stored without compression, which keeps screenshots fast to take at the
+/// cost of larger files.
+pub fn encode(width: i32, height: i32, pixels: &[Color], opaque: bool) -> Vec<u8> {
+    let mut png = SIGNATURE.to_vec();
+    push_chunk(&mut png, b"IHDR", &header(width, height));
+    push_chunk(&mut png, b"IDAT", &compress(width, pixels, opaque));
+    push_chunk(&mut png, b"IEND", &[]);
+    png
+}
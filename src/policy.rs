@@ -0,0 +1,77 @@
+use rect::Rect;
+use window::Window;
+
+/// Window management decisions, selected with `window_policy` in the config
+///
+/// A policy observes windows as they open and close, and may adjust or veto placement, focus
+/// and stacking. Every method has a default that keeps the compositor's own behavior, so a
+/// policy only implements the decisions it changes.
+pub trait Policy {
+    /// A window was opened, after it was placed
+    fn opened(&mut self, _id: usize, _window: &Window) {}
+
+    /// A window was closed
+    fn closed(&mut self, _id: usize) {}
+
+    /// Position for a new window inside the work area of its display, or None to keep the
+    /// position chosen by the compositor
+    fn place(&mut self, _window: &Window, _work_rect: &Rect) -> Option<(i32, i32)> {
+        None
+    }
+
+    /// Should a new window take focus when it opens
+    fn focus_on_open(&mut self, _window: &Window) -> bool {
+        true
+    }
+
+    /// Should a window take focus, return false to veto
+    fn focus(&mut self, _id: usize, _window: &Window) -> bool {
+        true
+    }
+
+    /// Stacking rank of a window, higher ranks are drawn above lower ones, asked when it opens
+    /// and when its layer or fullscreen state changes
+    fn stack_rank(&self, _window: &Window, rank: u8) -> u8 {
+        rank
+    }
+}
+
+/// Select a policy by name, using the default behavior for unknown names
+pub fn from_str(string: &str) -> Box<Policy + Send> {
+    match string {
+        "center" => Box::new(CenterPolicy),
+        "quiet" => Box::new(QuietPolicy),
+        _ => Box::new(DefaultPolicy)
+    }
+}
+
+/// The compositor's own behavior
+struct DefaultPolicy;
+
+impl Policy for DefaultPolicy {}
+
+/// Opens top level windows centered on the work area, instead of cascading them
+struct CenterPolicy;
+
+impl Policy for CenterPolicy {
+    fn place(&mut self, window: &Window, work_rect: &Rect) -> Option<(i32, i32)> {
+        if window.parent.is_some() || window.popup {
+            return None;
+        }
+
+        let bounds = window.bounds();
+        let x = work_rect.left() + (work_rect.width() - bounds.width())/2 + (window.x - bounds.left());
+        let y = work_rect.top() + (work_rect.height() - bounds.height())/2 + (window.y - bounds.top());
+        Some((x, y))
+    }
+}
+
+/// Opens windows behind the focused window, so they never steal focus from what is being typed,
+/// except for dialogs
+struct QuietPolicy;
+
+impl Policy for QuietPolicy {
+    fn focus_on_open(&mut self, window: &Window) -> bool {
+        window.parent.is_some()
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+use syscall::error::{Error, Result, EINVAL};
+
+/// Time in milliseconds a preview is shown for, unless the request sets its own
+const PREVIEW_TIMEOUT: u64 = 5000;
+
+/// A live preview of a window, requested by a panel writing `preview <window id> <x> <y>` to
+/// `orbital:control`
+///
+/// The preview is drawn above every window with its top left corner at x, y, and follows the
+/// contents of the window until it is hidden. An optional fourth number sets the time in
+/// milliseconds before it is hidden, and `preview none` hides it at once. Only one preview is
+/// shown at a time, so a new request replaces the last.
+pub struct Preview {
+    pub window: usize,
+    pub x: i32,
+    pub y: i32,
+    pub expires: Instant,
+}
+
+impl Preview {
+    /// Parse the arguments of a preview request, returning `None` to hide the preview
+    pub fn parse(args: &str) -> Result<Option<Preview>> {
+        if args.trim() == "none" {
+            return Ok(None);
+        }
+
+        let mut parts = args.split_whitespace();
+        let mut next = || parts.next().ok_or(Error::new(EINVAL));
+        let window = try!(try!(next()).parse::<usize>().or(Err(Error::new(EINVAL))));
+        let x = try!(try!(next()).parse::<i32>().or(Err(Error::new(EINVAL))));
+        let y = try!(try!(next()).parse::<i32>().or(Err(Error::new(EINVAL))));
+        let timeout = match next() {
+            Ok(timeout_str) => try!(timeout_str.parse::<u64>().or(Err(Error::new(EINVAL)))),
+            Err(_) => PREVIEW_TIMEOUT
+        };
+
+        Ok(Some(Preview {
+            window: window,
+            x: x,
+            y: y,
+            expires: Instant::now() + Duration::from_millis(timeout)
+        }))
+    }
+
+    /// Has the preview been shown for its timeout
+    pub fn expired(&self) -> bool {
+        Instant::now() >= self.expires
+    }
+}
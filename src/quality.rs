@@ -0,0 +1,87 @@
+use resize;
+use std::time::Duration;
+
+use latency::micros;
+
+#[derive(Clone, Copy, PartialEq)]
+enum QualityPolicy {
+    /// Degrade effects while frames exceed the budget
+    Adaptive,
+    /// Always render all effects
+    High,
+    /// Never render expensive effects
+    Low,
+}
+
+impl QualityPolicy {
+    fn from_str(string: &str) -> QualityPolicy {
+        match string {
+            "high" => QualityPolicy::High,
+            "low" => QualityPolicy::Low,
+            _ => QualityPolicy::Adaptive
+        }
+    }
+}
+
+/// Decides whether expensive effects are rendered, based on recent frame times
+pub struct Quality {
+    policy: QualityPolicy,
+    /// Frame budget in microseconds
+    budget: u64,
+    /// Moving average of frame times in microseconds
+    average: u64,
+    degraded: bool,
+}
+
+impl Quality {
+    pub fn new(policy: &str, budget_ms: u64) -> Quality {
+        let policy = QualityPolicy::from_str(policy);
+        Quality {
+            policy: policy,
+            budget: budget_ms * 1000,
+            average: 0,
+            degraded: policy == QualityPolicy::Low
+        }
+    }
+
+    /// Record the time taken to composite a frame, returns true if the quality level changed
+    pub fn frame(&mut self, duration: Duration) -> bool {
+        if self.policy != QualityPolicy::Adaptive || self.budget == 0 {
+            return false;
+        }
+
+        self.average = (self.average * 7 + micros(duration)) / 8;
+
+        if ! self.degraded && self.average > self.budget {
+            println!("orbital: frame time {} us over budget, reducing quality", self.average);
+            self.degraded = true;
+            true
+        } else if self.degraded && self.average < self.budget / 2 {
+            println!("orbital: frame time {} us within budget, restoring quality", self.average);
+            self.degraded = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Should windows be alpha blended, or copied opaquely
+    pub fn transparency(&self) -> bool {
+        ! self.degraded
+    }
+
+    /// Should images scaled while compositing, such as thumbnails and animated windows, be
+    /// filtered rather than sampled at the nearest pixel
+    pub fn smooth(&self) -> bool {
+        ! self.degraded
+    }
+
+    /// Filter used when scaling images as they are loaded
+    pub fn filter(&self) -> resize::Type {
+        if self.degraded {
+            resize::Type::Triangle
+        } else {
+            resize::Type::Lanczos3
+        }
+    }
+}
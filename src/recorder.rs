@@ -0,0 +1,165 @@
+use std::cmp::min;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result, Seek, SeekFrom, Write};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use image::Image;
+use latency::micros;
+use png;
+use rect::Rect;
+
+/// A frame of the recording, the area it replaces, its contents and its delay in thousandths of
+/// a second
+type Frame = (Rect, Image, u16);
+
+/// A recording of the screen to an animated PNG, toggled with Win+Shift+R or by writing `record`
+/// to `orbital:control`
+///
+/// The first frame is the whole screen. Each composite after it adds a frame of the area that
+/// changed, which is shown until the next composite, so the recording plays back at the pace it
+/// was drawn. Frames are compressed and written by another thread as they are replaced, so
+/// compositing does not wait for them, and the frame count is written when recording stops or
+/// the recorder is dropped.
+pub struct Recorder {
+    pub path: String,
+    /// Frames sent to the writing thread, closed to finish the file
+    frames: Option<Sender<Frame>>,
+    writer: Option<JoinHandle<Result<()>>>,
+    /// The latest frame, sent once its duration is known
+    pending: Option<(Rect, Image, Instant)>,
+}
+
+impl Recorder {
+    /// Create the file, with a copy of the whole screen as the first frame
+    pub fn start(path: String, screen: Image) -> Result<Recorder> {
+        let mut file = try!(File::create(&path));
+
+        let mut data = png::SIGNATURE.to_vec();
+        png::push_chunk(&mut data, b"IHDR", &png::header(screen.width(), screen.height()));
+        let control_offset = data.len() as u64;
+        png::push_chunk(&mut data, b"acTL", &[0; 8]);
+        try!(file.write_all(&data));
+
+        let (sender, receiver) = channel();
+        let writer = thread::spawn(move || write_frames(file, control_offset, receiver));
+
+        let mut recorder = Recorder {
+            path: path,
+            frames: Some(sender),
+            writer: Some(writer),
+            pending: None
+        };
+        let bounds = screen.bounds();
+        try!(recorder.frame(&bounds, screen));
+        Ok(recorder)
+    }
+
+    /// Add a frame of an area of the screen, with a copy of its contents
+    pub fn frame(&mut self, rect: &Rect, image: Image) -> Result<()> {
+        if rect.is_empty() {
+            return Ok(());
+        }
+
+        try!(self.send_pending());
+
+        self.pending = Some((*rect, image, Instant::now()));
+        Ok(())
+    }
+
+    /// Send the latest frame to be written, shown from when it was taken until now
+    fn send_pending(&mut self) -> Result<()> {
+        let (rect, image, time) = match self.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(())
+        };
+
+        let delay = min(micros(time.elapsed()) / 1000, 65535) as u16;
+        let sent = match self.frames {
+            Some(ref frames) => frames.send((rect, image, delay)).is_ok(),
+            None => false
+        };
+        if sent {
+            Ok(())
+        } else {
+            // The writing thread stopped, its error is returned when it is joined
+            Err(Error::new(ErrorKind::BrokenPipe, "recording stopped"))
+        }
+    }
+
+    /// Send the last frame and wait for the file to be finished
+    fn finish(&mut self) -> Result<()> {
+        let sent = self.send_pending();
+        self.frames = None;
+        let written = match self.writer.take() {
+            Some(writer) => writer.join().unwrap_or_else(|_| Err(Error::new(ErrorKind::Other, "recording thread panicked"))),
+            None => Ok(())
+        };
+        written.and(sent)
+    }
+
+    /// Write the last frame and the frame count, returning the path of the recording
+    pub fn stop(mut self) -> Result<String> {
+        try!(self.finish());
+        Ok(self.path.clone())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(err) = self.finish() {
+            println!("orbital: failed to save recording '{}': {}", self.path, err);
+        }
+    }
+}
+
+/// Compress and write frames until the recorder closes the channel, then end the file and write
+/// the frame count
+fn write_frames(mut file: File, control_offset: u64, frames: Receiver<Frame>) -> Result<()> {
+    let mut count = 0;
+    // Sequence number of the next fcTL or fdAT chunk
+    let mut sequence = 0;
+    for (rect, image, delay) in frames.iter() {
+        let mut control = Vec::new();
+        png::push_u32(&mut control, sequence);
+        png::push_u32(&mut control, rect.width() as u32);
+        png::push_u32(&mut control, rect.height() as u32);
+        png::push_u32(&mut control, rect.left() as u32);
+        png::push_u32(&mut control, rect.top() as u32);
+        // Delay in thousandths of a second, keeping the previous frame under this one and
+        // replacing its area
+        control.extend_from_slice(&[(delay >> 8) as u8, delay as u8, 0x03, 0xE8, 0, 0]);
+        sequence += 1;
+
+        let mut data = Vec::new();
+        png::push_chunk(&mut data, b"fcTL", &control);
+        let compressed = png::deflate(image.width(), image.data(), true);
+        if count == 0 {
+            // The first frame is also the still image shown by viewers without animation
+            png::push_chunk(&mut data, b"IDAT", &compressed);
+        } else {
+            let mut frame = Vec::with_capacity(4 + compressed.len());
+            png::push_u32(&mut frame, sequence);
+            frame.extend_from_slice(&compressed);
+            png::push_chunk(&mut data, b"fdAT", &frame);
+            sequence += 1;
+        }
+        count += 1;
+
+        try!(file.write_all(&data));
+    }
+
+    let mut end = Vec::new();
+    png::push_chunk(&mut end, b"IEND", &[]);
+    try!(file.write_all(&end));
+
+    // Frame count, and play forever
+    let mut animation = Vec::new();
+    png::push_u32(&mut animation, count);
+    png::push_u32(&mut animation, 0);
+    let mut chunk = Vec::new();
+    png::push_chunk(&mut chunk, b"acTL", &animation);
+    try!(file.seek(SeekFrom::Start(control_offset)));
+    file.write_all(&chunk)
+}
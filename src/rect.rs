@@ -1,6 +1,16 @@
 use std::cmp::{min, max};
 
-#[derive(Copy, Clone, Debug, Default)]
+/// Limit the size of a rect to be positive and to keep its far edge from overflowing
+fn extent(start: i32, size: i32) -> i32 {
+    max(0, min(size, i32::max_value().saturating_sub(start)))
+}
+
+/// A rect in screen coordinates
+///
+/// Sizes are never negative, and the right and bottom edges always fit in an `i32`, so rects
+/// built from client input or long drags can not overflow. Operations saturate at the limits of
+/// an `i32` instead.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Rect {
     x: i32,
     y: i32,
@@ -9,20 +19,27 @@ pub struct Rect {
 }
 
 impl Rect {
+    /// Create a rect, clamping negative sizes to zero and sizes past the limits of an `i32`
     pub fn new(x: i32, y: i32, w: i32, h: i32) -> Rect {
-        assert!(w >= 0);
-        assert!(h >= 0);
-
         Rect{
             x: x,
             y: y,
-            w: w,
-            h: h
+            w: extent(x, w),
+            h: extent(y, h)
+        }
+    }
+
+    /// Parse a rect written as `x,y,w,h`
+    pub fn from_str(string: &str) -> Option<Rect> {
+        let mut parts = string.split(',').map(|part| part.trim().parse::<i32>());
+        match (parts.next(), parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(x)), Some(Ok(y)), Some(Ok(w)), Some(Ok(h)), None) if w >= 0 && h >= 0 => Some(Rect::new(x, y, w, h)),
+            _ => None
         }
     }
 
     pub fn area(&self) -> i32 {
-        self.w * self.h
+        self.w.saturating_mul(self.h)
     }
 
     pub fn left(&self) -> i32 {
@@ -55,10 +72,7 @@ impl Rect {
         let top = min(self.top(), other.top());
         let bottom = max(self.bottom(), other.bottom());
 
-        assert!(left <= right);
-        assert!(top <= bottom);
-
-        Rect::new(left, top, right - left, bottom - top)
+        Rect::new(left, top, right.saturating_sub(left), bottom.saturating_sub(top))
     }
 
     pub fn contains(&self, x: i32, y: i32) -> bool {
@@ -78,10 +92,378 @@ impl Rect {
         let top = max(self.top(), other.top());
         let bottom = min(self.bottom(), other.bottom());
 
-        Rect::new(left, top, max(0, right - left), max(0, bottom - top))
+        Rect::new(left, top, right.saturating_sub(left), bottom.saturating_sub(top))
     }
 
     pub fn offset(&self, x: i32, y: i32) -> Rect {
-        Rect::new(self.x + x, self.y + y, self.w, self.h)
+        Rect::new(self.x.saturating_add(x), self.y.saturating_add(y), self.w, self.h)
+    }
+
+    /// Parts of this rect outside of another, as up to four disjoint rects
+    pub fn subtract(&self, other: &Rect) -> Vec<Rect> {
+        let intersection = self.intersection(other);
+        if intersection.is_empty() {
+            return if self.is_empty() { Vec::new() } else { vec![*self] };
+        }
+
+        let pieces = [
+            // Above and below the intersection, across the whole width
+            Rect::new(self.left(), self.top(), self.w, intersection.top() - self.top()),
+            Rect::new(self.left(), intersection.bottom(), self.w, self.bottom() - intersection.bottom()),
+            // Beside the intersection, only as high as it is
+            Rect::new(self.left(), intersection.top(), intersection.left() - self.left(), intersection.h),
+            Rect::new(intersection.right(), intersection.top(), self.right() - intersection.right(), intersection.h),
+        ];
+        pieces.iter().filter(|piece| ! piece.is_empty()).cloned().collect()
+    }
+}
+
+/// An area made of disjoint rects, which need not be rectangular
+///
+/// Unlike the container of two rects, a region covering two small rects far apart is no larger
+/// than they are, so redrawing it never redraws the space between them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Region {
+    rects: Vec<Rect>,
+}
+
+impl Region {
+    pub fn new() -> Region {
+        Region {
+            rects: Vec::new()
+        }
+    }
+
+    pub fn from_rect(rect: Rect) -> Region {
+        let mut region = Region::new();
+        region.add(rect);
+        region
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// The disjoint rects making up the region
+    pub fn iter(&self) -> ::std::slice::Iter<Rect> {
+        self.rects.iter()
+    }
+
+    /// Empty the region, returning its rects
+    pub fn take(&mut self) -> Vec<Rect> {
+        ::std::mem::replace(&mut self.rects, Vec::new())
+    }
+
+    /// Add the parts of a rect not already in the region
+    pub fn add(&mut self, rect: Rect) {
+        let mut pieces = vec![rect];
+        for existing in self.rects.iter() {
+            pieces = pieces.iter().flat_map(|piece| piece.subtract(existing)).collect();
+            if pieces.is_empty() {
+                return;
+            }
+        }
+        for piece in pieces {
+            self.push(piece);
+        }
+    }
+
+    /// Add a rect known to be disjoint from the region, joining it to a rect it lines up with
+    fn push(&mut self, mut rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+
+        // Joining may line the result up with another rect, so keep going until nothing joins
+        loop {
+            let joined = self.rects.iter().position(|existing| {
+                let beside = existing.top() == rect.top() && existing.height() == rect.height()
+                    && (existing.right() == rect.left() || rect.right() == existing.left());
+                let above = existing.left() == rect.left() && existing.width() == rect.width()
+                    && (existing.bottom() == rect.top() || rect.bottom() == existing.top());
+                beside || above
+            });
+            match joined {
+                Some(i) => rect = rect.container(&self.rects.swap_remove(i)),
+                None => break
+            }
+        }
+        self.rects.push(rect);
+    }
+
+    pub fn union(&mut self, other: &Region) {
+        for rect in other.rects.iter() {
+            self.add(*rect);
+        }
+    }
+
+    pub fn intersection(&self, other: &Region) -> Region {
+        let mut region = Region::new();
+        for rect in self.rects.iter() {
+            for other_rect in other.rects.iter() {
+                // Both sets of rects are disjoint, so their intersections are too
+                region.push(rect.intersection(other_rect));
+            }
+        }
+        region
+    }
+
+    /// Remove the parts of the region inside a rect
+    pub fn subtract(&mut self, rect: &Rect) {
+        let rects = self.take();
+        for existing in rects {
+            for piece in existing.subtract(rect) {
+                self.push(piece);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::max;
+
+    use super::{Rect, Region};
+
+    /// A small xorshift generator, so failures can be replayed without an external crate
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Mostly small numbers, so rects overlap often, with the limits of an `i32` mixed in
+        fn coord(&mut self) -> i32 {
+            match self.next() % 8 {
+                0 => i32::max_value(),
+                1 => i32::min_value(),
+                2 => self.next() as i32,
+                _ => (self.next() % 200) as i32 - 100
+            }
+        }
+
+        fn rect(&mut self) -> Rect {
+            Rect::new(self.coord(), self.coord(), self.coord(), self.coord())
+        }
+    }
+
+    const CASES: usize = 10000;
+
+    fn is_valid(rect: &Rect) -> bool {
+        rect.width() >= 0 && rect.height() >= 0
+        && rect.left().checked_add(rect.width()).is_some()
+        && rect.top().checked_add(rect.height()).is_some()
+    }
+
+    /// Whether a rect lies inside another, counting empty rects as inside anything
+    fn is_inside(rect: &Rect, other: &Rect) -> bool {
+        rect.is_empty() || (
+            rect.left() >= other.left() && rect.right() <= other.right()
+            && rect.top() >= other.top() && rect.bottom() <= other.bottom()
+        )
+    }
+
+    #[test]
+    fn new_clamps_sizes() {
+        let mut rng = Rng(1);
+        for _ in 0..CASES {
+            let (x, y, w, h) = (rng.coord(), rng.coord(), rng.coord(), rng.coord());
+            let rect = Rect::new(x, y, w, h);
+            assert!(is_valid(&rect), "{:?} from {} {} {} {}", rect, x, y, w, h);
+            assert_eq!((rect.left(), rect.top()), (x, y));
+            assert!(rect.width() <= max(w, 0) && rect.height() <= max(h, 0));
+        }
+
+        assert_eq!(Rect::new(0, 0, -5, 3).width(), 0);
+        assert_eq!(Rect::new(i32::max_value() - 10, 0, 100, 1).right(), i32::max_value());
+        assert_eq!(Rect::new(i32::max_value(), 0, 100, 1).width(), 0);
+    }
+
+    #[test]
+    fn intersection_is_inside_both() {
+        let mut rng = Rng(2);
+        for _ in 0..CASES {
+            let (a, b) = (rng.rect(), rng.rect());
+            let intersection = a.intersection(&b);
+            assert!(is_valid(&intersection));
+            assert!(is_inside(&intersection, &a), "{:?} not inside {:?}", intersection, a);
+            assert!(is_inside(&intersection, &b), "{:?} not inside {:?}", intersection, b);
+            assert_eq!(intersection.is_empty(), b.intersection(&a).is_empty());
+            if ! intersection.is_empty() {
+                assert_eq!(intersection, b.intersection(&a));
+            }
+            if ! a.is_empty() {
+                assert_eq!(a.intersection(&a), a);
+            }
+        }
+    }
+
+    #[test]
+    fn container_holds_both() {
+        let mut rng = Rng(3);
+        for _ in 0..CASES {
+            let (a, b) = (rng.rect(), rng.rect());
+            let container = a.container(&b);
+            assert!(is_valid(&container));
+            assert_eq!(container, b.container(&a));
+            // Sizes saturate, so only rects whose container fits are held entirely
+            let fits = container.right() == max(a.right(), b.right())
+                && container.bottom() == max(a.bottom(), b.bottom());
+            if fits {
+                assert!(is_inside(&a, &container), "{:?} not inside {:?}", a, container);
+                assert!(is_inside(&b, &container), "{:?} not inside {:?}", b, container);
+            }
+        }
+    }
+
+    #[test]
+    fn offset_saturates() {
+        let mut rng = Rng(4);
+        for _ in 0..CASES {
+            let rect = rng.rect();
+            let (x, y) = (rng.coord(), rng.coord());
+            let moved = rect.offset(x, y);
+            assert!(is_valid(&moved));
+            assert_eq!(moved.left(), rect.left().saturating_add(x));
+            assert_eq!(moved.top(), rect.top().saturating_add(y));
+        }
+    }
+
+    #[test]
+    fn from_str_rejects_negative_sizes() {
+        assert_eq!(Rect::from_str("1, 2, 3, 4"), Some(Rect::new(1, 2, 3, 4)));
+        assert_eq!(Rect::from_str("1,2,-3,4"), None);
+        assert_eq!(Rect::from_str("1,2,3"), None);
+        assert_eq!(Rect::from_str("1,2,3,4,5"), None);
+    }
+
+    /// A rect of small coordinates, so pixels can be counted
+    fn small_rect(rng: &mut Rng) -> Rect {
+        let (x, y, w, h) = (rng.next() % 20, rng.next() % 20, rng.next() % 20, rng.next() % 20);
+        Rect::new(x as i32, y as i32, w as i32, h as i32)
+    }
+
+    fn covers(rects: &[Rect], x: i32, y: i32) -> bool {
+        rects.iter().any(|rect| x >= rect.left() && x < rect.right() && y >= rect.top() && y < rect.bottom())
+    }
+
+    fn assert_disjoint(rects: &[Rect]) {
+        for (i, a) in rects.iter().enumerate() {
+            assert!(! a.is_empty(), "empty rect in {:?}", rects);
+            for b in rects[i + 1 ..].iter() {
+                assert!(a.intersection(b).is_empty(), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn subtract_overlapping() {
+        let pieces = Rect::new(0, 0, 10, 10).subtract(&Rect::new(5, 5, 10, 10));
+        assert_eq!(pieces, vec![Rect::new(0, 0, 10, 5), Rect::new(0, 5, 5, 5)]);
+    }
+
+    #[test]
+    fn subtract_contained() {
+        let pieces = Rect::new(0, 0, 10, 10).subtract(&Rect::new(2, 3, 4, 5));
+        assert_eq!(pieces, vec![
+            Rect::new(0, 0, 10, 3),
+            Rect::new(0, 8, 10, 2),
+            Rect::new(0, 3, 2, 5),
+            Rect::new(6, 3, 4, 5),
+        ]);
+        assert!(Rect::new(2, 3, 4, 5).subtract(&Rect::new(0, 0, 10, 10)).is_empty());
+    }
+
+    #[test]
+    fn subtract_disjoint() {
+        let rect = Rect::new(0, 0, 10, 10);
+        assert_eq!(rect.subtract(&Rect::new(20, 0, 5, 5)), vec![rect]);
+        // Touching edges do not overlap
+        assert_eq!(rect.subtract(&Rect::new(10, 0, 5, 5)), vec![rect]);
+        assert!(Rect::new(0, 0, 0, 10).subtract(&Rect::new(20, 0, 5, 5)).is_empty());
+    }
+
+    #[test]
+    fn subtract_leaves_only_outside_pixels() {
+        let mut rng = Rng(5);
+        for _ in 0..CASES / 10 {
+            let (a, b) = (small_rect(&mut rng), small_rect(&mut rng));
+            let pieces = a.subtract(&b);
+            assert_disjoint(&pieces);
+            for y in 0..40 {
+                for x in 0..40 {
+                    let expected = covers(&[a], x, y) && ! covers(&[b], x, y);
+                    assert_eq!(covers(&pieces, x, y), expected, "{:?} - {:?} at {},{}", a, b, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn region_joins_rects_that_line_up() {
+        let mut region = Region::new();
+        region.add(Rect::new(0, 0, 10, 10));
+        region.add(Rect::new(10, 0, 10, 10));
+        assert_eq!(region.iter().cloned().collect::<Vec<Rect>>(), vec![Rect::new(0, 0, 20, 10)]);
+
+        // The joined rect lines up with the next one, so all three join
+        region.add(Rect::new(0, 10, 20, 5));
+        assert_eq!(region.iter().cloned().collect::<Vec<Rect>>(), vec![Rect::new(0, 0, 20, 15)]);
+
+        // Adding a covered rect changes nothing
+        region.add(Rect::new(5, 5, 5, 5));
+        assert_eq!(region.iter().cloned().collect::<Vec<Rect>>(), vec![Rect::new(0, 0, 20, 15)]);
+
+        // Rects far apart stay apart
+        region.add(Rect::new(100, 100, 1, 1));
+        assert_eq!(region.iter().count(), 2);
+    }
+
+    #[test]
+    fn region_covers_the_same_pixels() {
+        let mut rng = Rng(6);
+        for _ in 0..CASES / 100 {
+            let mut region = Region::new();
+            let mut added = Vec::new();
+            let mut removed = Vec::new();
+            for _ in 0..8 {
+                let rect = small_rect(&mut rng);
+                if rng.next() % 4 == 0 {
+                    region.subtract(&rect);
+                    removed.push((added.len(), rect));
+                } else {
+                    region.add(rect);
+                    added.push(rect);
+                }
+            }
+
+            let rects = region.iter().cloned().collect::<Vec<Rect>>();
+            assert_disjoint(&rects);
+            for y in 0..40 {
+                for x in 0..40 {
+                    // A pixel is in the region if some rect added it after every rect that removed it
+                    let last_removed = removed.iter().filter(|&&(_, rect)| covers(&[rect], x, y))
+                        .map(|&(i, _)| i).max();
+                    let expected = added.iter().enumerate().any(|(i, rect)| {
+                        covers(&[*rect], x, y) && last_removed.map_or(true, |removed| i >= removed)
+                    });
+                    assert_eq!(covers(&rects, x, y), expected, "{:?} at {},{}", rects, x, y);
+                }
+            }
+
+            let other = Region::from_rect(small_rect(&mut rng));
+            let other_rects = other.iter().cloned().collect::<Vec<Rect>>();
+            let intersection = region.intersection(&other).iter().cloned().collect::<Vec<Rect>>();
+            assert_disjoint(&intersection);
+            for y in 0..40 {
+                for x in 0..40 {
+                    assert_eq!(covers(&intersection, x, y), covers(&rects, x, y) && covers(&other_rects, x, y));
+                }
+            }
+        }
     }
 }
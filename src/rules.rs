@@ -0,0 +1,91 @@
+/// What a window rule changes about a new window
+#[derive(Clone, Copy, PartialEq)]
+pub enum RuleAction {
+    /// Open at a position, as `position:x,y`
+    Position(i32, i32),
+    /// Open with a size, as `size:width,height`
+    Size(i32, i32),
+    /// Open on a workspace, counted from 1, as `workspace:n`
+    Workspace(usize),
+    /// Stack above normal windows, as `on_top`
+    OnTop,
+    /// Open without decorations, as `borderless`
+    Borderless,
+    /// Open filling the work area, as `maximized`
+    Maximized,
+}
+
+impl RuleAction {
+    pub fn from_str(string: &str) -> Option<RuleAction> {
+        let mut parts = string.splitn(2, ':');
+        let name = parts.next().unwrap_or("");
+        let value = parts.next().unwrap_or("");
+        match name {
+            "position" => pair(value).map(|(x, y)| RuleAction::Position(x, y)),
+            "size" => pair(value).and_then(|(w, h)| if w > 0 && h > 0 {
+                Some(RuleAction::Size(w, h))
+            } else {
+                None
+            }),
+            "workspace" => match value.parse::<usize>() {
+                Ok(number) if number > 0 => Some(RuleAction::Workspace(number - 1)),
+                _ => None
+            },
+            "on_top" => Some(RuleAction::OnTop),
+            "borderless" => Some(RuleAction::Borderless),
+            "maximized" => Some(RuleAction::Maximized),
+            _ => None
+        }
+    }
+}
+
+/// Two numbers separated by a comma
+fn pair(string: &str) -> Option<(i32, i32)> {
+    let mut parts = string.splitn(2, ',');
+    let a = parts.next().unwrap_or("").trim().parse::<i32>();
+    let b = parts.next().unwrap_or("").trim().parse::<i32>();
+    match (a, b) {
+        (Ok(a), Ok(b)) => Some((a, b)),
+        _ => None
+    }
+}
+
+/// A rule from the `[rules]` config section, applied to top level windows as they open
+///
+/// Each line is a window title, ending with `*` to match titles starting with the rest, then `=`
+/// and actions separated by spaces, such as `Terminal* = size:640,480 workspace:2`. Every
+/// matching rule applies, in the order they are written.
+pub struct Rule {
+    title: String,
+    pub actions: Vec<RuleAction>,
+}
+
+impl Rule {
+    /// Does a window title match the rule
+    pub fn matches(&self, title: &str) -> bool {
+        if self.title.ends_with('*') {
+            title.starts_with(&self.title[.. self.title.len() - 1])
+        } else {
+            title == self.title
+        }
+    }
+}
+
+/// Parse the `[rules]` config section, skipping unknown actions
+pub fn from_config(rules: &[(String, String)]) -> Vec<Rule> {
+    let mut parsed = Vec::new();
+    for &(ref title, ref actions_str) in rules.iter() {
+        let mut actions = Vec::new();
+        for action_str in actions_str.split_whitespace() {
+            match RuleAction::from_str(action_str) {
+                Some(action) => actions.push(action),
+                None => println!("orbital: unknown action '{}' in rule for '{}'", action_str, title)
+            }
+        }
+        parsed.push(Rule {
+            title: title.clone(),
+            actions: actions
+        });
+    }
+    parsed
+}
@@ -1,38 +1,113 @@
-use orbclient::{self, Color, Event, EventOption, KeyEvent, MouseEvent, FocusEvent, QuitEvent, MoveEvent, ResizeEvent, Renderer};
+use orbclient::{self, Color, Event, EventOption, KeyEvent, MouseEvent, FocusEvent, QuitEvent, MoveEvent, ResizeEvent};
 use orbfont;
 use resize;
 
-use std::collections::{BTreeMap, VecDeque};
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
 use std::path::Path;
-use std::{slice, str};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{mem, slice, str};
 use syscall::data::Packet;
-use syscall::error::{Error, Result, EBADF, EINVAL};
+use syscall::error::{Error, Result, EACCES, EBADF, EINVAL, EIO};
+use syscall::number::{SYS_FMAP, SYS_FPATH, SYS_FSYNC, SYS_READ, SYS_WRITE};
 use syscall::scheme::SchemeMut;
 
+use animation::{Animation, MoveAnimation, WindowAnimation, WindowAnimationKind};
+use capture::{self, Capture};
+use clipboard::{Clipboard, ClipboardHandle};
 use config::Config;
+use control::Control;
+use cursor::{CursorShape, Cursors};
+use decorator::{self, Buttons, Decorator};
+use hooks::Hooks;
+use display::Display;
+use fallback;
+use frame::FrameClock;
+use gamepad::{GamepadAction, GamepadBindings, GamepadEvent, EVENT_GAMEPAD};
 use image::{Image, ImageRef};
-use rect::Rect;
-use socket::Socket;
-use theme::{BACKGROUND_COLOR, BAR_COLOR, BAR_HIGHLIGHT_COLOR, TEXT_COLOR, TEXT_HIGHLIGHT_COLOR};
-use window::Window;
+use input::InputSource;
+use keys::{Action, KeyBindings};
+use kiosk::{Kiosk, KioskCursor, KioskOthers};
+use latency::micros;
+use launcher::Launcher;
+use layout::{Layout, LayoutState, LayoutWindow};
+use motion::reduce_motion_event;
+use notify::NotifyHandle;
+use overview::Overview;
+use png;
+use placement::{self, Placement};
+use policy::{self, Policy};
+use preview::Preview;
+use quality::Quality;
+use recorder::Recorder;
+use rect::{Rect, Region};
+use rules::{self, Rule, RuleAction};
+use scroll::ScrollDirection;
+use seat::Seat;
+use session::Session;
+use shadow::Shadow;
+use shake::Shake;
+use stack::Stack;
+use startup::Startup;
+use status::Status;
+use stylus::{StylusEvent, EVENT_STYLUS};
+use taskbar::{self, TaskButton, Taskbar, TaskbarPosition};
+use text::TextCache;
+use theme::{self, Theme};
+use theme_schedule::{ThemeMode, ThemeSchedule};
+use window::{Decorations, Layer, Window};
+use window_list::{self, WindowList};
 
-fn schedule(redraws: &mut Vec<Rect>, request: Rect) {
-    let mut push = true;
-    for mut rect in redraws.iter_mut() {
-        //If contained, ignore new redraw request
-        let container = rect.container(&request);
-        if container.area() <= rect.area() + request.area() {
-            *rect = container;
-            push = false;
-            break;
-        }
-    }
+/// Height of a row in the window switcher
+const SWITCHER_ROW: i32 = 72;
+/// Width of window thumbnails in the window switcher
+const THUMBNAIL_WIDTH: i32 = 96;
+/// Number of workspaces, switched to with Win+1 to Win+9
+const WORKSPACES: usize = 9;
+/// Time in milliseconds of one pulse of a window asking for attention
+const ATTENTION_PERIOD: u64 = 1200;
+/// Size of a notification, stacked in the top right corner of the screen
+const NOTIFICATION_WIDTH: i32 = 320;
+const NOTIFICATION_HEIGHT: i32 = 56;
+/// Space around notifications
+const NOTIFICATION_MARGIN: i32 = 8;
+/// Size notification icons are scaled to
+const NOTIFICATION_ICON: i32 = 40;
+/// Size of window previews requested by panels
+const PREVIEW_WIDTH: i32 = 240;
+const PREVIEW_HEIGHT: i32 = 180;
+/// Distance in pixels a tiled or maximized window is dragged by its title before it is restored,
+/// so clicks that move a little do not restore it
+const UNSNAP_DISTANCE: i32 = 8;
 
-    if push {
-        redraws.push(request);
+/// Label shown for a window in the switcher, task bar and previews
+fn window_label(id: usize, title: &str) -> String {
+    if title.is_empty() {
+        format!("[unnamed #{}]", id)
+    } else {
+        title.to_string()
     }
 }
 
+fn schedule(redraws: &mut Region, request: Rect) {
+    redraws.add(request);
+}
+
+/// Keep a window position near the screen, so the edges of a window of this size can not overflow
+fn keep_near(screen_rect: &Rect, x: i32, y: i32, width: i32, height: i32) -> (i32, i32) {
+    (
+        max(screen_rect.left().saturating_sub(width), min(screen_rect.right(), x)),
+        max(screen_rect.top().saturating_sub(height), min(screen_rect.bottom(), y))
+    )
+}
+
+/// Limit a window size to the screen
+fn fit_size(screen_rect: &Rect, width: i32, height: i32) -> (i32, i32) {
+    (min(width, screen_rect.width()), min(height, screen_rect.height()))
+}
+
 #[derive(Clone, Copy)]
 enum BackgroundMode {
     /// Do not resize the image, just center it
@@ -56,7 +131,151 @@ impl BackgroundMode {
     }
 }
 
-fn resize_image(image: Image, mode: BackgroundMode, display_width: i32, display_height: i32) -> Image {
+#[derive(Clone, Copy)]
+enum CloseMode {
+    /// Close the window as soon as the close button is clicked
+    Click,
+    /// Require a second click on the close button within the timeout
+    Confirm,
+    /// Require the close button to be held down for the timeout
+    Hold,
+}
+
+impl CloseMode {
+    fn from_str(string: &str) -> CloseMode {
+        match string {
+            "confirm" => CloseMode::Confirm,
+            "hold" => CloseMode::Hold,
+            _ => CloseMode::Click
+        }
+    }
+}
+
+/// What clicking a window with a button bound in the config does, set with `title_middle_click`,
+/// `title_right_click` and `win_middle_click`
+#[derive(Clone, Copy)]
+enum ClickAction {
+    None,
+    /// Send the window behind the others in its layer
+    Lower,
+    /// Keep the window above normal windows, or return it to them
+    OnTop,
+    Maximize,
+    Minimize,
+    Close,
+}
+
+impl ClickAction {
+    fn from_str(string: &str) -> ClickAction {
+        match string {
+            "lower" => ClickAction::Lower,
+            "on_top" => ClickAction::OnTop,
+            "maximize" => ClickAction::Maximize,
+            "minimize" => ClickAction::Minimize,
+            "close" => ClickAction::Close,
+            _ => ClickAction::None
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FocusMode {
+    /// Keyboard focus changes only when a window is clicked or focused explicitly, while the
+    /// pointer goes to whichever window it is over
+    Click,
+    /// Keyboard focus moves to a window once the pointer has rested in it for the focus delay,
+    /// without raising it, and stays there when the pointer leaves for the desktop
+    Follow,
+}
+
+impl FocusMode {
+    fn from_str(string: &str) -> FocusMode {
+        match string {
+            "follow" => FocusMode::Follow,
+            _ => FocusMode::Click
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum CloseFocus {
+    /// Focus the window under the cursor, falling back to the most recently used window
+    Cursor,
+    /// Focus the most recently used window
+    Recent,
+}
+
+impl CloseFocus {
+    fn from_str(string: &str) -> CloseFocus {
+        match string {
+            "recent" => CloseFocus::Recent,
+            _ => CloseFocus::Cursor
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum DesktopScroll {
+    /// Send the scroll to the focused window
+    Focused,
+    /// Switch to the previous or next workspace
+    Workspace,
+    /// Show the previous or next background
+    Background,
+}
+
+impl DesktopScroll {
+    fn from_str(string: &str) -> DesktopScroll {
+        match string {
+            "workspace" => DesktopScroll::Workspace,
+            "background" => DesktopScroll::Background,
+            _ => DesktopScroll::Focused
+        }
+    }
+}
+
+/// Which windows scrolling over a title bar cycles focus through
+#[derive(Clone, Copy, PartialEq)]
+enum TitleScroll {
+    /// Send the scroll to the focused window
+    None,
+    /// The windows stacked under the cursor
+    Stacked,
+    /// Every window on the workspace
+    All,
+}
+
+impl TitleScroll {
+    fn from_str(string: &str) -> TitleScroll {
+        match string {
+            "stacked" => TitleScroll::Stacked,
+            "all" => TitleScroll::All,
+            _ => TitleScroll::None
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TouchMode {
+    /// Always use normal decorations
+    Off,
+    /// Always use enlarged decorations
+    On,
+    /// Use enlarged decorations while the last pointer input came from a touchscreen
+    Auto,
+}
+
+impl TouchMode {
+    fn from_str(string: &str) -> TouchMode {
+        match string {
+            "on" => TouchMode::On,
+            "auto" => TouchMode::Auto,
+            _ => TouchMode::Off
+        }
+    }
+}
+
+fn resize_image(image: Image, mode: BackgroundMode, display_width: i32, display_height: i32, filter: resize::Type) -> Image {
     let (width, height) = match mode {
         BackgroundMode::Center => {
             return image;
@@ -98,6 +317,15 @@ fn resize_image(image: Image, mode: BackgroundMode, display_width: i32, display_
         return image;
     }
 
+    scale_image(&image, width, height, filter)
+}
+
+/// Resample an image to a new size
+fn scale_image(image: &Image, width: i32, height: i32, filter: resize::Type) -> Image {
+    if image.width() == 0 || image.height() == 0 || width == 0 || height == 0 {
+        return Image::new(width, height);
+    }
+
     let src_color = image.data();
     let mut dst_color = vec![Color::rgb(0, 0, 0); width as usize * height as usize].into_boxed_slice();
 
@@ -110,13 +338,13 @@ fn resize_image(image: Image, mode: BackgroundMode, display_width: i32, display_
 
     let mut resizer = resize::new(image.width() as usize, image.height() as usize,
                                   width as usize, height as usize,
-                                  resize::Pixel::RGBA, resize::Type::Lanczos3);
+                                  resize::Pixel::RGBA, filter);
     resizer.resize(&src, &mut dst);
 
     Image::from_data(width, height, dst_color)
 }
 
-fn load_backgrounds(configs: &Vec<String>, mode: BackgroundMode, display_width: i32, display_height: i32) -> Vec<Image> {
+fn load_backgrounds(configs: &Vec<String>, mode: BackgroundMode, display_width: i32, display_height: i32, quality: &Quality) -> Vec<Image> {
     let mut paths = Vec::new();
 
     for config in configs.iter() {
@@ -141,77 +369,334 @@ fn load_backgrounds(configs: &Vec<String>, mode: BackgroundMode, display_width:
         println!("orbital: loading {}", path.display());
         if let Some(image) = Image::from_path(path) {
             println!("orbital: resizing {}", path.display());
-            backgrounds.push(resize_image(image, mode, display_width, display_height));
+            backgrounds.push(resize_image(image, mode, display_width, display_height, quality.filter()));
         }
     }
 
     backgrounds
 }
 
+/// What the left button is dragging
+///
+/// Borders hold the window, the offset of the cursor from the dragged edges, and the position of
+/// the opposite edges, which stay in place while the window is resized.
 enum DragMode {
     None,
     Title(usize, i32, i32),
+    LeftBorder(usize, i32, i32),
     RightBorder(usize, i32),
+    TopBorder(usize, i32, i32),
     BottomBorder(usize, i32),
+    TopLeftBorder(usize, i32, i32, i32, i32),
+    TopRightBorder(usize, i32, i32, i32),
+    BottomLeftBorder(usize, i32, i32, i32),
     BottomRightBorder(usize, i32, i32),
+    Close(usize, Instant),
 }
 
 pub struct OrbitalScheme {
+    /// Framebuffer of the display being drawn, swapped in from `displays` while redrawing, so
+    /// windows are composited straight onto each display
     image: ImageRef<'static>,
+    displays: Vec<Display>,
+    config_path: String,
+    /// Modification time of the config when it was last loaded, to reload it when it changes
+    config_modified: Option<SystemTime>,
+    config_checked: Instant,
+    theme: Theme,
+    /// Light and dark variants of the theme, switched by the time of day
+    theme_schedule: ThemeSchedule,
+    /// Variant shown, None for the plain theme
+    theme_mode: Option<ThemeMode>,
+    decorator: Box<Decorator + Send>,
+    policy: Box<Policy + Send>,
+    placement: Placement,
+    /// Last geometry of windows by title, restored when they open again
+    session: Option<Session>,
+    /// Window rules from the `[rules]` config section
+    rules: Vec<Rule>,
     backgrounds: Vec<Image>,
+    background_mode: BackgroundMode,
     background_i: usize,
     window_close: Image,
     window_close_unfocused: Image,
+    window_maximize: Image,
     window_minimize: Image,
+    close_mode: CloseMode,
+    close_timeout: Duration,
+    close_armed: Option<(usize, Instant)>,
+    close_focus: CloseFocus,
+    title_middle_click: ClickAction,
+    title_right_click: ClickAction,
+    win_middle_click: ClickAction,
+    /// What scrolling over the desktop, with no window under the cursor, does
+    desktop_scroll: DesktopScroll,
+    /// What scrolling over a title bar does
+    title_scroll: TitleScroll,
     cursor: Image,
+    /// Images of the resize and move cursors, shown instead of `cursor` for other shapes
+    cursors: Cursors,
+    cursor_shape: CursorShape,
+    /// Cursor shown enlarged after the cursor is shaken, to help find it
+    cursor_large: Image,
+    shake: Shake,
+    shake_cursor_scale: i32,
+    shake_duration: Duration,
+    /// When the enlarged cursor is hidden again, while it is shown
+    shake_until: Option<Instant>,
     cursor_x: i32,
     cursor_y: i32,
     cursor_left: bool,
     cursor_middle: bool,
     cursor_right: bool,
     dragging: DragMode,
+    drag_time: Instant,
+    drag_velocity: (f32, f32),
+    fling_speed: f32,
+    fling_duration: Duration,
+    moves: Vec<MoveAnimation>,
+    unfocused_opacity: u8,
+    opacity_duration: Duration,
+    window_animations: bool,
+    /// Skip animations, fades and cursor shaking, for users bothered by motion
+    reduce_motion: bool,
+    /// Windows opening, closing, or minimizing
+    animations: Vec<WindowAnimation>,
+    /// Windows kept after they closed, until they have faded out
+    closed: BTreeMap<usize, Window>,
+    blur_radius: i32,
+    /// Copy a window covering a whole display straight to it
+    bypass: bool,
+    /// Raise windows clicked inside, rather than only focusing them
+    raise_on_click: bool,
+    focus_mode: FocusMode,
+    focus_delay: Duration,
+    /// Move the pointer into windows focused with the keyboard
+    warp_pointer: bool,
+    /// Window the pointer is over, which receives mouse events but not necessarily the keyboard
+    pointer_window: Option<usize>,
+    /// When the pointer entered its window, until keyboard focus has followed it there
+    pointer_entered: Option<Instant>,
+    shadow: Shadow,
+    /// Area of the shadow drawn in the last frame, to redraw when it changes
+    shadow_drawn: Rect,
+    touch_mode: TouchMode,
+    touch: bool,
+    touch_title_height: i32,
+    input_source: InputSource,
+    last_tick: Instant,
     win_key: bool,
+    shift_key: bool,
     win_tabbing: bool,
+    perf_hud: bool,
+    /// Windows shown side by side, while the overview is open or closing
+    overview: Option<Overview>,
+    /// Clicks pick a window to inspect instead of going to windows, toggled with Win+I
+    inspecting: bool,
+    /// Window picked in the inspector, whose metadata is in the control report
+    inspected: Option<usize>,
+    launcher: Launcher,
+    /// Apps launched but without a window yet, shown with the busy cursor
+    startups: Vec<Startup>,
+    /// Number of apps started from the launcher, to give each a startup id
+    launched: usize,
+    list_filter: String,
+    list_selected: usize,
+    list_scroll: usize,
+    workspace: usize,
     next_id: isize,
+    /// Process that sent the packet being handled
+    caller_pid: usize,
     next_x: i32,
     next_y: i32,
-    order: VecDeque<usize>,
+    order: Stack,
+    hooks: Hooks,
+    /// Time without input before the idle hooks run, disabled when zero
+    idle_timeout: Duration,
+    last_input: Instant,
+    idle: bool,
+    /// Time the screen fades out for before the idle timeout, staying blank while idle
+    dim_duration: Duration,
+    /// How dark the screen is drawn, out of 255
+    dim: u8,
+    keys: KeyBindings,
+    screenshot_dir: String,
+    /// Screen recording in progress, toggled with the record action or command
+    recorder: Option<Recorder>,
+    gamepad: GamepadBindings,
+    /// Window receiving gamepad events instead of the focused window, set with the `G` write command
+    gamepad_grab: Option<usize>,
+    kiosk: Option<Kiosk>,
+    /// Areas of the screen where presses are ignored
+    dead_zones: Vec<Rect>,
+    seats: Vec<Seat>,
+    /// Natural scrolling for each input device
+    scroll_direction: ScrollDirection,
+    quality: Quality,
     pub windows: BTreeMap<usize, Window>,
-    redraws: Vec<Rect>,
+    controls: BTreeMap<usize, Control>,
+    captures: BTreeMap<usize, Capture>,
+    notifications: BTreeMap<usize, NotifyHandle>,
+    window_lists: BTreeMap<usize, WindowList>,
+    taskbar: Taskbar,
+    /// Window preview requested through `orbital:control`
+    preview: Option<Preview>,
+    /// Time notifications are shown for unless they set their own
+    notification_timeout: Duration,
+    pub status: Status,
+    clipboard: Clipboard,
+    clipboards: BTreeMap<usize, ClipboardHandle>,
+    redraws: Region,
+    text_cache: TextCache,
     pub todo: Vec<Packet>,
+    /// Input or client requests were handled since the last frame, so the next one composites
+    pub frame_requested: bool,
+    /// When the next frame runs
+    pub frame_clock: FrameClock,
+    frame_rate: u32,
     font: orbfont::Font
 }
 
 impl OrbitalScheme {
-    pub fn new(width: i32, height: i32, data: &'static mut [Color], config: &Config) -> OrbitalScheme {
+    pub fn new(displays: Vec<Display>, config: &Config) -> OrbitalScheme {
+        let quality = Quality::new(&config.quality_policy, config.frame_budget);
+        let width = displays.iter().fold(0, |width, display| max(width, display.x + display.width()));
+        let height = displays.iter().fold(0, |height, display| max(height, display.y + display.height()));
+        let cursor = fallback::load(&config.cursor, "cursor", config.fallback_images, fallback::cursor);
+        let theme_schedule = ThemeSchedule::new(&config);
+        let theme_mode = theme_schedule.mode();
+        let background_mode = BackgroundMode::from_str(&config.background_mode);
+        let theme = theme_schedule.theme(theme_mode);
+        let backgrounds = load_backgrounds(theme_schedule.backgrounds(theme_mode), background_mode, width, height, &quality);
+        let cursor_large = scale_image(&cursor, cursor.width() * max(1, config.shake_cursor_scale),
+                                       cursor.height() * max(1, config.shake_cursor_scale), quality.filter());
         OrbitalScheme {
-            image: ImageRef::from_data(width, height, data),
-            backgrounds: load_backgrounds(&config.background,
-                                     BackgroundMode::from_str(&config.background_mode),
-                                     width, height),
+            image: ImageRef::empty(),
+            displays: displays,
+            config_path: config.path.clone(),
+            config_modified: fs::metadata(&config.path).and_then(|metadata| metadata.modified()).ok(),
+            config_checked: Instant::now(),
+            theme_schedule: theme_schedule,
+            theme_mode: theme_mode,
+            theme: theme,
+            decorator: decorator::from_str(&config.decoration_style),
+            policy: policy::from_str(&config.window_policy),
+            placement: Placement::from_str(&config.window_placement),
+            session: if config.session_file.is_empty() { None } else { Some(Session::load(&config.session_file)) },
+            rules: rules::from_config(&config.rules),
+            backgrounds: backgrounds,
+            background_mode: background_mode,
             background_i: 0,
-            window_close: Image::from_path(&config.window_close).unwrap_or(Image::new(0, 0)),
-            window_close_unfocused: Image::from_path(&config.window_close_unfocused).unwrap_or(Image::new(0, 0)),
-            window_minimize: Image::from_path(&config.window_minimize).unwrap_or(Image::new(0, 0)),
-            cursor: Image::from_path(&config.cursor).unwrap_or(Image::new(0, 0)),
+            window_close: fallback::load(&config.window_close, "close", config.fallback_images, fallback::close),
+            window_close_unfocused: fallback::load(&config.window_close_unfocused, "unfocused close", config.fallback_images, fallback::close_unfocused),
+            window_maximize: fallback::load(&config.window_maximize, "maximize", config.fallback_images, fallback::maximize),
+            window_minimize: fallback::load(&config.window_minimize, "minimize", config.fallback_images, fallback::minimize),
+            close_mode: CloseMode::from_str(&config.window_close_mode),
+            close_timeout: Duration::from_millis(config.window_close_timeout),
+            close_armed: None,
+            close_focus: CloseFocus::from_str(&config.focus_after_close),
+            title_middle_click: ClickAction::from_str(&config.title_middle_click),
+            title_right_click: ClickAction::from_str(&config.title_right_click),
+            win_middle_click: ClickAction::from_str(&config.win_middle_click),
+            desktop_scroll: DesktopScroll::from_str(&config.desktop_scroll),
+            title_scroll: TitleScroll::from_str(&config.title_scroll),
+            cursor: cursor,
+            cursors: Cursors::new(config),
+            cursor_shape: CursorShape::Default,
+            cursor_large: cursor_large,
+            shake: Shake::new(),
+            shake_cursor_scale: max(1, config.shake_cursor_scale),
+            shake_duration: Duration::from_millis(config.shake_duration),
+            shake_until: None,
             cursor_x: 0,
             cursor_y: 0,
             cursor_left: false,
             cursor_middle: false,
             cursor_right: false,
             dragging: DragMode::None,
+            drag_time: Instant::now(),
+            drag_velocity: (0.0, 0.0),
+            fling_speed: config.fling_speed as f32,
+            fling_duration: Duration::from_millis(config.fling_duration),
+            moves: Vec::new(),
+            unfocused_opacity: config.unfocused_opacity,
+            opacity_duration: Duration::from_millis(config.opacity_duration),
+            window_animations: config.window_animations,
+            reduce_motion: config.reduce_motion,
+            animations: Vec::new(),
+            closed: BTreeMap::new(),
+            blur_radius: config.blur_radius,
+            bypass: config.bypass,
+            raise_on_click: config.raise_on_click,
+            focus_mode: FocusMode::from_str(&config.focus_mode),
+            focus_delay: Duration::from_millis(config.focus_delay),
+            warp_pointer: config.warp_pointer,
+            pointer_window: None,
+            pointer_entered: None,
+            shadow: Shadow::new(config.shadow_size),
+            shadow_drawn: Rect::new(-1, -1, 0, 0),
+            touch_mode: TouchMode::from_str(&config.touch_mode),
+            touch: TouchMode::from_str(&config.touch_mode) == TouchMode::On,
+            touch_title_height: config.touch_title_height,
+            input_source: InputSource::Mouse,
+            last_tick: Instant::now(),
             win_key: false,
+            shift_key: false,
             // Is the user currently switching windows with win-tab
             // Set true when win-tab is pressed, set false when win is released.
             // While it is true, redraw() calls draw_window_list()
             win_tabbing: false,
+            // Is the performance HUD shown, toggled with win-p
+            perf_hud: config.perf_hud,
+            overview: None,
+            inspecting: false,
+            inspected: None,
+            launcher: Launcher::new(),
+            startups: Vec::new(),
+            launched: 0,
+            // Text typed to filter the window switcher, the selected window, and how far it is scrolled
+            list_filter: String::new(),
+            list_selected: 0,
+            list_scroll: 0,
+            workspace: 0,
             next_id: 1,
+            caller_pid: 0,
             next_x: 4,
             next_y: 32,
-            order: VecDeque::new(),
+            order: Stack::new(),
+            hooks: Hooks::new(&config.hooks, &config.hook_notify),
+            idle_timeout: Duration::from_secs(config.idle_timeout),
+            dim_duration: Duration::from_millis(config.dim_duration),
+            dim: 0,
+            last_input: Instant::now(),
+            idle: false,
+            keys: if config.kiosk_title.is_empty() { KeyBindings::new(&config.keys) } else { KeyBindings::none() },
+            screenshot_dir: config.screenshot_dir.clone(),
+            recorder: None,
+            gamepad: GamepadBindings::new(&config.gamepad),
+            gamepad_grab: None,
+            kiosk: Kiosk::new(config),
+            dead_zones: config.dead_zone.iter().filter_map(|zone| Rect::from_str(zone)).collect(),
+            seats: config.seat.iter().filter_map(|seat| Seat::from_str(seat)).collect(),
+            scroll_direction: ScrollDirection::new(config.natural_scrolling, &config.natural_scrolling_device),
+            quality: quality,
             windows: BTreeMap::new(),
-            redraws: vec![Rect::new(0, 0, width, height)],
+            controls: BTreeMap::new(),
+            captures: BTreeMap::new(),
+            notifications: BTreeMap::new(),
+            window_lists: BTreeMap::new(),
+            taskbar: Taskbar::new(TaskbarPosition::from_str(&config.taskbar)),
+            preview: None,
+            notification_timeout: Duration::from_millis(config.notification_timeout),
+            status: Status::new(),
+            clipboard: Clipboard::new(),
+            clipboards: BTreeMap::new(),
+            redraws: Region::from_rect(Rect::new(0, 0, width, height)),
+            text_cache: TextCache::new(config.text_cache_size * 1024),
             todo: Vec::new(),
+            frame_requested: true,
+            frame_clock: FrameClock::new(config.frame_rate),
+            frame_rate: config.frame_rate,
             font: orbfont::Font::find(Some("Sans"), None, None).unwrap()
         }
     }
@@ -220,292 +705,2718 @@ impl OrbitalScheme {
         if let Some(background) = self.backgrounds.get(self.background_i) {
             let w = background.width();
             let h = background.height();
-            let x = self.image.width()/2 - w/2;
-            let y = self.image.height()/2 - h/2;
+            let screen_rect = self.screen_rect();
+            let x = screen_rect.width()/2 - w/2;
+            let y = screen_rect.height()/2 - h/2;
             Rect::new(x, y, w, h)
         } else {
             Rect::new(-1, -1, 0, 0)
         }
     }
 
-    fn cursor_rect(&self) -> Rect {
-        Rect::new(self.cursor_x, self.cursor_y, self.cursor.width(), self.cursor.height())
+    /// Shape of the cursor drawn, which is busy over the desktop while apps are starting
+    fn shown_cursor_shape(&self) -> CursorShape {
+        if self.cursor_shape == CursorShape::Default && ! self.startups.is_empty() {
+            CursorShape::Busy
+        } else {
+            self.cursor_shape
+        }
     }
 
-    fn screen_rect(&self) -> Rect {
-        Rect::new(0, 0, self.image.width(), self.image.height())
+    fn cursor_rect(&self) -> Rect {
+        if self.kiosk.as_ref().map_or(false, |kiosk| kiosk.cursor == KioskCursor::Hide) {
+            Rect::new(-1, -1, 0, 0)
+        } else if let Some(image) = self.cursors.get(self.shown_cursor_shape()) {
+            Rect::new(self.cursor_x - image.width()/2, self.cursor_y - image.height()/2, image.width(), image.height())
+        } else if self.shake_until.is_some() {
+            Rect::new(self.cursor_x, self.cursor_y, self.cursor_large.width(), self.cursor_large.height())
+        } else {
+            Rect::new(self.cursor_x, self.cursor_y, self.cursor.width(), self.cursor.height())
+        }
     }
 
-    pub fn redraw(&mut self, display: &Socket){
-        let screen_rect = self.screen_rect();
-        let background_rect = self.background_rect();
+    /// Add or replace the startup with an id, or end it, redrawing the cursor in case it stops or
+    /// starts being busy
+    fn set_startup(&mut self, id: &str, startup: Option<Startup>) {
+        let cursor_rect = self.cursor_rect();
+        schedule(&mut self.redraws, cursor_rect);
+
+        self.startups.retain(|startup| startup.id != id);
+        if let Some(startup) = startup {
+            self.startups.push(startup);
+        }
+
         let cursor_rect = self.cursor_rect();
+        schedule(&mut self.redraws, cursor_rect);
+    }
 
-        for mut rect in self.redraws.drain(..) {
-            rect = rect.intersection(&screen_rect);
+    /// Area covering every display
+    fn screen_rect(&self) -> Rect {
+        self.displays.iter().fold(Rect::new(0, 0, 0, 0), |screen_rect, display| screen_rect.container(&display.rect()))
+    }
 
-            if ! rect.is_empty() {
-                //TODO: only clear area not covered by background
-                self.image.rect(rect.left(), rect.top(),
-                                rect.width() as u32, rect.height() as u32,
-                                BACKGROUND_COLOR);
+    /// Windows inhibiting idle that can be seen, not minimized, on another workspace, or covered
+    /// by an opaque window
+    fn idle_inhibitors(&self) -> Vec<usize> {
+        if ! self.windows.values().any(|window| window.inhibit_idle) {
+            return Vec::new();
+        }
 
-                let background_intersect = rect.intersection(&background_rect);
-                if ! background_intersect.is_empty(){
-                    if let Some(mut background) = self.backgrounds.get_mut(self.background_i) {
-                        self.image.roi(&background_intersect).blit(&background.roi(&background_intersect.offset(-background_rect.left(), -background_rect.top())));
-                    }
+        let mut inhibitors = Vec::new();
+        let mut above: Vec<Rect> = Vec::new();
+        for &id in self.stack() {
+            if let Some(window) = self.windows.get(&id) {
+                if ! window.visible(self.workspace) {
+                    continue;
                 }
-
-                for (i, id) in self.order.iter().enumerate().rev() {
-                    if let Some(mut window) = self.windows.get_mut(&id) {
-                        window.draw_title(&mut self.image, &rect, i == 0, if i == 0 {
-                            &mut self.window_close
-                        } else {
-                            &mut self.window_close_unfocused
-                        },&mut self.window_minimize);
-                        window.draw(&mut self.image, &rect);
-                    }
+                let rect = window.rect();
+                if window.inhibit_idle && ! above.iter().any(|cover| cover.intersection(&rect) == rect) {
+                    inhibitors.push(id);
                 }
-
-                let cursor_intersect = rect.intersection(&cursor_rect);
-                if ! cursor_intersect.is_empty() {
-                    self.image.roi(&cursor_intersect).blend(&self.cursor.roi(&cursor_intersect.offset(-cursor_rect.left(), -cursor_rect.top())));
+                if ! window.transparent {
+                    above.push(rect);
                 }
             }
         }
+        inhibitors
+    }
 
-        if self.win_tabbing {
-            self.draw_window_list();
-        }
+    /// Window ids from the top of the stack to the bottom, by layer and then by focus order
+    fn stack(&self) -> &[usize] {
+        self.order.stacked()
+    }
 
-        display.sync().unwrap();
+    /// Move a window into the layer it belongs in, after its layer or fullscreen state changed
+    fn restack(&mut self, id: usize) {
+        if let Some(window) = self.windows.get(&id) {
+            self.order.set_rank(id, self.policy.stack_rank(window, window.stack_rank()));
+        }
     }
 
-    fn win_tab(&mut self) {
-        if self.order.len() > 1 {
-            // Disable dragging
-            self.dragging = DragMode::None;
+    /// Does following the parents of a window reach a top level window, rather than a cycle that
+    /// would keep `stack` reordering dialogs forever
+    fn parents_end(&self, id: usize) -> bool {
+        let mut current = Some(id);
+        for _ in 0 .. self.windows.len() + 1 {
+            current = match current.and_then(|id| self.windows.get(&id)) {
+                Some(window) => window.parent,
+                None => return true
+            };
+        }
+        false
+    }
 
-            //Redraw old focused window
-            if let Some(id) = self.order.pop_front() {
-                if let Some(mut window) = self.windows.get_mut(&id) {
-                    schedule(&mut self.redraws, window.title_rect());
-                    schedule(&mut self.redraws, window.rect());
-                    window.event(FocusEvent {
-                        focused: false
-                    }.to_event());
+    /// Find the focused window if it exactly covers the display and nothing is stacked above it,
+    /// allowing it to be scanned out directly
+    fn fullscreen_window(&self) -> Option<usize> {
+        if let Some(id) = self.order.focused() {
+            if let Some(window) = self.windows.get(&id) {
+                let display_rect = self.display_rect(window.x, window.y);
+                if window.visible(self.workspace) && window.client_opacity == 255 && window.rect() == display_rect {
+                    let covered = self.stack().iter().take_while(|&&e| e != id).any(|e| {
+                        self.windows.get(e).map_or(false, |above| {
+                            above.visible(self.workspace) && ! above.bounds().intersection(&display_rect).is_empty()
+                        })
+                    });
+                    if ! covered {
+                        return Some(id);
+                    }
                 }
-                self.order.push_back(id);
             }
-            //Redraw new focused window
-            if let Some(id) = self.order.front() {
-                if let Some(mut window) = self.windows.get_mut(&id){
-                    schedule(&mut self.redraws, window.title_rect());
-                    schedule(&mut self.redraws, window.rect());
-                    window.event(FocusEvent {
-                        focused: true
-                    }.to_event());
+        }
+        None
+    }
+
+    /// Areas that skip compositing and copy a client buffer directly, each paired with its window
+    ///
+    /// These are the displays whose topmost window is opaque and covers all of the display, or
+    /// with bypass disabled, the display the focused window covers when it is fullscreen.
+    fn bypassed(&self) -> Vec<(Rect, usize)> {
+        // Window animations are drawn over the composited windows
+        if ! self.animations.is_empty() {
+            return Vec::new();
+        }
+
+        if ! self.bypass {
+            return self.fullscreen_window().into_iter()
+                .filter_map(|id| self.windows.get(&id).map(|window| (window.rect(), id)))
+                .collect();
+        }
+
+        let stack = self.stack();
+        let focused = self.order.focused();
+        let shadow = self.quality.transparency();
+        let mut bypassed = Vec::new();
+        for display in self.displays.iter() {
+            let display_rect = display.rect();
+            let top = stack.iter().filter_map(|id| self.windows.get(id).map(|window| (*id, window))).find(|&(id, window)| {
+                // The shadow of the focused window falls on displays beside it
+                let area = if shadow && focused == Some(id) { self.shadow.rect(&window.bounds()) } else { window.bounds() };
+                window.visible(self.workspace) && ! area.intersection(&display_rect).is_empty()
+            });
+            if let Some((id, window)) = top {
+                if window.opaque() && window.rect().intersection(&display_rect) == display_rect {
+                    bypassed.push((display_rect, id));
                 }
             }
         }
+        bypassed
     }
 
-    /// Draws a list of currently open windows in the middle of the screen
-    fn draw_window_list(&mut self) {
-        use orbfont;
-        let mut rendered_text: Vec<orbfont::Text> = vec![];
-        for id in self.order.iter() {
-            if let Some(window) = self.windows.get(id) {
-                if window.title.is_empty() {
-                    rendered_text.push(self.font.render(&format!("[unnamed #{}]", id), 16.0));
-                } else {
-                    rendered_text.push(self.font.render(&format!("{}", &window.title), 16.0));
+    /// Area of the shadow around the focused window, empty if no shadow is drawn
+    fn shadow_rect(&self) -> Rect {
+        if self.quality.transparency() && self.fullscreen_window().is_none() {
+            if let Some(id) = self.order.focused() {
+                if let Some(window) = self.windows.get(&id) {
+                    if window.visible(self.workspace) {
+                        return self.shadow.rect(&window.bounds());
+                    }
                 }
             }
         }
+        Rect::new(-1, -1, 0, 0)
+    }
 
-        let list_h = rendered_text.len() as i32 * 20 + 4;
-        let list_w = 400;
-        let target_rect = Rect::new(self.image.width()/2 - list_w/2,
-                                    self.image.height()/2 - list_h/2,
-                                    list_w, list_h);
-        // Color copied over from orbtk's window background
-        let mut image = Image::from_color(list_w, list_h, BAR_COLOR);
-        for (i, text) in rendered_text.iter().enumerate() {
-            if i == 0 {
-                image.rect(0, i as i32 * 20 + 2, list_w as u32, 20, BAR_HIGHLIGHT_COLOR);
-                text.draw(&mut image, 4, i as i32 * 20 + 4, TEXT_HIGHLIGHT_COLOR);
-            } else {
-                text.draw(&mut image, 4, i as i32 * 20 + 4, TEXT_COLOR);
+    pub fn redraw(&mut self){
+        let screen_rect = self.screen_rect();
+        let background_rect = self.background_rect();
+        let cursor_rect = self.cursor_rect();
+        let bypassed = self.bypassed();
+        // Copied, as windows are drawn while walking it
+        let stack = self.stack().to_vec();
+        let focused = self.order.focused();
+        let transparency = self.quality.transparency();
+        let start = Instant::now();
+
+        // Redraw the old and new shadow when the focused window moves, resizes, or changes
+        let shadow_rect = self.shadow_rect();
+        if shadow_rect != self.shadow_drawn {
+            if ! self.shadow_drawn.is_empty() {
+                schedule(&mut self.redraws, self.shadow_drawn);
+            }
+            if ! shadow_rect.is_empty() {
+                schedule(&mut self.redraws, shadow_rect);
             }
+            self.shadow_drawn = shadow_rect;
         }
-        self.image.roi(&target_rect).blit(&image.roi(&Rect::new(0, 0, list_w, list_h)));
-        schedule(&mut self.redraws, target_rect);
-    }
 
-    fn key_event(&mut self, event: KeyEvent) {
-        if event.scancode == 0x38 {
-            self.win_key = event.pressed;
-            // If the win key was released, stop drawing the win-tab window switcher
-            if !self.win_key {
-                self.win_tabbing = false;
+        // Redraw the task bar when its buttons or clock change
+        let taskbar_rect = self.taskbar_rect();
+        if ! taskbar_rect.is_empty() {
+            let buttons = self.taskbar_buttons();
+            if self.taskbar.update(buttons, taskbar::clock()) {
+                schedule(&mut self.redraws, taskbar_rect);
             }
-        } else if self.win_key {
-            match event.scancode {
-                orbclient::K_ESC => if event.pressed {
-                    if let Some(id) = self.order.front() {
-                        if let Some(mut window) = self.windows.get_mut(&id) {
-                            window.event(QuitEvent.to_event());
-                        }
-                    }
-                },
-                orbclient::K_TAB => if event.pressed {
-                    // Start drawing the window switcher. It's drawn by redraw()
-                    self.win_tabbing = true;
-                    self.win_tab();
-                },
-                orbclient::K_BKSP => if event.pressed {
-                    // Switch backgrounds
-                    let bg_rect = self.background_rect();
-                    schedule(&mut self.redraws, bg_rect);
+        }
 
-                    self.background_i += 1;
-                    if self.background_i >= self.backgrounds.len() {
-                        self.background_i = 0;
-                    }
+        let composited = ! self.redraws.is_empty();
 
-                    let bg_rect = self.background_rect();
-                    schedule(&mut self.redraws, bg_rect);
-                },
-                _ => if event.pressed {
-                    println!("WIN+{:X}", event.scancode);
+        // Opening windows are only drawn by their animation
+        let opening: Vec<usize> = self.animations.iter()
+            .filter(|animation| animation.kind == WindowAnimationKind::Open)
+            .map(|animation| animation.id)
+            .collect();
+
+        // Take the redraws, as drawing the task bar borrows all of self
+        let mut redraws = Region::new();
+        for mut rect in self.redraws.take() {
+            // Blurred backgrounds spread changes beneath a window by the blur radius, so redraw
+            // that much more of it
+            if self.blur_radius > 0 && transparency {
+                let radius = self.blur_radius;
+                let blurred = Rect::new(rect.left() - radius, rect.top() - radius,
+                                        rect.width() + 2 * radius, rect.height() + 2 * radius);
+                for window in self.windows.values() {
+                    if window.transparent && window.visible(self.workspace) && ! rect.intersection(&window.rect()).is_empty() {
+                        rect = rect.container(&blurred.intersection(&window.rect()));
+                    }
                 }
             }
-        } else if let Some(id) = self.order.front() {
-            if let Some(mut window) = self.windows.get_mut(&id) {
-                window.event(event.to_event());
-            }
+            redraws.add(rect.intersection(&screen_rect));
         }
-    }
 
-    fn mouse_event(&mut self, event: MouseEvent) {
-        // Check for focus switch, dragging, and forward mouse events to applications
-        match self.dragging {
-            DragMode::None => {
-                let mut focus = 0;
-                let mut i = 0;
-                for &id in self.order.iter() {
+        // Composite straight onto each display in turn, then draw the overlays over it
+        for i in 0..self.displays.len() {
+            mem::swap(&mut self.image, self.displays[i].image_mut());
+            let display_rect = self.image.bounds();
+            let direct = bypassed.iter()
+                .find(|&&(bypass_rect, _)| bypass_rect.intersection(&display_rect) == display_rect)
+                .map(|&(_, id)| id);
+
+            for rect in redraws.iter() {
+                let piece = rect.intersection(&display_rect);
+                if piece.is_empty() {
+                    continue;
+                }
+
+                if let Some(id) = direct {
+                    // Nothing else is visible, skip compositing and copy the client buffer
                     if let Some(mut window) = self.windows.get_mut(&id) {
-                        if window.rect().contains(event.x, event.y) {
-                            let mut window_event = event.to_event();
-                            window_event.a -= window.x as i64;
-                            window_event.b -= window.y as i64;
-                            window.event(window_event);
-                            if event.left_button  && ! self.cursor_left
-                            || event.middle_button && ! self.cursor_middle
-                            || event.right_button && ! self.cursor_right {
-                                focus = i;
-                            }
-                            break;
-                        } else if window.title_rect().contains(event.x, event.y) {
-                            if event.left_button && ! self.cursor_left  {
-                                focus = i;
-                                if window.exit_contains(event.x, event.y) {
-                                    window.event(QuitEvent.to_event());
-                                } else {
-                                    self.dragging = DragMode::Title(id, event.x, event.y);
-                                }
-                            }
-                            break;
-                        } else if window.right_border_rect().contains(event.x, event.y) {
-                            //TODO: Change cursor to resize cursor
-                            if event.left_button && ! self.cursor_left  {
-                                focus = i;
-                                self.dragging = DragMode::RightBorder(id, event.x - (window.x + window.width()));
+                        window.draw_direct(&mut self.image, &piece);
+                    }
+                } else {
+                    // Only clear the area the background image does not cover
+                    let background_intersect = piece.intersection(&background_rect);
+                    let mut uncovered = Region::from_rect(piece);
+                    if ! background_intersect.is_empty(){
+                        if let Some(background) = self.backgrounds.get(self.background_i) {
+                            self.image.roi_mut(&background_intersect).blit(&background.roi(&background_intersect.offset(-background_rect.left(), -background_rect.top())));
+                            uncovered.subtract(&background_intersect);
+                        }
+                    }
+                    for rect in uncovered.iter() {
+                        self.image.roi_mut(rect).fill(self.theme.background);
+                    }
+
+                    let close_armed = match self.dragging {
+                        DragMode::Close(id, _) => Some(id),
+                        _ => self.close_armed.map(|(id, _)| id)
+                    };
+
+                    for id in stack.iter().rev() {
+                        if let Some(mut window) = self.windows.get_mut(&id) {
+                            if ! window.visible(self.workspace) || opening.contains(id) {
+                                continue;
                             }
-                            break;
-                        } else if window.bottom_border_rect().contains(event.x, event.y) {
-                            //TODO: Change cursor to resize cursor
-                            if event.left_button && ! self.cursor_left  {
-                                focus = i;
-                                self.dragging = DragMode::BottomBorder(id, event.y - (window.y + window.height()));
+                            let is_focused = focused == Some(*id);
+                            if is_focused && ! self.shadow_drawn.is_empty() {
+                                self.shadow.draw(&mut self.image, &window.bounds(), &piece);
                             }
-                            break;
-                        } else if window.bottom_right_border_rect().contains(event.x, event.y) {
-                            //TODO: Change cursor to resize cursor
-                            if event.left_button && ! self.cursor_left  {
-                                focus = i;
-                                self.dragging = DragMode::BottomRightBorder(id, event.x - (window.x + window.width()), event.y - (window.y + window.height()));
+                            let buttons = Buttons {
+                                close: if is_focused { &self.window_close } else { &self.window_close_unfocused },
+                                maximize: &self.window_maximize,
+                                minimize: &self.window_minimize
+                            };
+                            self.decorator.draw_title(window, &mut self.image, &piece, is_focused, &buttons, close_armed == Some(*id));
+                            if transparency {
+                                if window.transparent && self.blur_radius > 0 {
+                                    let blur_rect = piece.intersection(&window.rect());
+                                    self.image.roi_mut(&blur_rect).box_blur(self.blur_radius);
+                                }
+                                window.draw(&mut self.image, &piece);
+                            } else {
+                                window.draw_direct(&mut self.image, &piece);
                             }
-                            break;
                         }
                     }
-                    i += 1;
-                }
-                if focus > 0 {
-                    //Redraw old focused window
-                    if let Some(id) = self.order.front() {
-                        if let Some(mut window) = self.windows.get_mut(&id){
-                            schedule(&mut self.redraws, window.title_rect());
-                            schedule(&mut self.redraws, window.rect());
-                            window.event(FocusEvent {
-                                focused: false
-                            }.to_event());
-                        }
+
+                    let taskbar_intersect = piece.intersection(&taskbar_rect);
+                    if ! taskbar_intersect.is_empty() {
+                        self.draw_taskbar(&taskbar_rect, &taskbar_intersect);
                     }
-                    //Redraw new focused window
-                    if let Some(id) = self.order.remove(focus) {
-                        if let Some(mut window) = self.windows.get_mut(&id){
-                            schedule(&mut self.redraws, window.title_rect());
-                            schedule(&mut self.redraws, window.rect());
-                            window.event(FocusEvent {
-                                focused: true
-                            }.to_event());
-                        }
-                        self.order.push_front(id);
+                }
+
+                let cursor_intersect = piece.intersection(&cursor_rect);
+                if ! cursor_intersect.is_empty() {
+                    let cursor = match self.cursors.get(self.shown_cursor_shape()) {
+                        Some(image) => image,
+                        None => if self.shake_until.is_some() { &self.cursor_large } else { &self.cursor }
+                    };
+                    self.image.roi_mut(&cursor_intersect).blend(&cursor.roi(&cursor_intersect.offset(-cursor_rect.left(), -cursor_rect.top())));
+                }
+
+                for seat in self.seats.iter() {
+                    let seat_rect = Rect::new(seat.x, seat.y, self.cursor.width(), self.cursor.height());
+                    let seat_intersect = piece.intersection(&seat_rect);
+                    if ! seat_intersect.is_empty() {
+                        self.image.roi_mut(&seat_intersect).blend(&self.cursor.roi(&seat_intersect.offset(-seat_rect.left(), -seat_rect.top())));
                     }
                 }
-            },
-            DragMode::Title(window_id, drag_x, drag_y) => {
-                if event.left_button {
-                    if let Some(mut window) = self.windows.get_mut(&window_id) {
-                        if drag_x != event.x || drag_y != event.y {
-                            schedule(&mut self.redraws, window.title_rect());
-                            schedule(&mut self.redraws, window.rect());
+            }
 
-                            window.x += event.x - drag_x;
-                            window.y += event.y - drag_y;
+            self.draw_overlays(&redraws);
 
-                            let move_event = MoveEvent {
-                                x: window.x,
-                                y: window.y
-                            }.to_event();
-                            window.event(move_event);
+            mem::swap(&mut self.image, self.displays[i].image_mut());
+        }
 
-                            self.dragging = DragMode::Title(window_id, event.x, event.y);
+        // Push composited areas and overlays, which schedule themselves, to the displays
+        let mut drawn = redraws.clone();
+        drawn.union(&self.redraws);
+        for display in self.displays.iter_mut() {
+            for rect in drawn.iter() {
+                display.damage(rect, self.dim);
+            }
+            display.sync();
+        }
 
-                            schedule(&mut self.redraws, window.title_rect());
-                            schedule(&mut self.redraws, window.rect());
-                        }
-                    } else {
-                        self.dragging = DragMode::None;
-                    }
-                } else {
-                    self.dragging = DragMode::None;
+        for (_id, window) in self.windows.iter_mut() {
+            window.latency.present();
+        }
+
+        if composited && self.quality.frame(start.elapsed()) {
+            schedule(&mut self.redraws, screen_rect);
+        }
+
+        // Record the area pushed to the displays, paced by the composites
+        let recorded = drawn.iter()
+            .fold(None, |area: Option<Rect>, rect| Some(area.map_or(*rect, |area| area.container(rect))));
+        if let (Some(area), true) = (recorded, self.recorder.is_some()) {
+            let image = self.screen_image(&area);
+            let result = match self.recorder {
+                Some(ref mut recorder) => recorder.frame(&area, image),
+                None => Ok(())
+            };
+            if let Err(err) = result {
+                // Finish the file, the writing thread's error explaining why the frame failed
+                let err = match self.recorder.take().map(|recorder| recorder.stop()) {
+                    Some(Err(stop_err)) => stop_err,
+                    _ => err
+                };
+                self.status.error(format!("failed to record screen: {}", err));
+            }
+        }
+
+        if composited && self.captures.values().any(|capture| capture.armed && capture.pending()) {
+            let screen = self.screen_image(&screen_rect);
+            for capture in self.captures.values_mut() {
+                if capture.armed && capture.pending() {
+                    capture.take(&screen);
                 }
-            },
-            DragMode::RightBorder(window_id, off_x) => {
-                if event.left_button {
-                    if let Some(mut window) = self.windows.get_mut(&window_id) {
-                        let w = event.x - off_x - window.x;
-                        if w > 0 && w != window.width()  {
-                            let resize_event = ResizeEvent {
-                                width: w as u32,
-                                height: window.height() as u32
-                            }.to_event();
-                            window.event(resize_event);
-                        }
+            }
+        }
+
+        if composited {
+            self.status.frame();
+        }
+    }
+
+    /// Draws the overlays above the windows onto the display being drawn, given the areas
+    /// composited this frame
+    fn draw_overlays(&mut self, composited: &Region) {
+        self.draw_window_animations();
+
+        self.draw_overview(composited);
+
+        self.draw_notifications();
+
+        self.draw_preview();
+
+        if self.win_tabbing {
+            self.draw_window_list();
+        }
+
+        if self.perf_hud {
+            self.draw_perf_hud();
+        }
+
+        if self.inspecting {
+            self.draw_inspector();
+        }
+
+        if self.launcher.active {
+            self.draw_launcher();
+        }
+    }
+
+    /// Copy of an area of the displays, black where no display covers it
+    fn screen_image(&self, rect: &Rect) -> Image {
+        let mut image = Image::new(rect.width(), rect.height());
+        for display in self.displays.iter() {
+            let intersect = rect.intersection(&display.rect());
+            if ! intersect.is_empty() {
+                image.roi_mut(&intersect.offset(-rect.left(), -rect.top())).blit(&display.roi(&intersect));
+            }
+        }
+        image
+    }
+
+    /// Report of compositor state, returned by reading `orbital:control`
+    fn control_report(&self) -> String {
+        let mut report = String::new();
+        report.push_str("[latency]\n");
+        report.push_str(&self.latency_report());
+        report.push_str("[memory]\n");
+        report.push_str(&self.memory_report());
+        report.push_str("[record]\n");
+        let recording = self.recorder.as_ref().map_or("none", |recorder| &recorder.path);
+        report.push_str(&format!("recording={}\n", recording));
+        report.push_str("[inspect]\n");
+        if let Some(id) = self.inspected {
+            if let Some(window) = self.windows.get(&id) {
+                report.push_str(&window.inspect(id));
+            }
+        }
+        report.push_str("[idle]\n");
+        report.push_str(&format!("idle={}\n", self.idle));
+        for id in self.idle_inhibitors() {
+            if let Some(window) = self.windows.get(&id) {
+                report.push_str(&format!("inhibitor {} {}\n", id, window.title));
+            }
+        }
+        report
+    }
+
+    /// Report of startup phase, health, displays and clients, returned by reading `orbital:status`
+    fn status_report(&self) -> String {
+        let mut report = self.status.report();
+        report.push_str(&format!("displays={}\n", self.displays.len()));
+        for (i, display) in self.displays.iter().enumerate() {
+            report.push_str(&format!("display {} {}x{} at {},{}\n", i, display.width(), display.height(), display.x, display.y));
+        }
+        let mut owners: Vec<usize> = self.windows.values().map(|window| window.owner).collect();
+        owners.sort();
+        owners.dedup();
+        report.push_str(&format!("windows={}\n", self.windows.len()));
+        report.push_str(&format!("clients={}\n", owners.len()));
+        for seat in self.seats.iter() {
+            let focused = seat.focused.map_or("none".to_string(), |id| id.to_string());
+            report.push_str(&format!("seat {} at {},{} focused={}\n", seat.name, seat.x, seat.y, focused));
+        }
+        report
+    }
+
+    /// Report memory used by each window and by compositor subsystems, in bytes
+    fn memory_report(&self) -> String {
+        let mut report = String::new();
+        let mut total = 0;
+        for (id, window) in self.windows.iter() {
+            let buffer = window.buffer_bytes();
+            let title = window.title_bytes();
+            let events = window.event_bytes();
+            report.push_str(&format!("window {} buffer={} title={} events={} queued={} {}\n",
+                                     id, buffer, title, events, window.events.len(), window.title));
+            total += buffer + title + events;
+        }
+
+        let backgrounds = self.backgrounds.iter().fold(0, |sum, background| sum + background.bytes());
+        let theme = self.window_close.bytes() + self.window_close_unfocused.bytes()
+                    + self.window_maximize.bytes() + self.window_minimize.bytes() + self.cursor.bytes() + self.cursors.bytes();
+        // Overlays are drawn straight onto the displays, so only the images they keep count
+        let overlays = self.notifications.values()
+            .filter_map(|handle| handle.notification.as_ref().and_then(|notification| notification.icon.as_ref()))
+            .fold(self.cursor_large.bytes(), |sum, icon| sum + icon.bytes());
+        report.push_str(&format!("backgrounds={}\n", backgrounds));
+        report.push_str(&format!("theme={}\n", theme));
+        report.push_str(&format!("overlays={}\n", overlays));
+        report.push_str(&format!("text_cache={}\n", self.text_cache.bytes()));
+        report.push_str(&format!("clipboard={} {}\n", self.clipboard.bytes(), self.clipboard.mime));
+        report.push_str(&format!("todo={}\n", self.todo.len()));
+        total += backgrounds + theme + overlays + self.text_cache.bytes() + self.clipboard.bytes();
+        report.push_str(&format!("total={}\n", total));
+        report
+    }
+
+    /// Report input to present latency of each window, in microseconds
+    fn latency_report(&self) -> String {
+        let mut report = String::new();
+        for (id, window) in self.windows.iter() {
+            report.push_str(&format!("{} {} {} {} {}\n",
+                                     id, window.latency.last, window.latency.average(), window.latency.max,
+                                     window.title));
+        }
+        report
+    }
+
+    /// Draws input latency statistics in the top right corner of the screen
+    fn draw_perf_hud(&mut self) {
+        let mut lines = Vec::new();
+        for id in self.order.iter() {
+            if let Some(window) = self.windows.get(id) {
+                lines.push(format!("#{}: {:.1} ms avg {:.1} ms max {:.1} ms",
+                                   id,
+                                   window.latency.last as f32 / 1000.0,
+                                   window.latency.average() as f32 / 1000.0,
+                                   window.latency.max as f32 / 1000.0));
+            }
+        }
+
+        let hud_h = lines.len() as i32 * 20 + 4;
+        let hud_w = 320;
+        let target_rect = Rect::new(self.screen_rect().width() - hud_w, 0, hud_w, hud_h);
+        self.image.roi_mut(&target_rect).fill(self.theme.bar);
+        for (i, line) in lines.iter().enumerate() {
+            self.draw_text(line, target_rect.left() + 4, target_rect.top() + i as i32 * 20 + 4, self.theme.text, self.theme.bar, &target_rect);
+        }
+        schedule(&mut self.redraws, target_rect);
+    }
+
+    /// Start picking a window to inspect, or stop inspecting
+    fn toggle_inspect(&mut self) {
+        self.inspecting = ! self.inspecting;
+        if ! self.inspecting {
+            self.inspected = None;
+        }
+        let screen_rect = self.screen_rect();
+        schedule(&mut self.redraws, screen_rect);
+    }
+
+    /// Inspect the window under a click, printing its metadata to the log
+    fn inspect_click(&mut self, x: i32, y: i32) {
+        self.inspected = self.window_at(x, y);
+        if let Some(id) = self.inspected {
+            if let Some(window) = self.windows.get(&id) {
+                print!("orbital: inspect window\n{}", window.inspect(id));
+            }
+        }
+    }
+
+    /// Outline the areas of the inspected window, or of the window under the cursor until one is
+    /// picked, with its recent damage and metadata
+    fn draw_inspector(&mut self) {
+        let id = match self.inspected.or_else(|| self.window_at(self.cursor_x, self.cursor_y)) {
+            Some(id) => id,
+            None => return
+        };
+        let (areas, damage, lines) = match self.windows.get(&id) {
+            Some(window) => {
+                let mut areas = vec![
+                    (window.bounds(), self.theme.bar_highlight),
+                    (window.title_rect(), self.theme.text_highlight),
+                    (window.rect(), self.theme.attention)
+                ];
+                for rect in window.input_region.iter() {
+                    areas.push((rect.offset(window.x, window.y).intersection(&window.rect()), self.theme.text_highlight));
+                }
+                let damage: Vec<Rect> = window.damage_history.iter().map(|rect| rect.offset(window.x, window.y)).collect();
+                let lines: Vec<String> = window.inspect(id).lines().map(|line| line.to_string()).collect();
+                (areas, damage, lines)
+            },
+            None => return
+        };
+
+        let screen_rect = self.screen_rect();
+        for rect in damage.iter() {
+            self.image.roi_mut(&rect.intersection(&screen_rect)).tint(self.theme.attention, 64);
+        }
+        for &(rect, color) in areas.iter() {
+            if rect.is_empty() {
+                continue;
+            }
+            let edges = [
+                Rect::new(rect.left(), rect.top(), rect.width(), 1),
+                Rect::new(rect.left(), rect.bottom() - 1, rect.width(), 1),
+                Rect::new(rect.left(), rect.top(), 1, rect.height()),
+                Rect::new(rect.right() - 1, rect.top(), 1, rect.height())
+            ];
+            for edge in edges.iter() {
+                self.image.roi_mut(&edge.intersection(&screen_rect)).fill(color);
+            }
+            schedule(&mut self.redraws, rect);
+        }
+        for rect in damage.iter() {
+            schedule(&mut self.redraws, *rect);
+        }
+
+        let box_h = lines.len() as i32 * 20 + 4;
+        let box_w = 360;
+        let target_rect = Rect::new(0, 0, box_w, box_h).intersection(&screen_rect);
+        self.image.roi_mut(&target_rect).fill(self.theme.bar);
+        for (i, line) in lines.iter().enumerate() {
+            self.draw_text(line, target_rect.left() + 4, target_rect.top() + i as i32 * 20 + 4, self.theme.text, self.theme.bar, &target_rect);
+        }
+        schedule(&mut self.redraws, target_rect);
+    }
+
+    /// Draw a line of text from the text cache, clipped to a rect
+    fn draw_text(&mut self, text: &str, x: i32, y: i32, color: Color, background: Color, clip: &Rect) {
+        let image = self.text_cache.render(&self.font, text, 16.0, color, background);
+        let image_rect = Rect::new(x, y, image.width(), image.height());
+        let image_intersect = clip.intersection(&image_rect);
+        if ! image_intersect.is_empty() {
+            self.image.roi_mut(&image_intersect).blit(&image.roi(&image_intersect.offset(-image_rect.left(), -image_rect.top())));
+        }
+    }
+
+    /// Replace the clipboard with data written to a clipboard handle
+    fn commit_clipboard(&mut self, id: usize) {
+        let clipboard_opt = match self.clipboards.get_mut(&id) {
+            Some(handle) => handle.commit(),
+            None => None
+        };
+        if let Some(clipboard) = clipboard_opt {
+            self.clipboard = clipboard;
+            self.hooks.run("copy", None);
+        }
+    }
+
+    /// Handle a packet, only allowing the process that opened a window, running as the same user,
+    /// or root, to access its contents or path
+    pub fn handle_checked(&mut self, packet: &mut Packet) {
+        let guarded = match packet.a {
+            SYS_READ | SYS_WRITE | SYS_FMAP | SYS_FPATH | SYS_FSYNC => true,
+            _ => false
+        };
+        if guarded && packet.uid != 0 {
+            if let Some(window) = self.windows.get(&packet.b) {
+                if window.owner != packet.pid || window.owner_uid != packet.uid {
+                    packet.a = Error::mux(Err(Error::new(EACCES)));
+                    return;
+                }
+            }
+        }
+
+        // New windows belong to the caller, which decides what they may do as they open
+        self.caller_pid = packet.pid;
+        self.handle(packet);
+    }
+
+    /// Should a read that finds no events wait for them, rather than failing with EAGAIN
+    ///
+    /// Windows opened without the `a` flag are sync, their reads wait until events arrive.
+    /// Captures wait until the screen is captured, notifications until they are closed, and
+    /// watched window lists until windows change. Reads of other ids are delayed as they always
+    /// were, which only matters for handles that fail with EAGAIN.
+    pub fn read_blocks(&self, id: usize) -> bool {
+        match self.windows.get(&id) {
+            Some(window) => ! window.async,
+            None => self.window_lists.get(&id).map_or(true, |list| list.watch)
+        }
+    }
+
+    /// Allocate a handle id, never returning an id that is still open, or that belongs to a
+    /// closed window still fading out or to an animation
+    fn alloc_id(&mut self) -> usize {
+        loop {
+            let id = self.next_id as usize;
+            self.next_id += 1;
+            if self.next_id < 0 {
+                self.next_id = 1;
+            }
+            if ! self.windows.contains_key(&id) && ! self.controls.contains_key(&id) && ! self.clipboards.contains_key(&id)
+            && ! self.captures.contains_key(&id) && ! self.notifications.contains_key(&id)
+            && ! self.window_lists.contains_key(&id) && ! self.closed.contains_key(&id)
+            && ! self.animations.iter().any(|animation| animation.id == id)
+            && ! self.moves.iter().any(|animation| animation.id == id) {
+                return id;
+            }
+        }
+    }
+
+    /// Hide a window and move it to the back, focusing the next window
+    fn minimize(&mut self, id: usize) {
+        let target = self.minimize_target(id);
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            if window.minimized {
+                return;
+            }
+            window.minimized = true;
+            if self.window_animations && ! self.reduce_motion && window.workspace == self.workspace {
+                self.animations.retain(|animation| animation.id != id);
+                self.animations.push(WindowAnimation::minimize(id, window.rect(), target));
+            }
+            schedule(&mut self.redraws, window.title_rect());
+            schedule(&mut self.redraws, window.rect());
+            window.event(FocusEvent {
+                focused: false
+            }.to_event());
+            self.hooks.run("minimize", Some((id, &window.title)));
+        }
+
+        let focused = self.order.is_focused(id);
+        self.order.lower(id);
+        if focused {
+            self.refocus();
+        }
+    }
+
+    /// Area a window shrinks into as it is minimized, its task bar button or the bottom of the
+    /// screen without a task bar
+    fn minimize_target(&self, id: usize) -> Rect {
+        let taskbar_rect = self.taskbar_rect();
+        if ! taskbar_rect.is_empty() {
+            let buttons = self.taskbar_buttons();
+            let button_rects = self.taskbar.button_rects(&taskbar_rect, buttons.len());
+            if let Some((_, rect)) = buttons.iter().zip(button_rects.iter()).find(|&(button, _)| button.id == id) {
+                return *rect;
+            }
+            return taskbar_rect;
+        }
+        let screen_rect = self.screen_rect();
+        Rect::new(screen_rect.left() + screen_rect.width()/2 - 32, screen_rect.bottom() - 16, 64, 16)
+    }
+
+    /// Send a window behind the others in its layer, focusing the most recent window if it was
+    /// focused
+    fn lower(&mut self, id: usize) {
+        if let Some(window) = self.windows.get(&id) {
+            schedule(&mut self.redraws, window.bounds());
+        } else {
+            return;
+        }
+
+        let focused = self.order.is_focused(id);
+        self.order.lower(id);
+        if focused {
+            let recent = match self.recent_window() {
+                Some(recent) if recent != id && self.may_focus(recent) => Some(recent),
+                _ => None
+            };
+            if let Some(recent) = recent {
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    schedule(&mut self.redraws, window.title_rect());
+                    window.event(FocusEvent {
+                        focused: false
+                    }.to_event());
+                }
+                self.refocus_window(recent);
+            } else {
+                self.order.raise(id);
+            }
+        }
+    }
+
+    /// Move a window between the normal and dock layers, keeping it above normal windows
+    fn toggle_on_top(&mut self, id: usize) {
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            window.layer = match window.layer {
+                Layer::Normal => Layer::Dock,
+                Layer::Dock => Layer::Normal,
+                layer => layer
+            };
+            schedule(&mut self.redraws, window.bounds());
+        }
+        self.restack(id);
+    }
+
+    /// Run the action bound to a click on a window
+    fn click_action(&mut self, id: usize, action: ClickAction) {
+        match action {
+            ClickAction::None => (),
+            ClickAction::Lower => self.lower(id),
+            ClickAction::OnTop => self.toggle_on_top(id),
+            ClickAction::Maximize => self.toggle_maximize(id),
+            ClickAction::Minimize => self.minimize(id),
+            ClickAction::Close => if let Some(mut window) = self.windows.get_mut(&id) {
+                window.event(QuitEvent.to_event());
+            }
+        }
+    }
+
+    /// Show a minimized window again
+    fn unminimize(&mut self, id: usize) {
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            if window.minimized {
+                window.minimized = false;
+                schedule(&mut self.redraws, window.title_rect());
+                schedule(&mut self.redraws, window.rect());
+                self.hooks.run("restore", Some((id, &window.title)));
+            }
+        }
+    }
+
+    /// Show the windows of another workspace, focusing the most recent one
+    fn switch_workspace(&mut self, workspace: usize) {
+        if workspace == self.workspace {
+            return;
+        }
+
+        if let Some(id) = self.order.focused() {
+            if let Some(mut window) = self.windows.get_mut(&id) {
+                window.event(FocusEvent {
+                    focused: false
+                }.to_event());
+            }
+        }
+
+        self.workspace = workspace;
+        let screen_rect = self.screen_rect();
+        schedule(&mut self.redraws, screen_rect);
+        self.hooks.run_env("workspace", &[("ORBITAL_WORKSPACE", format!("{}", workspace + 1))]);
+        self.refocus();
+    }
+
+    /// Move a window to another workspace, focusing the next window if it was focused
+    fn move_to_workspace(&mut self, id: usize, workspace: usize) {
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            if window.workspace == workspace {
+                return;
+            }
+            window.workspace = workspace;
+            schedule(&mut self.redraws, window.title_rect());
+            schedule(&mut self.redraws, window.rect());
+        }
+
+        if self.order.is_focused(id) {
+            if let Some(mut window) = self.windows.get_mut(&id) {
+                window.event(FocusEvent {
+                    focused: false
+                }.to_event());
+            }
+            self.refocus();
+        }
+    }
+
+    fn decorations(&self) -> Decorations {
+        self.decorator.decorations(self.touch, self.touch_title_height)
+    }
+
+    /// Switch between normal and enlarged decorations
+    fn set_touch(&mut self, touch: bool) {
+        if touch != self.touch {
+            self.touch = touch;
+            let decorations = self.decorations();
+            for window in self.windows.values_mut() {
+                window.decorations = decorations;
+            }
+            let screen_rect = self.screen_rect();
+            schedule(&mut self.redraws, screen_rect);
+        }
+    }
+
+    /// Read the config again, applying backgrounds, the cursor, button images, theme colors, click
+    /// actions and window rules
+    pub fn reload_config(&mut self) {
+        println!("orbital: reloading config '{}'", self.config_path);
+        // Remember the version read, so the watcher does not read it again
+        self.config_modified = fs::metadata(&self.config_path).and_then(|metadata| metadata.modified()).ok();
+        let config = Config::from_path(&self.config_path);
+
+        self.cursor = fallback::load(&config.cursor, "cursor", config.fallback_images, fallback::cursor);
+        self.cursors = Cursors::new(&config);
+        self.shake_cursor_scale = max(1, config.shake_cursor_scale);
+        self.shake_duration = Duration::from_millis(config.shake_duration);
+        self.cursor_large = scale_image(&self.cursor, self.cursor.width() * self.shake_cursor_scale,
+                                        self.cursor.height() * self.shake_cursor_scale, self.quality.filter());
+        self.window_close = fallback::load(&config.window_close, "close", config.fallback_images, fallback::close);
+        self.window_close_unfocused = fallback::load(&config.window_close_unfocused, "unfocused close", config.fallback_images, fallback::close_unfocused);
+        self.window_maximize = fallback::load(&config.window_maximize, "maximize", config.fallback_images, fallback::maximize);
+        self.window_minimize = fallback::load(&config.window_minimize, "minimize", config.fallback_images, fallback::minimize);
+
+        self.decorator = decorator::from_str(&config.decoration_style);
+        self.policy = policy::from_str(&config.window_policy);
+        self.placement = Placement::from_str(&config.window_placement);
+        self.rules = rules::from_config(&config.rules);
+        self.title_middle_click = ClickAction::from_str(&config.title_middle_click);
+        self.title_right_click = ClickAction::from_str(&config.title_right_click);
+        self.win_middle_click = ClickAction::from_str(&config.win_middle_click);
+        self.scroll_direction.reload(config.natural_scrolling, &config.natural_scrolling_device);
+        if config.frame_rate != self.frame_rate {
+            self.frame_rate = config.frame_rate;
+            self.frame_clock = FrameClock::new(config.frame_rate);
+        }
+        let reduce_motion_changed = config.reduce_motion != self.reduce_motion;
+        self.reduce_motion = config.reduce_motion;
+        let decorations = self.decorations();
+        for window in self.windows.values_mut() {
+            window.decorations = decorations;
+            if reduce_motion_changed {
+                window.event(reduce_motion_event(config.reduce_motion));
+            }
+        }
+
+        // Keep a variant picked with the control scheme
+        let forced = self.theme_schedule.forced;
+        self.theme_schedule = ThemeSchedule::new(&config);
+        self.theme_schedule.forced = forced;
+        self.theme_mode = self.theme_schedule.mode();
+        self.background_mode = BackgroundMode::from_str(&config.background_mode);
+        self.apply_theme();
+    }
+
+    /// Show the theme and backgrounds of the current variant, redrawing everything
+    fn apply_theme(&mut self) {
+        let screen_rect = self.screen_rect();
+        let (width, height) = (screen_rect.width(), screen_rect.height());
+        self.backgrounds = load_backgrounds(self.theme_schedule.backgrounds(self.theme_mode), self.background_mode,
+                                            width, height, &self.quality);
+        if self.background_i >= self.backgrounds.len() {
+            self.background_i = 0;
+        }
+
+        self.theme = self.theme_schedule.theme(self.theme_mode);
+        for window in self.windows.values_mut() {
+            window.set_theme(self.theme, &self.font);
+        }
+
+        let screen_rect = self.screen_rect();
+        schedule(&mut self.redraws, screen_rect);
+    }
+
+    /// Switch to the variant for the time of day or the one picked, returns true if it changed
+    fn update_theme_mode(&mut self) -> bool {
+        let mode = self.theme_schedule.mode();
+        if mode == self.theme_mode {
+            return false;
+        }
+        self.theme_mode = mode;
+        self.apply_theme();
+        true
+    }
+
+    /// Reload the config if it was modified, checking at most once a second
+    fn check_config(&mut self) -> bool {
+        if self.config_checked.elapsed() < Duration::from_secs(1) {
+            return false;
+        }
+        self.config_checked = Instant::now();
+
+        let modified = fs::metadata(&self.config_path).and_then(|metadata| metadata.modified()).ok();
+        if modified.is_some() && modified != self.config_modified {
+            self.reload_config();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Record the kind of device used for input, telling all windows when it changes
+    fn set_input_source(&mut self, source: InputSource) {
+        if source != self.input_source {
+            self.input_source = source;
+            for window in self.windows.values_mut() {
+                window.event(source.to_event());
+            }
+        }
+    }
+
+    /// An input device was plugged in or removed
+    pub fn input_device(&mut self, name: &str, added: bool) {
+        if added {
+            println!("orbital: input device '{}' added", name);
+            self.hooks.run_device("input_added", name);
+        } else {
+            println!("orbital: input device '{}' removed", name);
+            // Modifiers held on a removed keyboard will never be released
+            self.win_key = false;
+            self.shift_key = false;
+            self.hooks.run_device("input_removed", name);
+        }
+    }
+
+    /// Rect of the display containing a point, or the nearest display
+    fn display_rect(&self, x: i32, y: i32) -> Rect {
+        let mut nearest = self.screen_rect();
+        let mut nearest_distance = i32::max_value();
+        for display in self.displays.iter() {
+            let rect = display.rect();
+            let dx = max(0, max(rect.left() - x, x - (rect.right() - 1)));
+            let dy = max(0, max(rect.top() - y, y - (rect.bottom() - 1)));
+            if dx + dy < nearest_distance {
+                nearest = rect;
+                nearest_distance = dx + dy;
+            }
+        }
+        nearest
+    }
+
+    /// Area of the display containing a point available for windows, excluding any bars
+    fn work_rect(&self, x: i32, y: i32) -> Rect {
+        let display_rect = self.display_rect(x, y);
+        if self.displays.get(0).map_or(false, |display| display.rect() == display_rect) {
+            self.taskbar.work_rect(&display_rect)
+        } else {
+            display_rect
+        }
+    }
+
+    /// Areas of every display available for windows
+    fn work_rects(&self) -> Vec<Rect> {
+        self.displays.iter().map(|display| {
+            let display_rect = display.rect();
+            self.work_rect(display_rect.left(), display_rect.top())
+        }).collect()
+    }
+
+    /// Area of the task bar, empty if there is none or a fullscreen window hides it
+    fn taskbar_rect(&self) -> Rect {
+        let rect = match self.displays.get(0) {
+            Some(display) => self.taskbar.rect(&display.rect()),
+            None => return Rect::new(-1, -1, 0, 0)
+        };
+        let hidden = self.windows.values().any(|window| {
+            window.visible(self.workspace) && window.fullscreen.is_some() && ! window.rect().intersection(&rect).is_empty()
+        });
+        if hidden {
+            Rect::new(-1, -1, 0, 0)
+        } else {
+            rect
+        }
+    }
+
+    /// Buttons of the windows on the task bar, in the order they were opened
+    fn taskbar_buttons(&self) -> Vec<TaskButton> {
+        let focused = self.order.focused();
+        self.windows.iter().filter(|&(_, window)| {
+            window.workspace == self.workspace && ! window.popup && window.parent.is_none() && window.layer == Layer::Normal
+        }).map(|(&id, window)| TaskButton {
+            id: id,
+            title: window.title.clone(),
+            focused: focused == Some(id),
+            minimized: window.minimized
+        }).collect()
+    }
+
+    /// Find the window whose task bar button is under a point
+    fn taskbar_button_at(&self, x: i32, y: i32) -> Option<usize> {
+        let buttons = self.taskbar_buttons();
+        let button_rects = self.taskbar.button_rects(&self.taskbar_rect(), buttons.len());
+        buttons.iter().zip(button_rects.iter()).find(|&(_, rect)| rect.contains(x, y)).map(|(button, _)| button.id)
+    }
+
+    /// Draws the part of the task bar inside a clip rect
+    fn draw_taskbar(&mut self, rect: &Rect, clip: &Rect) {
+        self.image.roi_mut(clip).fill(self.theme.bar);
+
+        let buttons = self.taskbar_buttons();
+        let button_rects = self.taskbar.button_rects(rect, buttons.len());
+        for (button, button_rect) in buttons.iter().zip(button_rects.iter()) {
+            let button_clip = clip.intersection(button_rect);
+            if button_clip.is_empty() {
+                continue;
+            }
+
+            let background = if button.focused { self.theme.bar_highlight } else { self.theme.bar };
+            let color = if button.focused { self.theme.text_highlight } else { self.theme.text };
+            if button.focused {
+                self.image.roi_mut(&button_clip).fill(background);
+            }
+            let title = window_label(button.id, &button.title);
+            let title = if button.minimized { format!("({})", title) } else { title };
+            self.draw_text(&title, button_rect.left() + 4, button_rect.top() + 2, color, background, &button_clip);
+        }
+
+        let clock_rect = self.taskbar.clock_rect(rect);
+        let clock_clip = clip.intersection(&clock_rect);
+        if ! clock_clip.is_empty() {
+            let (text, background) = (self.theme.text, self.theme.bar);
+            self.draw_text(&taskbar::clock(), clock_rect.left() + 8, clock_rect.top() + 4, text, background, &clock_clip);
+        }
+    }
+
+    /// Run a command written to `orbital:manage` on the window it names
+    ///
+    /// The commands are `focus <id>`, which also restores the window and shows its workspace,
+    /// `minimize <id>`, `close <id>`, which asks the client to close, and `move <id> <x> <y>`.
+    fn manage(&mut self, command: &str) -> Result<()> {
+        let mut parts = command.split_whitespace();
+        let action = parts.next().unwrap_or("");
+        let id = try!(parts.next().unwrap_or("").parse::<usize>().or(Err(Error::new(EINVAL))));
+        let (workspace, width, height) = match self.windows.get(&id) {
+            Some(window) => (window.workspace, window.width(), window.height()),
+            None => return Err(Error::new(EINVAL))
+        };
+
+        match action {
+            "focus" => {
+                self.switch_workspace(workspace);
+                self.unminimize(id);
+                self.focus(id);
+            },
+            "minimize" => self.minimize(id),
+            "close" => if let Some(mut window) = self.windows.get_mut(&id) {
+                window.event(QuitEvent.to_event());
+            },
+            "move" => {
+                let x = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                let y = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+
+                // Keep positions near the screen, as for windows moving themselves
+                let screen_rect = self.screen_rect();
+                let (x, y) = keep_near(&screen_rect, x, y, width, height);
+                self.move_resize(id, Rect::new(x, y, width, height));
+            },
+            _ => return Err(Error::new(EINVAL))
+        }
+        Ok(())
+    }
+
+    /// Current arrangement of top level windows, bottom first, relative to the display their
+    /// center is on
+    fn layout(&self) -> Layout {
+        let displays: Vec<Rect> = self.displays.iter().map(|display| display.rect()).collect();
+        let mut windows = Vec::new();
+        for id in self.stack().iter().rev() {
+            if let Some(window) = self.windows.get(id) {
+                if window.popup || window.parent.is_some() || window.title.is_empty() || window.title.contains('\n') {
+                    continue;
+                }
+                let rect = window.restore.or(window.fullscreen).unwrap_or(window.rect());
+                let (cx, cy) = (rect.left() + rect.width()/2, rect.top() + rect.height()/2);
+                let display = displays.iter().position(|display_rect| display_rect.contains(cx, cy)).unwrap_or(0);
+                let origin = displays.get(display).map_or((0, 0), |display_rect| (display_rect.left(), display_rect.top()));
+                windows.push(LayoutWindow {
+                    title: window.title.clone(),
+                    workspace: window.workspace,
+                    display: display,
+                    rect: rect.offset(-origin.0, -origin.1),
+                    state: if window.minimized {
+                        LayoutState::Minimized
+                    } else if window.restore.is_some() {
+                        LayoutState::Maximized
+                    } else {
+                        LayoutState::Normal
+                    }
+                });
+            }
+        }
+        Layout {
+            displays: displays,
+            windows: windows
+        }
+    }
+
+    /// Move open windows into the places a layout has for their titles
+    fn apply_layout(&mut self, layout: &Layout) {
+        let mut placed = Vec::new();
+        for entry in layout.windows.iter() {
+            let found = self.stack().iter().rev().cloned().find(|id| {
+                ! placed.contains(id) && self.windows.get(id).map_or(false, |window| {
+                    ! window.popup && window.parent.is_none() && window.title == entry.title
+                })
+            });
+            let id = match found {
+                Some(id) => id,
+                None => continue
+            };
+            placed.push(id);
+
+            let origin = self.displays.get(entry.display).map_or((0, 0), |display| (display.x, display.y));
+            let target = entry.rect.offset(origin.0, origin.1);
+
+            self.set_fullscreen(id, false);
+            self.move_to_workspace(id, min(WORKSPACES - 1, entry.workspace));
+            if entry.state == LayoutState::Maximized {
+                // The client has not resized yet, so the geometry to restore is set directly
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    window.restore = Some(target);
+                }
+                let work_rect = self.work_rect(target.left() + target.width()/2, target.top() + target.height()/2);
+                self.snap(id, work_rect);
+            } else {
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    window.restore = None;
+                }
+                self.move_resize(id, target);
+            }
+            if entry.state == LayoutState::Minimized {
+                self.minimize(id);
+            } else {
+                self.unminimize(id);
+            }
+        }
+    }
+
+    /// Move a window and ask its client to resize, redrawing the old and new positions
+    fn move_resize(&mut self, id: usize, target: Rect) {
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            schedule(&mut self.redraws, window.title_rect());
+            schedule(&mut self.redraws, window.rect());
+
+            let (w, h) = window.clamp_size(target.width(), target.height());
+            let current = window.target_rect();
+            if w != current.width() || h != current.height() {
+                window.request_resize(target.left(), target.top(), w, h);
+            } else {
+                window.x = target.left();
+                window.y = target.top();
+                window.event(MoveEvent {
+                    x: window.x,
+                    y: window.y
+                }.to_event());
+            }
+
+            schedule(&mut self.redraws, window.title_rect());
+            schedule(&mut self.redraws, window.rect());
+        }
+    }
+
+    /// Move and resize a window while its left or top border is dragged, ending the drag when the
+    /// button is released or the window is gone
+    fn drag_border(&mut self, id: usize, target: Option<Rect>, left_button: bool) {
+        match target {
+            Some(target) => if ! left_button {
+                self.dragging = DragMode::None;
+            } else if target.width() > 0 && target.height() > 0 {
+                let moved = self.windows.get(&id).map_or(false, |window| target != window.target_rect());
+                if moved {
+                    self.move_resize(id, target);
+                }
+            },
+            None => self.dragging = DragMode::None
+        }
+    }
+
+    /// Fit a window and its title bar into an area, remembering its geometry so it can be restored
+    fn snap(&mut self, id: usize, area: Rect) {
+        let target = if let Some(mut window) = self.windows.get_mut(&id) {
+            if window.restore.is_none() {
+                window.restore = Some(window.rect());
+            }
+            let title_rect = window.title_rect();
+            let title_h = if title_rect.is_empty() { 0 } else { window.y - title_rect.top() };
+            Rect::new(area.left(), area.top() + title_h, area.width(), area.height() - title_h)
+        } else {
+            return;
+        };
+
+        self.move_resize(id, target);
+    }
+
+    /// Resize a window to fill the work area, or restore its previous geometry if already maximized
+    fn toggle_maximize(&mut self, id: usize) {
+        let restore = if let Some(mut window) = self.windows.get_mut(&id) {
+            window.restore.take()
+        } else {
+            return;
+        };
+
+        match restore {
+            Some(rect) => self.move_resize(id, rect),
+            None => {
+                let work_rect = match self.windows.get(&id) {
+                    Some(window) => self.work_rect(window.x + window.width()/2, window.y + window.height()/2),
+                    None => return
+                };
+                self.snap(id, work_rect);
+            }
+        }
+    }
+
+    /// Make a window cover its display above other windows without decorations, or restore its
+    /// previous geometry, leaving focus to the caller
+    fn set_fullscreen(&mut self, id: usize, fullscreen: bool) {
+        let target = if let Some(mut window) = self.windows.get_mut(&id) {
+            if fullscreen == window.fullscreen.is_some() {
+                return;
+            }
+
+            // The title bar is hidden while fullscreen, so redraw where it was before it changes
+            schedule(&mut self.redraws, window.title_rect());
+            if fullscreen {
+                window.fullscreen = Some(window.rect());
+                None
+            } else {
+                window.fullscreen.take()
+            }
+        } else {
+            return;
+        };
+        self.restack(id);
+
+        match target {
+            Some(rect) => self.move_resize(id, rect),
+            None => {
+                let display_rect = match self.windows.get(&id) {
+                    Some(window) => self.display_rect(window.x + window.width()/2, window.y + window.height()/2),
+                    None => return
+                };
+                self.move_resize(id, display_rect);
+            }
+        }
+    }
+
+    /// Move a window requested off screen, or at a stale position such as for a display that is
+    /// no longer connected, onto the nearest work area so its title bar can be reached
+    fn place(&self, window: &mut Window) {
+        if let Some((x, y)) = window.reachable_position(&self.work_rects()) {
+            window.x = x;
+            window.y = y;
+            window.event(MoveEvent {
+                x: window.x,
+                y: window.y
+            }.to_event());
+        }
+    }
+
+    /// Tile a window released at the edge of a display, returns false if it was not at an edge
+    fn edge_snap(&mut self, id: usize, x: i32, y: i32) -> bool {
+        let work_rect = self.work_rect(x, y);
+        let screen_rect = self.display_rect(x, y);
+        if x <= screen_rect.left() {
+            self.snap(id, Rect::new(work_rect.left(), work_rect.top(), work_rect.width()/2, work_rect.height()));
+            true
+        } else if x >= screen_rect.right() - 1 {
+            self.snap(id, Rect::new(work_rect.left() + work_rect.width()/2, work_rect.top(),
+                                    work_rect.width() - work_rect.width()/2, work_rect.height()));
+            true
+        } else if y <= screen_rect.top() {
+            self.snap(id, work_rect);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Snap a window released while dragging quickly against the screen edge it was thrown toward
+    fn fling(&mut self, id: usize) {
+        let (vx, vy) = self.drag_velocity;
+        if self.fling_speed <= 0.0 || (vx.abs() < self.fling_speed && vy.abs() < self.fling_speed) {
+            return;
+        }
+
+        if let Some(window) = self.windows.get(&id) {
+            let screen_rect = self.work_rect(window.x + window.width()/2, window.y + window.height()/2);
+            let title_rect = window.title_rect();
+            let title_h = if title_rect.is_empty() { 0 } else { window.y - title_rect.top() };
+
+            let (x, y) = if vx.abs() >= vy.abs() {
+                if vx < 0.0 {
+                    (screen_rect.left(), window.y)
+                } else {
+                    (screen_rect.right() - window.width(), window.y)
+                }
+            } else if vy < 0.0 {
+                (window.x, screen_rect.top() + title_h)
+            } else {
+                (window.x, screen_rect.bottom() - window.height())
+            };
+
+            self.moves.push(MoveAnimation::new(id, window.x, window.y, x, y, self.motion(self.fling_duration)));
+        }
+    }
+
+    /// Duration of an animation, none with `reduce_motion`
+    fn motion(&self, duration: Duration) -> Duration {
+        if self.reduce_motion { Duration::from_secs(0) } else { duration }
+    }
+
+    /// Darkness of the screen fading out before the idle timeout, blank once idle
+    fn dim_level(&self) -> u8 {
+        let zero = Duration::from_secs(0);
+        if self.idle_timeout == zero || self.dim_duration == zero {
+            0
+        } else if self.idle {
+            255
+        } else if self.reduce_motion {
+            0
+        } else {
+            let elapsed = micros(self.last_input.elapsed());
+            let timeout = micros(self.idle_timeout);
+            let duration = micros(self.dim_duration);
+            if elapsed + duration <= timeout {
+                0
+            } else {
+                (min(elapsed + duration - timeout, duration) * 255 / duration) as u8
+            }
+        }
+    }
+
+    /// Record input, leaving idle and restoring a dimmed screen at once
+    fn activity(&mut self) {
+        self.last_input = Instant::now();
+        if self.idle {
+            self.idle = false;
+            self.hooks.run("active", None);
+        }
+        if self.dim > 0 {
+            self.dim = 0;
+            let screen_rect = self.screen_rect();
+            schedule(&mut self.redraws, screen_rect);
+        }
+    }
+
+    /// Advance animations, returns true if anything needs to be redrawn
+    pub fn tick(&mut self) -> bool {
+        let mut changed = self.check_config();
+
+        self.hooks.reap();
+
+        if self.update_theme_mode() {
+            changed = true;
+        }
+
+        // Windows inhibiting idle count as activity, so the timeout starts again when they stop
+        if ! self.idle && ! self.idle_inhibitors().is_empty() {
+            self.last_input = Instant::now();
+        }
+        if ! self.idle && self.idle_timeout > Duration::from_secs(0) && self.last_input.elapsed() >= self.idle_timeout {
+            self.idle = true;
+            self.hooks.run("idle", None);
+        }
+
+        // Fade the screen out, giving the user a chance to keep the session awake
+        let dim = self.dim_level();
+        if dim != self.dim {
+            self.dim = dim;
+            let screen_rect = self.screen_rect();
+            schedule(&mut self.redraws, screen_rect);
+            changed = true;
+        }
+
+        for handle in self.notifications.values_mut() {
+            if handle.expire() {
+                changed = true;
+            }
+        }
+
+        let expired: Vec<String> = self.startups.iter()
+            .filter(|startup| startup.expired())
+            .map(|startup| startup.id.clone())
+            .collect();
+        for id in expired {
+            self.set_startup(&id, None);
+            changed = true;
+        }
+
+        // Remove the overview once it has closed, and redraw it while it moves
+        if self.overview.as_ref().map_or(false, |overview| overview.done()) {
+            self.overview = None;
+            let screen_rect = self.screen_rect();
+            schedule(&mut self.redraws, screen_rect);
+            changed = true;
+        } else if self.overview.as_ref().map_or(false, |overview| overview.animating()) {
+            let screen_rect = self.screen_rect();
+            schedule(&mut self.redraws, screen_rect);
+            changed = true;
+        }
+
+        // Finish window animations, drawing opened windows in their place
+        let finished: Vec<(usize, WindowAnimationKind, Rect)> = self.animations.iter()
+            .filter(|animation| animation.done())
+            .map(|animation| (animation.id, animation.kind, animation.bounds()))
+            .collect();
+        for &(id, kind, bounds) in finished.iter() {
+            schedule(&mut self.redraws, bounds);
+            match kind {
+                WindowAnimationKind::Open => if let Some(window) = self.windows.get(&id) {
+                    schedule(&mut self.redraws, window.bounds());
+                },
+                WindowAnimationKind::Close => {
+                    self.closed.remove(&id);
+                },
+                WindowAnimationKind::Minimize => ()
+            }
+        }
+        self.animations.retain(|animation| ! animation.done());
+        if ! finished.is_empty() || ! self.animations.is_empty() {
+            changed = true;
+        }
+
+        if self.preview.as_ref().map_or(false, |preview| preview.expired()) {
+            self.preview = None;
+            changed = true;
+        }
+
+        // Redraw to update the task bar clock
+        if ! self.taskbar_rect().is_empty() && self.taskbar.clock_changed() {
+            changed = true;
+        }
+
+        // Pulse windows asking for attention until they are focused
+        let front = self.order.focused();
+        for (id, window) in self.windows.iter_mut() {
+            if window.attention.is_some() {
+                if Some(*id) == front {
+                    window.attention = None;
+                }
+                schedule(&mut self.redraws, window.title_rect());
+                changed = true;
+            }
+        }
+
+        if self.follow_pointer() {
+            changed = true;
+        }
+
+        // Shrink the cursor again after it was enlarged by shaking
+        if self.shake_until.map_or(false, |until| Instant::now() >= until) {
+            let cursor_rect = self.cursor_rect();
+            schedule(&mut self.redraws, cursor_rect);
+            self.shake_until = None;
+            changed = true;
+        }
+
+        let elapsed = micros(self.last_tick.elapsed());
+        self.last_tick = Instant::now();
+
+        // Fade windows towards their focused or unfocused opacity
+        let duration = micros(self.motion(self.opacity_duration));
+        let step = if duration > 0 {
+            max(1, min(255, elapsed * 255 / duration)) as i32
+        } else {
+            255
+        };
+        for (id, window) in self.windows.iter_mut() {
+            let target = if Some(*id) == front { 255 } else { self.unfocused_opacity as i32 };
+            let opacity = window.opacity as i32;
+            if opacity != target {
+                window.opacity = if opacity < target {
+                    min(target, opacity + step)
+                } else {
+                    max(target, opacity - step)
+                } as u8;
+                schedule(&mut self.redraws, window.rect());
+                changed = true;
+            }
+        }
+
+        let mut i = 0;
+        while i < self.moves.len() {
+            let id = self.moves[i].id;
+            let (x, y) = self.moves[i].position();
+            let done = self.moves[i].done();
+            let resize = if done { self.moves[i].resize } else { None };
+
+            if let Some(mut window) = self.windows.get_mut(&id) {
+                if x != window.x || y != window.y {
+                    schedule(&mut self.redraws, window.title_rect());
+                    schedule(&mut self.redraws, window.rect());
+
+                    window.x = x;
+                    window.y = y;
+                    window.event(MoveEvent {
+                        x: x,
+                        y: y
+                    }.to_event());
+
+                    schedule(&mut self.redraws, window.title_rect());
+                    schedule(&mut self.redraws, window.rect());
+                    changed = true;
+                }
+
+                if let Some((w, h)) = resize {
+                    if w != window.width() || h != window.height() {
+                        window.event(ResizeEvent {
+                            width: w as u32,
+                            height: h as u32
+                        }.to_event());
+                    }
+                }
+            }
+
+            if done || ! self.windows.contains_key(&id) {
+                self.moves.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+
+        changed
+    }
+
+    /// Find the window whose rect or title bar contains a point, searching from the top
+    fn window_at(&self, x: i32, y: i32) -> Option<usize> {
+        for &id in self.stack().iter() {
+            if let Some(window) = self.windows.get(&id) {
+                if ! window.visible(self.workspace) || window.click_through {
+                    continue;
+                }
+                if window.input_contains(x, y) || window.title_rect().contains(x, y) {
+                    return Some(id);
+                }
+            }
+        }
+        None
+    }
+
+    /// Is a point on the title bar of the topmost window under it
+    fn title_at(&self, x: i32, y: i32) -> bool {
+        self.window_at(x, y)
+            .and_then(|id| self.windows.get(&id))
+            .map_or(false, |window| window.title_rect().contains(x, y) && ! window.rect().contains(x, y))
+    }
+
+    /// Focus the next window stacked under a point, or on the workspace with `title_scroll=all`,
+    /// sending the topmost to the back, or bring the bottom one to the front going backwards
+    fn cycle_focus(&mut self, x: i32, y: i32, forward: bool) {
+        let all = self.title_scroll == TitleScroll::All;
+        let ids: Vec<usize> = self.stack().iter().cloned().filter(|id| {
+            self.windows.get(id).map_or(false, |window| {
+                window.visible(self.workspace) && ! window.popup && ! window.click_through
+                && (all || window.bounds().contains(x, y))
+            })
+        }).collect();
+        if ids.len() < 2 {
+            return;
+        }
+
+        if forward {
+            self.lower(ids[0]);
+            self.focus_window(ids[1], true);
+        } else {
+            self.focus_window(ids[ids.len() - 1], true);
+        }
+    }
+
+    /// Most recently used window shown on the current workspace
+    fn recent_window(&self) -> Option<usize> {
+        for &id in self.order.iter() {
+            if let Some(window) = self.windows.get(&id) {
+                if window.visible(self.workspace) && ! window.popup {
+                    return Some(id);
+                }
+            }
+        }
+        None
+    }
+
+    /// Give focus to a replacement window after the focused window was closed, minimized, or hidden
+    fn refocus(&mut self) {
+        let id_opt = match self.close_focus {
+            CloseFocus::Cursor => self.window_at(self.cursor_x, self.cursor_y).and_then(|id| {
+                if self.windows.get(&id).map_or(false, |window| window.popup) { None } else { Some(id) }
+            }).or(self.recent_window()),
+            CloseFocus::Recent => self.recent_window()
+        };
+
+        if let Some(id) = id_opt {
+            self.refocus_window(id);
+        }
+    }
+
+    /// Redraw the parts of a window covered by windows above it, before raising it
+    ///
+    /// Focus only changes how title bars are drawn, so the rest of a window that is not covered
+    /// looks the same after it is focused and raised. Fading to the unfocused opacity redraws
+    /// windows as it goes, and the shadow redraws itself when it follows focus.
+    fn schedule_covered(&mut self, id: usize) {
+        let bounds = match self.windows.get(&id) {
+            Some(window) => window.bounds(),
+            None => return
+        };
+        let mut above = Region::new();
+        for above_id in self.stack().iter().take_while(|&&above_id| above_id != id) {
+            if let Some(above_window) = self.windows.get(above_id) {
+                if above_window.visible(self.workspace) {
+                    above.add(above_window.bounds());
+                }
+            }
+        }
+        let covered = Region::from_rect(bounds).intersection(&above);
+        self.redraws.union(&covered);
+    }
+
+    /// Give focus to a window after the focused window went away, unless the policy vetoes it
+    fn refocus_window(&mut self, id: usize) {
+        if ! self.may_focus(id) {
+            return;
+        }
+        self.schedule_covered(id);
+        self.order.raise(id);
+
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            schedule(&mut self.redraws, window.title_rect());
+            window.event(FocusEvent {
+                focused: true
+            }.to_event());
+            self.hooks.run("focus", Some((id, &window.title)));
+        }
+    }
+
+    /// Select the next window in the switcher, without changing focus until win is released
+    fn win_tab(&mut self) {
+        // Disable dragging
+        self.dragging = DragMode::None;
+
+        let count = self.list_windows().len();
+        self.list_selected = Stack::switch(self.list_selected, count, true);
+        let screen_rect = self.screen_rect();
+        schedule(&mut self.redraws, screen_rect);
+    }
+
+    /// Windows listed in the switcher, most recently used first
+    fn list_windows(&self) -> Vec<usize> {
+        self.order.switcher(|id| self.windows.get(&id).map_or(false, |window| self.list_matches(id, window)))
+    }
+
+    /// Should a window be listed in the window switcher, matching the filter against the label
+    /// shown for it
+    fn list_matches(&self, id: usize, window: &Window) -> bool {
+        window.workspace == self.workspace && ! window.popup
+        && window_label(id, &window.title).to_lowercase().contains(&self.list_filter.to_lowercase())
+    }
+
+    /// Hide popups that a click outside of dismisses, asking their clients to close them
+    fn dismiss_popups(&mut self, x: i32, y: i32) {
+        for window in self.windows.values_mut() {
+            if window.popup && window.visible(self.workspace) && ! window.rect().contains(x, y) {
+                window.minimized = true;
+                schedule(&mut self.redraws, window.rect());
+                window.event(QuitEvent.to_event());
+            }
+        }
+    }
+
+    /// Focus a window and raise it within its layer, unless it is already focused
+    fn focus(&mut self, id: usize) {
+        self.focus_window(id, true);
+    }
+
+    /// Ask the policy and kiosk whether a window may take focus, every focus change goes through
+    /// this check
+    fn may_focus(&mut self, id: usize) -> bool {
+        match self.windows.get(&id) {
+            Some(window) => self.policy.focus(id, window)
+                            && self.kiosk.as_ref().map_or(true, |kiosk| kiosk.allows_focus(id, window)),
+            None => false
+        }
+    }
+
+    /// Raise a window within its layer and focus it
+    fn raise(&mut self, id: usize) {
+        self.schedule_covered(id);
+        self.order.raise(id);
+    }
+
+    /// Focus a window, raising it too if asked
+    fn focus_window(&mut self, id: usize, raise: bool) {
+        if self.order.is_focused(id) {
+            if raise {
+                self.raise(id);
+            }
+            return;
+        }
+        if ! self.may_focus(id) {
+            return;
+        }
+
+        //Redraw old focused window, which keeps its focus if an extra seat focuses it
+        if let Some(old_id) = self.order.focused() {
+            let seat_focused = self.seats.iter().any(|seat| seat.focused == Some(old_id));
+            if let Some(mut window) = self.windows.get_mut(&old_id){
+                schedule(&mut self.redraws, window.title_rect());
+                if ! seat_focused {
+                    window.event(FocusEvent {
+                        focused: false
+                    }.to_event());
+                }
+            }
+        }
+        //Redraw new focused window
+        if let Some(mut window) = self.windows.get_mut(&id){
+            schedule(&mut self.redraws, window.title_rect());
+            window.event(FocusEvent {
+                focused: true
+            }.to_event());
+            self.hooks.run("focus", Some((id, &window.title)));
+        }
+        if raise {
+            self.raise(id);
+        } else {
+            self.order.focus(id);
+        }
+    }
+
+    /// Stop drawing the window switcher, focusing the selected window and restoring it if it was
+    /// minimized
+    fn close_window_list(&mut self, select: bool) {
+        if self.win_tabbing {
+            let selected = self.list_windows().get(self.list_selected).cloned();
+            if let (true, Some(id)) = (select, selected) {
+                self.unminimize(id);
+                self.focus(id);
+                self.warp_to_focused();
+            }
+            let screen_rect = self.screen_rect();
+            schedule(&mut self.redraws, screen_rect);
+        }
+        self.win_tabbing = false;
+        self.list_filter.clear();
+    }
+
+    /// Move the pointer to the middle of the focused window after a keyboard focus change, so the
+    /// next click lands on it
+    fn warp_to_focused(&mut self) {
+        if ! self.warp_pointer {
+            return;
+        }
+        let rect = match self.order.focused().and_then(|id| self.windows.get(&id)) {
+            Some(window) if window.visible(self.workspace) => window.rect(),
+            _ => return
+        };
+        if rect.contains(self.cursor_x, self.cursor_y) {
+            return;
+        }
+
+        // Handle it as a move, updating the hovered window and cursor shape
+        let event = MouseEvent {
+            x: rect.left() + rect.width() / 2,
+            y: rect.top() + rect.height() / 2,
+            left_button: self.cursor_left,
+            middle_button: self.cursor_middle,
+            right_button: self.cursor_right
+        };
+        self.mouse_event(event);
+    }
+
+    /// Send a gamepad event to the grabbing or focused window, unless its button controls the
+    /// window switcher. Buttons other than `switch_window` only control the switcher while it is
+    /// open.
+    fn gamepad_event(&mut self, event_union: Event) {
+        let event = GamepadEvent::from_event(event_union);
+        let action = if self.kiosk.is_some() {
+            None
+        } else {
+            event.pressed().and_then(|button| self.gamepad.action(button))
+        };
+        match action {
+            Some(GamepadAction::SwitchWindow) => {
+                if ! self.win_tabbing {
+                    self.win_tabbing = true;
+                    self.list_selected = 0;
+                    self.list_scroll = 0;
+                }
+                self.win_tab();
+                return;
+            },
+            Some(GamepadAction::SwitchPrev) if self.win_tabbing => {
+                let count = self.list_windows().len();
+                self.list_selected = Stack::switch(self.list_selected, count, false);
+                let screen_rect = self.screen_rect();
+                schedule(&mut self.redraws, screen_rect);
+                return;
+            },
+            Some(GamepadAction::SwitchSelect) if self.win_tabbing => {
+                self.close_window_list(true);
+                return;
+            },
+            Some(GamepadAction::SwitchCancel) if self.win_tabbing => {
+                self.close_window_list(false);
+                return;
+            },
+            _ => ()
+        }
+
+        let grab = self.gamepad_grab.and_then(|id| {
+            self.windows.get(&id).and_then(|window| if window.visible(self.workspace) { Some(id) } else { None })
+        });
+        if let Some(id) = grab.or(self.order.focused()) {
+            if let Some(mut window) = self.windows.get_mut(&id) {
+                window.event(event_union);
+            }
+        }
+    }
+
+    /// Select the first window matching the switcher filter after it was edited
+    fn filter_window_list(&mut self) {
+        self.list_selected = 0;
+        self.list_scroll = 0;
+        let screen_rect = self.screen_rect();
+        schedule(&mut self.redraws, screen_rect);
+    }
+
+    /// Draws a list of currently open windows with thumbnails in the middle of the screen
+    fn draw_window_list(&mut self) {
+        let ids = self.list_windows();
+
+        // Show the filter being typed above the list
+        let header = if self.list_filter.is_empty() { 0 } else { 20 };
+
+        // Limit the list to the screen height, scrolling to keep the selection visible
+        let screen_rect = self.screen_rect();
+        let max_rows = max(1, (screen_rect.height() - header - 4) / SWITCHER_ROW) as usize;
+        let visible = min(ids.len(), max_rows);
+        if self.list_selected < self.list_scroll {
+            self.list_scroll = self.list_selected;
+        }
+        if self.list_selected >= self.list_scroll + visible {
+            self.list_scroll = self.list_selected + 1 - visible;
+        }
+        self.list_scroll = min(self.list_scroll, ids.len() - visible);
+        let scroll = self.list_scroll;
+
+        let list_h = header + visible as i32 * SWITCHER_ROW + 4;
+        let list_w = 400;
+        let target_rect = Rect::new(screen_rect.width()/2 - list_w/2,
+                                    screen_rect.height()/2 - list_h/2,
+                                    list_w, list_h);
+        // Color copied over from orbtk's window background
+        self.image.roi_mut(&target_rect).fill(self.theme.bar);
+        if header > 0 {
+            let filter = format!("> {}", self.list_filter);
+            self.draw_text(&filter, target_rect.left() + 4, target_rect.top() + 4, self.theme.text, self.theme.bar, &target_rect);
+        }
+
+        for (i, id) in ids.iter().enumerate().skip(scroll).take(visible) {
+            let row_rect = Rect::new(target_rect.left(),
+                                     target_rect.top() + header + (i - scroll) as i32 * SWITCHER_ROW + 2,
+                                     list_w, SWITCHER_ROW);
+            let selected = i == self.list_selected;
+            let background = if selected { self.theme.bar_highlight } else { self.theme.bar };
+            let color = if selected { self.theme.text_highlight } else { self.theme.text };
+            if selected {
+                self.image.roi_mut(&target_rect).roi(&row_rect.offset(-target_rect.left(), -target_rect.top())).fill(self.theme.bar_highlight);
+            }
+
+            // Mark windows asking for attention with a pulsing bar
+            let pulse = self.windows.get(id).and_then(|window| window.attention.as_ref()).map(|attention| attention.pulse());
+            if let Some(pulse) = pulse {
+                let mark_rect = Rect::new(row_rect.left(), row_rect.top() + 4, 3, SWITCHER_ROW - 8).intersection(&target_rect);
+                self.image.roi_mut(&mark_rect).tint(self.theme.attention, (64.0 + pulse * 191.0) as u8);
+            }
+
+            let title = if let Some(window) = self.windows.get(id) {
+                let thumbnail_rect = Rect::new(row_rect.left() + 4, row_rect.top() + 4,
+                                               THUMBNAIL_WIDTH, SWITCHER_ROW - 8);
+                window.draw_thumbnail(&mut self.image, &thumbnail_rect.intersection(&target_rect), &target_rect, self.quality.smooth());
+
+                let title = window_label(*id, &window.title);
+                if window.minimized {
+                    format!("({})", title)
+                } else {
+                    title
+                }
+            } else {
+                continue;
+            };
+
+            self.draw_text(&title, row_rect.left() + THUMBNAIL_WIDTH + 12, row_rect.top() + SWITCHER_ROW/2 - 8,
+                           color, background, &row_rect.intersection(&target_rect));
+        }
+        schedule(&mut self.redraws, target_rect);
+    }
+
+    /// Areas of the notifications being shown, newest at the top
+    fn notification_rects(&self) -> Vec<(usize, Rect)> {
+        let x = self.screen_rect().width() - NOTIFICATION_WIDTH - NOTIFICATION_MARGIN;
+        let mut y = NOTIFICATION_MARGIN;
+        let mut rects = Vec::new();
+        for (id, handle) in self.notifications.iter().rev() {
+            if handle.notification.is_some() {
+                rects.push((*id, Rect::new(x, y, NOTIFICATION_WIDTH, NOTIFICATION_HEIGHT)));
+                y += NOTIFICATION_HEIGHT + NOTIFICATION_MARGIN;
+            }
+        }
+        rects
+    }
+
+    /// Find the notification under a point
+    fn notification_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.notification_rects().into_iter().find(|&(_, rect)| rect.contains(x, y)).map(|(id, _)| id)
+    }
+
+    /// Draws the window preview, with its top left corner kept on the screen
+    fn draw_preview(&mut self) {
+        let (id, x, y) = match self.preview {
+            Some(ref preview) => (preview.window, preview.x, preview.y),
+            None => return
+        };
+        let screen_rect = self.screen_rect();
+        let x = max(0, min(screen_rect.width() - PREVIEW_WIDTH, x));
+        let y = max(0, min(screen_rect.height() - PREVIEW_HEIGHT, y));
+        let target_rect = Rect::new(x, y, PREVIEW_WIDTH, PREVIEW_HEIGHT);
+        self.image.roi_mut(&target_rect).fill(self.theme.bar);
+
+        let title = match self.windows.get(&id) {
+            Some(window) => {
+                let thumbnail_rect = Rect::new(x + 4, y + 24, PREVIEW_WIDTH - 8, PREVIEW_HEIGHT - 28);
+                window.draw_thumbnail(&mut self.image, &thumbnail_rect, &target_rect, self.quality.smooth());
+                window_label(id, &window.title)
+            },
+            None => return
+        };
+        self.draw_text(&title, x + 4, y + 4, self.theme.text, self.theme.bar, &target_rect);
+        schedule(&mut self.redraws, target_rect);
+    }
+
+    /// Draws the windows of the overview over the workspace, moving between their places and
+    /// their grid cells while it opens or closes
+    ///
+    /// Only the areas composited this frame are drawn over, as the overview covers the screen. It
+    /// is redrawn while it moves, and where thumbnails or the highlighted cell change.
+    fn draw_overview(&mut self, areas: &Region) {
+        let (progress, rects) = match self.overview {
+            Some(ref overview) => (overview.progress(), overview.cells.iter().filter_map(|&(id, ref cell)| {
+                self.windows.get(&id).map(|window| (id, *cell, overview.rect(&window.rect(), cell)))
+            }).collect::<Vec<(usize, Rect, Rect)>>()),
+            None => return
+        };
+
+        for area in areas.iter() {
+            self.image.roi_mut(area).tint(Color::rgb(0, 0, 0), (progress * 224.0) as u8);
+        }
+
+        // Draw the topmost window last, so it stays above the others as they move
+        for &(id, cell, rect) in rects.iter().rev() {
+            for area in areas.iter() {
+                if progress >= 1.0 && cell.contains(self.cursor_x, self.cursor_y) {
+                    self.image.roi_mut(&cell.intersection(area)).tint(self.theme.bar_highlight, 96);
+                }
+                if let Some(window) = self.windows.get(&id) {
+                    window.draw_thumbnail(&mut self.image, &rect, area, self.quality.smooth());
+                }
+            }
+        }
+    }
+
+    /// Open the overview of the windows on the workspace, or start closing it
+    fn toggle_overview(&mut self) {
+        if let Some(ref mut overview) = self.overview {
+            overview.close();
+            return;
+        }
+
+        let ids: Vec<usize> = self.stack().iter().cloned().filter(|id| {
+            self.windows.get(id).map_or(false, |window| window.visible(self.workspace) && ! window.popup)
+        }).collect();
+        if ids.is_empty() {
+            return;
+        }
+        let area = self.work_rect(self.cursor_x, self.cursor_y);
+        self.overview = Some(Overview::new(ids, &area, self.reduce_motion));
+        let screen_rect = self.screen_rect();
+        schedule(&mut self.redraws, screen_rect);
+    }
+
+    /// Focus the window clicked in the overview, if any, and close it
+    fn overview_click(&mut self, x: i32, y: i32) {
+        let clicked = self.overview.as_ref().and_then(|overview| {
+            if overview.closing { None } else { overview.window_at(x, y) }
+        });
+        if let Some(id) = clicked {
+            self.focus_window(id, true);
+        }
+        if let Some(ref mut overview) = self.overview {
+            overview.close();
+        }
+    }
+
+    /// Draws windows that are opening, closing, or minimizing
+    fn draw_window_animations(&mut self) {
+        for animation in self.animations.iter() {
+            let window = match animation.kind {
+                WindowAnimationKind::Close => self.closed.get(&animation.id),
+                _ => self.windows.get(&animation.id)
+            };
+            if let Some(window) = window {
+                window.draw_scaled(&mut self.image, &animation.rect(), animation.opacity(), self.quality.smooth());
+            }
+            schedule(&mut self.redraws, animation.bounds());
+        }
+    }
+
+    /// Draws notifications above every window
+    fn draw_notifications(&mut self) {
+        for (id, target_rect) in self.notification_rects() {
+            self.image.roi_mut(&target_rect).fill(self.theme.bar);
+
+            let (title, body, text_x) = match self.notifications.get(&id).and_then(|handle| handle.notification.as_ref()) {
+                Some(notification) => {
+                    let mut text_x = target_rect.left() + NOTIFICATION_MARGIN;
+                    if let Some(ref icon) = notification.icon {
+                        let icon_rect = Rect::new(text_x, target_rect.top() + (NOTIFICATION_HEIGHT - icon.height())/2,
+                                                  icon.width(), icon.height()).intersection(&target_rect);
+                        self.image.roi_mut(&icon_rect).blend(&icon.roi(&Rect::new(0, 0, icon_rect.width(), icon_rect.height())));
+                        text_x += icon.width() + NOTIFICATION_MARGIN;
+                    }
+                    (notification.title.clone(), notification.body.clone(), text_x)
+                },
+                None => continue
+            };
+
+            self.draw_text(&title, text_x, target_rect.top() + 8, self.theme.text_highlight, self.theme.bar, &target_rect);
+            self.draw_text(&body, text_x, target_rect.top() + 30, self.theme.text, self.theme.bar, &target_rect);
+            schedule(&mut self.redraws, target_rect);
+        }
+    }
+
+    fn launcher_rect(&self) -> Rect {
+        let screen_rect = self.screen_rect();
+        Rect::new(screen_rect.width()/2 - 200, screen_rect.height()/3, 400, 24)
+    }
+
+    /// Draws the run command prompt
+    fn draw_launcher(&mut self) {
+        let target_rect = self.launcher_rect();
+        self.image.roi_mut(&target_rect).fill(self.theme.bar);
+        let line = format!("Run: {}_", self.launcher.text);
+        self.draw_text(&line, target_rect.left() + 4, target_rect.top() + 4, self.theme.text, self.theme.bar, &target_rect);
+        schedule(&mut self.redraws, target_rect);
+    }
+
+    /// Edit or run the command in the launcher
+    fn launcher_key(&mut self, event: KeyEvent) {
+        match event.scancode {
+            orbclient::K_ESC => self.launcher.hide(),
+            orbclient::K_ENTER => {
+                // The app opens on the workspace it was started from
+                self.launched += 1;
+                let id = format!("launcher-{}", self.launched);
+                if self.launcher.submit(&id) {
+                    let workspace = self.workspace;
+                    self.set_startup(&id, Some(Startup::new(id.clone(), vec![RuleAction::Workspace(workspace)])));
+                }
+            },
+            orbclient::K_UP => self.launcher.history_prev(),
+            orbclient::K_DOWN => self.launcher.history_next(),
+            orbclient::K_TAB => self.launcher.complete(),
+            orbclient::K_BKSP => {
+                self.launcher.text.pop();
+            },
+            _ => if event.character != '\0' && ! event.character.is_control() {
+                self.launcher.text.push(event.character);
+            }
+        }
+        let launcher_rect = self.launcher_rect();
+        schedule(&mut self.redraws, launcher_rect);
+    }
+
+    /// Show the next or previous background
+    fn cycle_background(&mut self, forward: bool) {
+        if self.backgrounds.is_empty() {
+            return;
+        }
+
+        let bg_rect = self.background_rect();
+        schedule(&mut self.redraws, bg_rect);
+
+        let count = self.backgrounds.len();
+        self.background_i = if forward {
+            (self.background_i + 1) % count
+        } else {
+            (self.background_i + count - 1) % count
+        };
+
+        let bg_rect = self.background_rect();
+        schedule(&mut self.redraws, bg_rect);
+    }
+
+    /// Run the compositor action bound to a key
+    fn key_action(&mut self, action: Action) {
+        match action {
+            Action::Close => {
+                if let Some(id) = self.order.focused() {
+                    if let Some(mut window) = self.windows.get_mut(&id) {
+                        window.event(QuitEvent.to_event());
+                    }
+                }
+            },
+            Action::SwitchWindow => {
+                // Start drawing the window switcher. It's drawn by redraw()
+                if ! self.win_tabbing {
+                    self.win_tabbing = true;
+                    self.list_selected = 0;
+                    self.list_scroll = 0;
+                }
+                self.win_tab();
+            },
+            Action::PerfHud => {
+                // Toggle the performance HUD, clearing it when hidden
+                self.perf_hud = ! self.perf_hud;
+                let screen_rect = self.screen_rect();
+                let hud_rect = Rect::new(screen_rect.width() - 320, 0, 320, screen_rect.height());
+                schedule(&mut self.redraws, hud_rect);
+            },
+            Action::Run => {
+                // Open the run command prompt
+                self.launcher.show();
+                let launcher_rect = self.launcher_rect();
+                schedule(&mut self.redraws, launcher_rect);
+            },
+            Action::Dump => {
+                // Dump compositor state to the log
+                println!("orbital: state dump\n{}", self.control_report());
+            },
+            Action::Workspace(workspace) => {
+                self.switch_workspace(workspace);
+                self.warp_to_focused();
+            },
+            Action::MoveToWorkspace(workspace) => {
+                // Send the focused window to another workspace
+                if let Some(id) = self.order.focused() {
+                    self.move_to_workspace(id, workspace);
+                }
+            },
+            Action::Background => self.cycle_background(true),
+            Action::Screenshot => self.save_screenshot(false),
+            Action::ScreenshotWindow => self.save_screenshot(true),
+            Action::Record => self.toggle_recording(),
+            Action::Raise => if let Some(id) = self.order.focused() {
+                self.raise(id);
+            },
+            Action::ZoomIn => self.zoom_focused(1),
+            Action::ZoomOut => self.zoom_focused(-1),
+            Action::Overview => self.toggle_overview(),
+            Action::Inspect => self.toggle_inspect()
+        }
+    }
+
+    /// PNG of the composited screen, or of the contents of the focused window
+    fn screenshot(&self, window: bool) -> Option<Vec<u8>> {
+        if window {
+            self.order.focused().and_then(|id| self.windows.get(&id)).map(|window| {
+                let contents = window.contents();
+                png::encode(contents.width(), contents.height(), contents.data(), ! window.transparent)
+            })
+        } else {
+            let screen = self.screen_image(&self.screen_rect());
+            Some(png::encode(screen.width(), screen.height(), screen.data(), true))
+        }
+    }
+
+    /// Save a screenshot to the screenshot directory, and run the screenshot hooks with its path
+    fn save_screenshot(&mut self, window: bool) {
+        let data = match self.screenshot(window) {
+            Some(data) => data,
+            None => return
+        };
+
+        let path = self.screenshot_path("screenshot");
+        match File::create(&path).and_then(|mut file| file.write_all(&data)) {
+            Ok(()) => {
+                println!("orbital: saved screenshot '{}'", path);
+                self.hooks.run_env("screenshot", &[("ORBITAL_SCREENSHOT", path)]);
+            },
+            Err(err) => println!("orbital: failed to save screenshot '{}': {}", path, err)
+        }
+    }
+
+    /// Path of a new file in the screenshot directory, named by the time in milliseconds and
+    /// numbered if that name is taken
+    fn screenshot_path(&self, name: &str) -> String {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs() * 1000 + time.subsec_nanos() as u64 / 1000000).unwrap_or(0);
+        let mut path = format!("{}/{}-{}.png", self.screenshot_dir, name, time);
+        let mut number = 1;
+        while Path::new(&path).exists() {
+            number += 1;
+            path = format!("{}/{}-{}-{}.png", self.screenshot_dir, name, time, number);
+        }
+        path
+    }
+
+    /// Start recording the screen to the screenshot directory, or stop and run the record hooks
+    /// with the path of the recording
+    fn toggle_recording(&mut self) {
+        match self.recorder.take() {
+            Some(recorder) => match recorder.stop() {
+                Ok(path) => {
+                    println!("orbital: saved recording '{}'", path);
+                    self.hooks.run_env("record", &[("ORBITAL_RECORDING", path)]);
+                },
+                Err(err) => self.status.error(format!("failed to save recording: {}", err))
+            },
+            None => {
+                let path = self.screenshot_path("recording");
+                match Recorder::start(path.clone(), self.screen_image(&self.screen_rect())) {
+                    Ok(recorder) => {
+                        println!("orbital: recording to '{}'", path);
+                        self.recorder = Some(recorder);
+                    },
+                    Err(err) => self.status.error(format!("failed to record to '{}': {}", path, err))
+                }
+            }
+        }
+    }
+
+    /// Change the magnification of the focused window, following the cursor if it is inside
+    fn zoom_focused(&mut self, change: i32) {
+        if let Some(id) = self.order.focused() {
+            if let Some(mut window) = self.windows.get_mut(&id) {
+                let x = self.cursor_x - window.x;
+                let y = self.cursor_y - window.y;
+                let zoom = window.zoom + change;
+                window.set_zoom(zoom, x, y);
+                schedule(&mut self.redraws, window.rect());
+            }
+        }
+    }
+
+    fn key_event(&mut self, event: KeyEvent) {
+        if event.pressed {
+            self.set_input_source(InputSource::Keyboard);
+        }
+
+        if event.scancode == orbclient::K_LEFT_SHIFT || event.scancode == orbclient::K_RIGHT_SHIFT {
+            self.shift_key = event.pressed;
+        }
+
+        if event.scancode == 0x38 && self.kiosk.is_none() {
+            self.win_key = event.pressed;
+            // If the win key was released, stop drawing the win-tab window switcher
+            if !self.win_key {
+                self.close_window_list(true);
+            }
+        } else if self.launcher.active {
+            if event.pressed {
+                self.launcher_key(event);
+            }
+        } else if self.overview.is_some() && event.scancode == orbclient::K_ESC {
+            if event.pressed {
+                self.toggle_overview();
+            }
+        } else if self.inspecting && event.scancode == orbclient::K_ESC {
+            if event.pressed {
+                self.toggle_inspect();
+            }
+        } else if self.win_tabbing && event.scancode == orbclient::K_BKSP {
+            // Edit the window switcher filter
+            if event.pressed {
+                self.list_filter.pop();
+                self.filter_window_list();
+            }
+        } else if self.win_tabbing && event.character != '\0' && ! event.character.is_control() {
+            if event.pressed {
+                self.list_filter.push(event.character);
+                self.filter_window_list();
+            }
+        } else if let Some(action) = self.keys.action(self.win_key, self.shift_key, event.scancode) {
+            if event.pressed {
+                self.key_action(action);
+            }
+        } else if self.win_key {
+            if event.pressed {
+                println!("WIN+{:X}", event.scancode);
+                self.hooks.run("bell", None);
+            }
+        } else if let Some(id) = self.order.focused() {
+            if let Some(mut window) = self.windows.get_mut(&id) {
+                if window.visible(self.workspace) {
+                    window.event(event.to_event());
+                }
+            }
+        }
+    }
+
+    fn mouse_event(&mut self, mut event: MouseEvent) {
+        // Keep the cursor on a display, as their union may not be rectangular
+        let display_rect = self.display_rect(event.x, event.y);
+        event.x = max(display_rect.left(), min(display_rect.right() - 1, event.x));
+        event.y = max(display_rect.top(), min(display_rect.bottom() - 1, event.y));
+
+        if let Some(ref kiosk) = self.kiosk {
+            if kiosk.cursor == KioskCursor::Confine {
+                if let Some(window) = kiosk.window.and_then(|id| self.windows.get(&id)) {
+                    let rect = window.rect();
+                    if ! rect.is_empty() {
+                        event.x = max(rect.left(), min(rect.right() - 1, event.x));
+                        event.y = max(rect.top(), min(rect.bottom() - 1, event.y));
+                    }
+                }
+            }
+        }
+
+        // Buttons can not be pressed inside dead zones, but can be released
+        if self.dead_zones.iter().any(|zone| zone.contains(event.x, event.y)) {
+            event.left_button = event.left_button && self.cursor_left;
+            event.middle_button = event.middle_button && self.cursor_middle;
+            event.right_button = event.right_button && self.cursor_right;
+        }
+
+        // Touchscreens jump to a new position as they press, mice move there first
+        let moved = event.x != self.cursor_x || event.y != self.cursor_y;
+
+        // Redraw the overview cells the cursor moves between, to move the highlight
+        if let Some(ref overview) = self.overview {
+            let old_cell = overview.cell_at(self.cursor_x, self.cursor_y);
+            let new_cell = overview.cell_at(event.x, event.y);
+            if old_cell != new_cell {
+                for cell in old_cell.into_iter().chain(new_cell) {
+                    schedule(&mut self.redraws, cell);
+                }
+            }
+        }
+        if moved && event.left_button && ! self.cursor_left {
+            self.set_input_source(InputSource::Touch);
+            if self.touch_mode == TouchMode::Auto {
+                self.set_touch(true);
+            }
+        } else if moved && ! event.left_button {
+            self.set_input_source(InputSource::Mouse);
+            if self.touch_mode == TouchMode::Auto {
+                self.set_touch(false);
+            }
+        } else if self.input_source == InputSource::Keyboard {
+            self.set_input_source(InputSource::Mouse);
+        }
+
+        // Check for focus switch, dragging, and forward mouse events to applications
+        let mut hover_shape = CursorShape::Default;
+        // Moves and resizes started with Win+right-drag continue while the right button is held
+        let drag_held = event.left_button || event.right_button;
+        match self.dragging {
+            DragMode::None => {
+                if event.left_button && ! self.cursor_left
+                || event.middle_button && ! self.cursor_middle
+                || event.right_button && ! self.cursor_right {
+                    self.dismiss_popups(event.x, event.y);
+                }
+
+                // Notifications are above every window, clicking one tells its client and removes it
+                let notification = self.notification_at(event.x, event.y);
+                if let Some(id) = notification {
+                    let reason = if event.left_button && ! self.cursor_left {
+                        Some("click")
+                    } else if event.right_button && ! self.cursor_right {
+                        Some("dismiss")
+                    } else {
+                        None
+                    };
+                    if let (Some(reason), Some(handle)) = (reason, self.notifications.get_mut(&id)) {
+                        handle.close(reason);
+                    }
+                }
+
+                // Clicking a task bar button focuses its window, restoring it if it is minimized
+                let on_taskbar = self.taskbar_rect().contains(event.x, event.y);
+                if on_taskbar && event.left_button && ! self.cursor_left {
+                    if let Some(id) = self.taskbar_button_at(event.x, event.y) {
+                        self.unminimize(id);
+                        self.focus(id);
+                    }
+                }
+
+                // The overview takes every click, focusing the window clicked
+                if self.overview.is_some() && event.left_button && ! self.cursor_left {
+                    self.overview_click(event.x, event.y);
+                }
+
+                // The inspector takes every click, picking the window clicked
+                if self.inspecting && event.left_button && ! self.cursor_left {
+                    self.inspect_click(event.x, event.y);
+                }
+
+                // Windows beneath notifications, the task bar, the overview and the inspector get
+                // no mouse events
+                let stack = if notification.is_some() || on_taskbar || self.overview.is_some() || self.inspecting {
+                    Vec::new()
+                } else {
+                    self.stack().to_vec()
+                };
+
+                let mut focus = None;
+                // Clicks in windows may focus them without raising them
+                let mut raise = true;
+                let mut maximize = None;
+                let mut minimize = None;
+                let mut click = None;
+                for &id in stack.iter() {
+                    if let Some(mut window) = self.windows.get_mut(&id) {
+                        if ! window.visible(self.workspace) || window.click_through {
+                            // Minimized windows, other workspaces and click-through windows can not be clicked
+                        } else if self.win_key && ! window.popup && window.fullscreen.is_none() && window.input_contains(event.x, event.y) {
+                            // Win+drag moves a window from anywhere, and Win+right-drag resizes
+                            // it from the nearest corner
+                            if event.left_button && ! self.cursor_left {
+                                focus = Some(id);
+                                self.dragging = DragMode::Title(id, event.x, event.y);
+                                self.drag_time = Instant::now();
+                                self.drag_velocity = (0.0, 0.0);
+                                self.moves.retain(|animation| animation.id != id);
+                            } else if event.right_button && ! self.cursor_right && window.resizable {
+                                focus = Some(id);
+                                let right = window.x + window.width();
+                                let bottom = window.y + window.height();
+                                let left_half = event.x < window.x + window.width()/2;
+                                let top_half = event.y < window.y + window.height()/2;
+                                self.dragging = match (left_half, top_half) {
+                                    (true, true) => DragMode::TopLeftBorder(id, event.x - window.x, event.y - window.y, right, bottom),
+                                    (false, true) => DragMode::TopRightBorder(id, event.x - right, event.y - window.y, bottom),
+                                    (true, false) => DragMode::BottomLeftBorder(id, event.x - window.x, event.y - bottom, right),
+                                    (false, false) => DragMode::BottomRightBorder(id, event.x - right, event.y - bottom)
+                                };
+                            } else if event.middle_button && ! self.cursor_middle {
+                                click = Some((id, self.win_middle_click));
+                            }
+                            hover_shape = CursorShape::Move;
+                            break;
+                        } else if window.input_contains(event.x, event.y) {
+                            let mut window_event = event.to_event();
+                            window_event.a -= window.x as i64;
+                            window_event.b -= window.y as i64;
+                            if window.zoom > 1 {
+                                // Pan to follow the cursor, and send positions in the contents
+                                if window.zoom_pan(event.x - window.x, event.y - window.y) {
+                                    schedule(&mut self.redraws, window.rect());
+                                }
+                                let (x, y) = window.zoom_point(event.x - window.x, event.y - window.y);
+                                window_event.a = x as i64;
+                                window_event.b = y as i64;
+                            }
+                            window.event(window_event);
+                            if event.left_button  && ! self.cursor_left
+                            || event.middle_button && ! self.cursor_middle
+                            || event.right_button && ! self.cursor_right {
+                                focus = Some(id);
+                                raise = self.raise_on_click;
+                            }
+                            break;
+                        } else if window.title_rect().contains(event.x, event.y) {
+                            if event.left_button && ! self.cursor_left  {
+                                focus = Some(id);
+                                if window.exit_contains(event.x, event.y) {
+                                    match self.close_mode {
+                                        CloseMode::Click => {
+                                            window.event(QuitEvent.to_event());
+                                        },
+                                        CloseMode::Confirm => {
+                                            let confirmed = match self.close_armed {
+                                                Some((armed_id, armed_time)) => armed_id == id && armed_time.elapsed() <= self.close_timeout,
+                                                None => false
+                                            };
+                                            if confirmed {
+                                                self.close_armed = None;
+                                                window.event(QuitEvent.to_event());
+                                            } else {
+                                                self.close_armed = Some((id, Instant::now()));
+                                            }
+                                        },
+                                        CloseMode::Hold => {
+                                            self.dragging = DragMode::Close(id, Instant::now());
+                                        }
+                                    }
+                                    schedule(&mut self.redraws, window.title_rect());
+                                } else if window.maximize_contains(event.x, event.y) {
+                                    maximize = Some(id);
+                                } else if window.minimize_contains(event.x, event.y) {
+                                    minimize = Some(id);
+                                } else {
+                                    self.dragging = DragMode::Title(id, event.x, event.y);
+                                    self.drag_time = Instant::now();
+                                    self.drag_velocity = (0.0, 0.0);
+                                    self.moves.retain(|animation| animation.id != id);
+                                }
+                            } else if event.middle_button && ! self.cursor_middle {
+                                click = Some((id, self.title_middle_click));
+                            } else if event.right_button && ! self.cursor_right {
+                                click = Some((id, self.title_right_click));
+                            }
+                            break;
+                        } else if window.left_border_rect().contains(event.x, event.y) {
+                            hover_shape = CursorShape::Horizontal;
+                            if event.left_button && ! self.cursor_left  {
+                                focus = Some(id);
+                                self.dragging = DragMode::LeftBorder(id, event.x - window.x, window.x + window.width());
+                            }
+                            break;
+                        } else if window.right_border_rect().contains(event.x, event.y) {
+                            hover_shape = CursorShape::Horizontal;
+                            if event.left_button && ! self.cursor_left  {
+                                focus = Some(id);
+                                self.dragging = DragMode::RightBorder(id, event.x - (window.x + window.width()));
+                            }
+                            break;
+                        } else if window.bottom_border_rect().contains(event.x, event.y) {
+                            hover_shape = CursorShape::Vertical;
+                            if event.left_button && ! self.cursor_left  {
+                                focus = Some(id);
+                                self.dragging = DragMode::BottomBorder(id, event.y - (window.y + window.height()));
+                            }
+                            break;
+                        } else if window.top_border_rect().contains(event.x, event.y) {
+                            hover_shape = CursorShape::Vertical;
+                            if event.left_button && ! self.cursor_left  {
+                                focus = Some(id);
+                                self.dragging = DragMode::TopBorder(id, event.y - window.y, window.y + window.height());
+                            }
+                            break;
+                        } else if window.top_left_border_rect().contains(event.x, event.y) {
+                            hover_shape = CursorShape::Diagonal;
+                            if event.left_button && ! self.cursor_left  {
+                                focus = Some(id);
+                                self.dragging = DragMode::TopLeftBorder(id, event.x - window.x, event.y - window.y,
+                                                                        window.x + window.width(), window.y + window.height());
+                            }
+                            break;
+                        } else if window.top_right_border_rect().contains(event.x, event.y) {
+                            hover_shape = CursorShape::Antidiagonal;
+                            if event.left_button && ! self.cursor_left  {
+                                focus = Some(id);
+                                self.dragging = DragMode::TopRightBorder(id, event.x - (window.x + window.width()), event.y - window.y,
+                                                                         window.y + window.height());
+                            }
+                            break;
+                        } else if window.bottom_left_border_rect().contains(event.x, event.y) {
+                            hover_shape = CursorShape::Antidiagonal;
+                            if event.left_button && ! self.cursor_left  {
+                                focus = Some(id);
+                                self.dragging = DragMode::BottomLeftBorder(id, event.x - window.x, event.y - (window.y + window.height()),
+                                                                           window.x + window.width());
+                            }
+                            break;
+                        } else if window.bottom_right_border_rect().contains(event.x, event.y) {
+                            hover_shape = CursorShape::Diagonal;
+                            if event.left_button && ! self.cursor_left  {
+                                focus = Some(id);
+                                self.dragging = DragMode::BottomRightBorder(id, event.x - (window.x + window.width()), event.y - (window.y + window.height()));
+                            }
+                            break;
+                        }
+                    }
+                }
+                if let Some((armed_id, armed_time)) = self.close_armed {
+                    if armed_time.elapsed() > self.close_timeout {
+                        // Confirmation window expired, redraw the close button unarmed
+                        self.close_armed = None;
+                        if let Some(window) = self.windows.get(&armed_id) {
+                            schedule(&mut self.redraws, window.title_rect());
+                        }
+                    }
+                }
+                if let Some(id) = maximize {
+                    self.toggle_maximize(id);
+                }
+                if let Some(id) = minimize {
+                    self.minimize(id);
+                    focus = None;
+                }
+                if let Some((id, action)) = click {
+                    self.click_action(id, action);
+                }
+
+                let pointer = if stack.is_empty() { None } else { self.window_at(event.x, event.y) };
+                self.set_pointer_window(pointer);
+                if let Some(id) = focus {
+                    if ! self.windows.get(&id).map_or(false, |window| window.popup) {
+                        self.focus_window(id, raise);
+                    }
+                }
+            },
+            DragMode::Title(window_id, drag_x, drag_y) => {
+                let work_rects = self.work_rects();
+                if drag_held {
+                    if let Some(mut window) = self.windows.get_mut(&window_id) {
+                        // The drag point stays where it was pressed until a snapped window moves
+                        let moved = if window.restore.is_some() {
+                            (event.x - drag_x).abs() >= UNSNAP_DISTANCE || (event.y - drag_y).abs() >= UNSNAP_DISTANCE
+                        } else {
+                            drag_x != event.x || drag_y != event.y
+                        };
+                        if moved {
+                            schedule(&mut self.redraws, window.title_rect());
+                            schedule(&mut self.redraws, window.rect());
+
+                            // Dragging a tiled or maximized window away restores its size
+                            if let Some(restore) = window.restore.take() {
+                                if drag_x - window.x > restore.width() {
+                                    window.x = drag_x - restore.width()/2;
+                                }
+                                window.event(ResizeEvent {
+                                    width: restore.width() as u32,
+                                    height: restore.height() as u32
+                                }.to_event());
+                            }
+
+                            window.x += event.x - drag_x;
+                            window.y += event.y - drag_y;
+                            // The title bar stays on a display, so the window can be dragged back
+                            if let Some((x, y)) = window.reachable_position(&work_rects) {
+                                window.x = x;
+                                window.y = y;
+                            }
+
+                            let move_event = MoveEvent {
+                                x: window.x,
+                                y: window.y
+                            }.to_event();
+                            window.event(move_event);
+
+                            self.dragging = DragMode::Title(window_id, event.x, event.y);
+
+                            // Track a smoothed drag velocity in pixels per second for flinging
+                            let elapsed = self.drag_time.elapsed();
+                            let dt = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1000000000.0;
+                            if dt > 0.0 {
+                                let (vx, vy) = self.drag_velocity;
+                                self.drag_velocity = ((vx + (event.x - drag_x) as f32 / dt) / 2.0,
+                                                      (vy + (event.y - drag_y) as f32 / dt) / 2.0);
+                            }
+                            self.drag_time = Instant::now();
+
+                            schedule(&mut self.redraws, window.title_rect());
+                            schedule(&mut self.redraws, window.rect());
+                        }
+                    } else {
+                        self.dragging = DragMode::None;
+                    }
+                } else {
+                    self.dragging = DragMode::None;
+                    if self.edge_snap(window_id, event.x, event.y) {
+                        // Snapped to an edge, do not fling
+                    } else if self.drag_time.elapsed() < Duration::from_millis(100) {
+                        // Only fling if the window was still moving when released
+                        self.fling(window_id);
+                    }
+                }
+            },
+            DragMode::RightBorder(window_id, off_x) => {
+                if drag_held {
+                    if let Some(mut window) = self.windows.get_mut(&window_id) {
+                        let current = window.target_rect();
+                        let (w, _) = window.clamp_size(event.x - off_x - current.left(), current.height());
+                        if w > 0 && w != current.width()  {
+                            window.request_resize(current.left(), current.top(), w, current.height());
+                        }
                     } else {
                         self.dragging = DragMode::None;
                     }
@@ -513,16 +3424,52 @@ impl OrbitalScheme {
                     self.dragging = DragMode::None;
                 }
             },
+            DragMode::LeftBorder(window_id, off_x, right) => {
+                let target = self.windows.get(&window_id).map(|window| {
+                    let current = window.target_rect();
+                    let (w, h) = window.clamp_size(right - (event.x - off_x), current.height());
+                    Rect::new(right - max(0, w), current.top(), max(0, w), h)
+                });
+                self.drag_border(window_id, target, drag_held);
+            },
+            DragMode::TopBorder(window_id, off_y, bottom) => {
+                let target = self.windows.get(&window_id).map(|window| {
+                    let current = window.target_rect();
+                    let (w, h) = window.clamp_size(current.width(), bottom - (event.y - off_y));
+                    Rect::new(current.left(), bottom - max(0, h), w, max(0, h))
+                });
+                self.drag_border(window_id, target, drag_held);
+            },
+            DragMode::TopLeftBorder(window_id, off_x, off_y, right, bottom) => {
+                let target = self.windows.get(&window_id).map(|window| {
+                    let (w, h) = window.clamp_size(right - (event.x - off_x), bottom - (event.y - off_y));
+                    Rect::new(right - max(0, w), bottom - max(0, h), max(0, w), max(0, h))
+                });
+                self.drag_border(window_id, target, drag_held);
+            },
+            DragMode::TopRightBorder(window_id, off_x, off_y, bottom) => {
+                let target = self.windows.get(&window_id).map(|window| {
+                    let current = window.target_rect();
+                    let (w, h) = window.clamp_size(event.x - off_x - current.left(), bottom - (event.y - off_y));
+                    Rect::new(current.left(), bottom - max(0, h), max(0, w), max(0, h))
+                });
+                self.drag_border(window_id, target, drag_held);
+            },
+            DragMode::BottomLeftBorder(window_id, off_x, off_y, right) => {
+                let target = self.windows.get(&window_id).map(|window| {
+                    let current = window.target_rect();
+                    let (w, h) = window.clamp_size(right - (event.x - off_x), event.y - off_y - current.top());
+                    Rect::new(right - max(0, w), current.top(), max(0, w), max(0, h))
+                });
+                self.drag_border(window_id, target, drag_held);
+            },
             DragMode::BottomBorder(window_id, off_y) => {
-                if event.left_button {
+                if drag_held {
                     if let Some(mut window) = self.windows.get_mut(&window_id) {
-                        let h = event.y - off_y - window.y;
-                        if h > 0 && h != window.height()  {
-                            let resize_event = ResizeEvent {
-                                width: window.width() as u32,
-                                height: h as u32
-                            }.to_event();
-                            window.event(resize_event);
+                        let current = window.target_rect();
+                        let (_, h) = window.clamp_size(current.width(), event.y - off_y - current.top());
+                        if h > 0 && h != current.height()  {
+                            window.request_resize(current.left(), current.top(), current.width(), h);
                         }
                     } else {
                         self.dragging = DragMode::None;
@@ -531,17 +3478,25 @@ impl OrbitalScheme {
                     self.dragging = DragMode::None;
                 }
             },
+            DragMode::Close(window_id, start) => {
+                if ! event.left_button {
+                    self.dragging = DragMode::None;
+                    if let Some(mut window) = self.windows.get_mut(&window_id) {
+                        // Only close if the button was held long enough and released over it
+                        if window.exit_contains(event.x, event.y) && start.elapsed() >= self.close_timeout {
+                            window.event(QuitEvent.to_event());
+                        }
+                        schedule(&mut self.redraws, window.title_rect());
+                    }
+                }
+            },
             DragMode::BottomRightBorder(window_id, off_x, off_y) => {
-                if event.left_button {
+                if drag_held {
                     if let Some(mut window) = self.windows.get_mut(&window_id) {
-                        let w = event.x - off_x - window.x;
-                        let h = event.y - off_y - window.y;
-                        if w > 0 && h > 0 && w != window.width() && h != window.height()  {
-                            let resize_event = ResizeEvent {
-                                width: w as u32,
-                                height: h as u32
-                            }.to_event();
-                            window.event(resize_event);
+                        let current = window.target_rect();
+                        let (w, h) = window.clamp_size(event.x - off_x - current.left(), event.y - off_y - current.top());
+                        if w > 0 && h > 0 && (w != current.width() || h != current.height())  {
+                            window.request_resize(current.left(), current.top(), w, h);
                         }
                     } else {
                         self.dragging = DragMode::None;
@@ -552,6 +3507,23 @@ impl OrbitalScheme {
             }
         }
 
+        // Show the shape of the border under the cursor, or of the drag in progress
+        let cursor_shape = match self.dragging {
+            DragMode::None | DragMode::Close(..) => hover_shape,
+            DragMode::Title(..) => CursorShape::Move,
+            DragMode::LeftBorder(..) | DragMode::RightBorder(..) => CursorShape::Horizontal,
+            DragMode::TopBorder(..) | DragMode::BottomBorder(..) => CursorShape::Vertical,
+            DragMode::TopLeftBorder(..) | DragMode::BottomRightBorder(..) => CursorShape::Diagonal,
+            DragMode::TopRightBorder(..) | DragMode::BottomLeftBorder(..) => CursorShape::Antidiagonal
+        };
+        if cursor_shape != self.cursor_shape {
+            let cursor_rect = self.cursor_rect();
+            schedule(&mut self.redraws, cursor_rect);
+            self.cursor_shape = cursor_shape;
+            let cursor_rect = self.cursor_rect();
+            schedule(&mut self.redraws, cursor_rect);
+        }
+
         // Update saved mouse information
         if event.x != self.cursor_x || event.y != self.cursor_y {
             let cursor_rect = self.cursor_rect();
@@ -560,6 +3532,11 @@ impl OrbitalScheme {
             self.cursor_x = event.x;
             self.cursor_y = event.y;
 
+            // Enlarge the cursor when it is shaken, so it can be found on large screens
+            if self.shake.motion(event.x) && self.shake_cursor_scale > 1 && ! self.reduce_motion {
+                self.shake_until = Some(Instant::now() + self.shake_duration);
+            }
+
             let cursor_rect = self.cursor_rect();
             schedule(&mut self.redraws, cursor_rect);
         }
@@ -567,14 +3544,298 @@ impl OrbitalScheme {
         self.cursor_left = event.left_button;
         self.cursor_middle = event.middle_button;
         self.cursor_right = event.right_button;
+
+        self.follow_pointer();
+    }
+
+    /// Track the window under the pointer, starting the focus delay when it changes
+    fn set_pointer_window(&mut self, id: Option<usize>) {
+        if id != self.pointer_window {
+            self.pointer_window = id;
+            self.pointer_entered = id.map(|_| Instant::now());
+        }
+    }
+
+    /// In the follow mode, focus the window under the pointer once it has rested there for the
+    /// focus delay, returns true if focus changed
+    ///
+    /// Focus follows each window the pointer enters only once, so a window focused with the
+    /// keyboard keeps focus until the pointer moves to another window.
+    fn follow_pointer(&mut self) -> bool {
+        if self.focus_mode != FocusMode::Follow {
+            return false;
+        }
+        match self.dragging {
+            DragMode::None => (),
+            _ => return false
+        }
+        let id = match (self.pointer_window, self.pointer_entered) {
+            (Some(id), Some(entered)) if entered.elapsed() >= self.focus_delay => id,
+            _ => return false
+        };
+        self.pointer_entered = None;
+
+        let focusable = self.windows.get(&id).map_or(false, |window| {
+            window.visible(self.workspace) && ! window.popup
+        });
+        if ! focusable || self.order.is_focused(id) {
+            return false;
+        }
+        self.focus_window(id, false);
+        self.order.is_focused(id)
+    }
+
+    /// Send a stylus event to the window under it if that window handles the stylus, otherwise
+    /// emulate the mouse so other windows and window management still work
+    fn stylus_event(&mut self, event: StylusEvent) {
+        let target = match self.dragging {
+            DragMode::None => self.window_at(event.x, event.y).and_then(|id| {
+                self.windows.get(&id).and_then(|window| {
+                    if window.stylus && window.rect().contains(event.x, event.y) { Some(id) } else { None }
+                })
+            }),
+            _ => None
+        };
+
+        match target {
+            Some(id) => {
+                if event.x != self.cursor_x || event.y != self.cursor_y {
+                    let cursor_rect = self.cursor_rect();
+                    schedule(&mut self.redraws, cursor_rect);
+                    self.cursor_x = event.x;
+                    self.cursor_y = event.y;
+                    let cursor_rect = self.cursor_rect();
+                    schedule(&mut self.redraws, cursor_rect);
+                }
+
+                let pressed = event.pressure > 0;
+                if pressed && ! self.cursor_left && ! self.windows.get(&id).map_or(false, |window| window.popup) {
+                    self.focus(id);
+                }
+                self.cursor_left = pressed;
+                self.cursor_right = event.barrel;
+
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    let mut window_event = event;
+                    window_event.x -= window.x;
+                    window_event.y -= window.y;
+                    window.event(window_event.to_event());
+                }
+            },
+            None => self.mouse_event(event.to_mouse())
+        }
+    }
+
+    /// Handle an input event from a hot-plugged device, routed to the seat the device belongs to
+    pub fn device_event(&mut self, device: &str, event: Event) {
+        let event = self.scroll_direction.apply(device, event);
+        match self.seats.iter().position(|seat| seat.owns(device)) {
+            Some(seat_i) => self.seat_event(seat_i, event),
+            None => self.input_event(0, event)
+        }
+    }
+
+    /// Handle an input event from an extra seat, which only clicks, types, scrolls, draws and
+    /// plays into windows
+    fn seat_event(&mut self, seat_i: usize, event_union: Event) {
+        self.activity();
+
+        let (x, y, focused) = match self.seats.get(seat_i) {
+            Some(seat) => (seat.x, seat.y, seat.focused),
+            None => return
+        };
+        match event_union.to_option() {
+            EventOption::Key(_) => if let Some(id) = focused {
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    if window.visible(self.workspace) {
+                        window.event(event_union);
+                    }
+                }
+            },
+            EventOption::Mouse(event) => if let Some((id, window_x, window_y)) = self.seat_pointer(seat_i, event) {
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    let mut window_event = event;
+                    window_event.x = window_x;
+                    window_event.y = window_y;
+                    window.event(window_event.to_event());
+                }
+            },
+            EventOption::Unknown(event) if event.code == EVENT_STYLUS => {
+                // Windows that do not handle the stylus get the mouse it emulates
+                let event = StylusEvent::from_event(event);
+                let mouse = event.to_mouse();
+                if let Some((id, window_x, window_y)) = self.seat_pointer(seat_i, mouse) {
+                    if let Some(mut window) = self.windows.get_mut(&id) {
+                        if window.stylus {
+                            let mut window_event = event;
+                            window_event.x = window_x;
+                            window_event.y = window_y;
+                            window.event(window_event.to_event());
+                        } else {
+                            let mut window_event = mouse;
+                            window_event.x = window_x;
+                            window_event.y = window_y;
+                            window.event(window_event.to_event());
+                        }
+                    }
+                }
+            },
+            EventOption::Unknown(event) if event.code == EVENT_GAMEPAD => if let Some(id) = focused {
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    if window.visible(self.workspace) {
+                        window.event(event_union);
+                    }
+                }
+            },
+            EventOption::Scroll(_) => if let Some(id) = self.window_at(x, y) {
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    window.event(event_union);
+                }
+            },
+            event => println!("orbital: unexpected event: {:?}", event)
+        }
+    }
+
+    /// Move the cursor of an extra seat, focusing the window under it for that seat when a button
+    /// is pressed, returns the window whose contents are under the cursor and the position within
+    fn seat_pointer(&mut self, seat_i: usize, mut event: MouseEvent) -> Option<(usize, i32, i32)> {
+        let screen_rect = self.screen_rect();
+        event.x = max(screen_rect.left(), min(screen_rect.right() - 1, event.x));
+        event.y = max(screen_rect.top(), min(screen_rect.bottom() - 1, event.y));
+
+        let (pressed, old_focused) = match self.seats.get_mut(seat_i) {
+            Some(seat) => {
+                let cursor_w = self.cursor.width();
+                let cursor_h = self.cursor.height();
+                schedule(&mut self.redraws, Rect::new(seat.x, seat.y, cursor_w, cursor_h));
+                schedule(&mut self.redraws, Rect::new(event.x, event.y, cursor_w, cursor_h));
+
+                let pressed = event.left_button && ! seat.left_button
+                              || event.middle_button && ! seat.middle_button
+                              || event.right_button && ! seat.right_button;
+                seat.x = event.x;
+                seat.y = event.y;
+                seat.left_button = event.left_button;
+                seat.middle_button = event.middle_button;
+                seat.right_button = event.right_button;
+                (pressed, seat.focused)
+            },
+            None => return None
+        };
+
+        let id_opt = self.window_at(event.x, event.y);
+        if let (true, Some(id)) = (pressed, id_opt) {
+            if old_focused != Some(id) {
+                self.seat_focus(seat_i, Some(id));
+            }
+        }
+        let id = match id_opt {
+            Some(id) => id,
+            None => return None
+        };
+        match self.windows.get(&id) {
+            Some(window) if window.rect().contains(event.x, event.y) => Some((id, event.x - window.x, event.y - window.y)),
+            _ => None
+        }
+    }
+
+    /// Change the window focused by an extra seat, telling the window losing it unless another
+    /// seat still focuses it
+    fn seat_focus(&mut self, seat_i: usize, id_opt: Option<usize>) {
+        let old_opt = match self.seats.get_mut(seat_i) {
+            Some(seat) => mem::replace(&mut seat.focused, id_opt),
+            None => return
+        };
+        if let Some(old_id) = old_opt {
+            if ! self.has_focus(old_id) {
+                if let Some(mut window) = self.windows.get_mut(&old_id) {
+                    window.event(FocusEvent {
+                        focused: false
+                    }.to_event());
+                }
+            }
+        }
+        if let Some(id) = id_opt {
+            if let Some(mut window) = self.windows.get_mut(&id) {
+                window.event(FocusEvent {
+                    focused: true
+                }.to_event());
+            }
+        }
+    }
+
+    /// Is a window focused by the first seat or any extra seat
+    fn has_focus(&self, id: usize) -> bool {
+        self.order.is_focused(id) || self.seats.iter().any(|seat| seat.focused == Some(id))
+    }
+
+    /// Handle an input event from a display, with mouse positions relative to that display
+    pub fn event(&mut self, display: usize, event: Event) {
+        let event = self.scroll_direction.apply("display", event);
+        self.input_event(display, event);
     }
 
-    pub fn event(&mut self, event_union: Event){
+    /// Handle an input event from a display or a device outside of any seat, with scrolling
+    /// already in the direction set for the device
+    fn input_event(&mut self, display: usize, event_union: Event){
+        self.activity();
+
         match event_union.to_option() {
             EventOption::Key(event) => self.key_event(event),
-            EventOption::Mouse(event) => self.mouse_event(event),
-            EventOption::Scroll(_) => {
-                if let Some(id) = self.order.front() {
+            EventOption::Mouse(mut event) => {
+                if let Some(display) = self.displays.get(display) {
+                    event.x += display.x;
+                    event.y += display.y;
+                }
+                self.mouse_event(event)
+            },
+            EventOption::Unknown(event) if event.code == EVENT_GAMEPAD => self.gamepad_event(event),
+            EventOption::Unknown(event) if event.code == EVENT_STYLUS => {
+                let mut event = StylusEvent::from_event(event);
+                if let Some(display) = self.displays.get(display) {
+                    event.x += display.x;
+                    event.y += display.y;
+                }
+                self.stylus_event(event)
+            },
+            EventOption::Scroll(event) => {
+                self.set_input_source(InputSource::Mouse);
+                if self.win_tabbing {
+                    // Move the switcher selection, it is scrolled into view when drawn
+                    let count = self.list_windows().len() as i32;
+                    self.list_selected = max(0, min(count - 1, self.list_selected as i32 - event.y)) as usize;
+                    let screen_rect = self.screen_rect();
+                    schedule(&mut self.redraws, screen_rect);
+                } else if self.win_key {
+                    // Change the opacity of the focused window, never making it invisible
+                    if let Some(id) = self.order.focused() {
+                        if let Some(mut window) = self.windows.get_mut(&id) {
+                            window.client_opacity = max(32, min(255, window.client_opacity as i32 + event.y * 16)) as u8;
+                            schedule(&mut self.redraws, window.rect());
+                        }
+                    }
+                } else if self.title_scroll != TitleScroll::None && self.title_at(self.cursor_x, self.cursor_y) && event.y != 0 {
+                    let (x, y) = (self.cursor_x, self.cursor_y);
+                    self.cycle_focus(x, y, event.y < 0);
+                } else if self.window_at(self.cursor_x, self.cursor_y).is_none() && event.y != 0 {
+                    // Scrolling up goes to the previous workspace or background
+                    match self.desktop_scroll {
+                        DesktopScroll::Focused => if let Some(id) = self.order.focused() {
+                            if let Some(mut window) = self.windows.get_mut(&id) {
+                                window.event(event_union);
+                            }
+                        },
+                        DesktopScroll::Workspace => {
+                            let workspace = if event.y > 0 {
+                                self.workspace.saturating_sub(1)
+                            } else {
+                                min(WORKSPACES - 1, self.workspace + 1)
+                            };
+                            self.switch_workspace(workspace);
+                        },
+                        DesktopScroll::Background => self.cycle_background(event.y < 0)
+                    }
+                } else if let Some(id) = self.order.focused() {
                     if let Some(mut window) = self.windows.get_mut(&id) {
                         window.event(event_union);
                     }
@@ -586,18 +3847,157 @@ impl OrbitalScheme {
 }
 
 impl SchemeMut for OrbitalScheme {
-    fn open(&mut self, url: &[u8], _flags: usize, _uid: u32, _gid: u32) -> Result<usize> {
+    fn open(&mut self, url: &[u8], _flags: usize, uid: u32, _gid: u32) -> Result<usize> {
         let path = try!(str::from_utf8(url).or(Err(Error::new(EINVAL))));
+
+        if path == "control" {
+            let id = self.alloc_id();
+            let control = Control::new("control", uid, self.control_report());
+            self.controls.insert(id, control);
+            return Ok(id);
+        }
+
+        if path == "status" {
+            let id = self.alloc_id();
+            let control = Control::new("status", uid, self.status_report());
+            self.controls.insert(id, control);
+            return Ok(id);
+        }
+
+        if path == "screenshot" || path == "screenshot/window" {
+            // Screenshots show other clients' windows, like capture
+            if uid != 0 {
+                return Err(Error::new(EACCES));
+            }
+            let window = path == "screenshot/window";
+            let data = try!(self.screenshot(window).ok_or(Error::new(EINVAL)));
+            let id = self.alloc_id();
+            let control = Control::from_data(if window { "screenshot/window" } else { "screenshot" }, uid, data);
+            self.controls.insert(id, control);
+            return Ok(id);
+        }
+
+        if path == "manage" {
+            // Managing other clients' windows, for standalone task bars and scripts
+            if uid != 0 {
+                return Err(Error::new(EACCES));
+            }
+            let id = self.alloc_id();
+            self.controls.insert(id, Control::new("manage", uid, String::new()));
+            return Ok(id);
+        }
+
+        if path.starts_with("contents/") {
+            // Contents of other clients' windows, for taskbar previews and screen sharing
+            if uid != 0 {
+                return Err(Error::new(EACCES));
+            }
+            let window_id = try!(path[9..].parse::<usize>().or(Err(Error::new(EINVAL))));
+            let data = match self.windows.get(&window_id) {
+                Some(window) => capture::raw(&window.contents()),
+                None => return Err(Error::new(EINVAL))
+            };
+            let id = self.alloc_id();
+            self.controls.insert(id, Control::from_data("contents", uid, data));
+            return Ok(id);
+        }
+
+        if path == "clipboard" {
+            let id = self.alloc_id();
+            let handle = ClipboardHandle::new(&self.clipboard);
+            self.clipboards.insert(id, handle);
+            return Ok(id);
+        }
+
+        if path == "windows" || path == "windows/watch" {
+            let mut list = String::new();
+            for (&window_id, window) in self.windows.iter() {
+                list.push_str(&window_list::describe(window_id, window, self.order.is_focused(window_id)));
+                list.push('\n');
+            }
+            let id = self.alloc_id();
+            self.window_lists.insert(id, WindowList::new(list, path == "windows/watch"));
+            return Ok(id);
+        }
+
+        if path == "notify" {
+            let id = self.alloc_id();
+            self.notifications.insert(id, NotifyHandle::new());
+            return Ok(id);
+        }
+
+        if path == "capture" || path.starts_with("capture/") {
+            // Captures show other clients' windows, like contents/
+            if uid != 0 {
+                return Err(Error::new(EACCES));
+            }
+            let window = if path == "capture" {
+                None
+            } else {
+                let window = try!(path[8..].parse::<usize>().or(Err(Error::new(EINVAL))));
+                if ! self.windows.contains_key(&window) {
+                    return Err(Error::new(EINVAL));
+                }
+                Some(window)
+            };
+            let id = self.alloc_id();
+            self.captures.insert(id, Capture::new(window));
+            // Make sure there is a composite to capture
+            if window.is_none() {
+                let screen_rect = self.screen_rect();
+                schedule(&mut self.redraws, screen_rect);
+            }
+            return Ok(id);
+        }
+
         let mut parts = path.split("/");
 
-        let flags = parts.next().unwrap_or("");
+        // Apps started with a startup id add it to the end of the flags, such as `r!launcher-3`
+        let mut startup_parts = parts.next().unwrap_or("").splitn(2, '!');
+        let flags_parent = startup_parts.next().unwrap_or("");
+        let startup_id = startup_parts.next();
+
+        // Dialogs add their parent's id to the flags, such as `r@12`, and may only belong to
+        // windows of the same process
+        let mut flag_parts = flags_parent.splitn(2, '@');
+        let flags = flag_parts.next().unwrap_or("");
+        let parent = match flag_parts.next() {
+            Some(parent_str) => {
+                let parent = try!(parent_str.parse::<usize>().or(Err(Error::new(EINVAL))));
+                match self.windows.get(&parent) {
+                    Some(parent_window) => if uid != 0 && parent_window.owner != self.caller_pid {
+                        return Err(Error::new(EACCES));
+                    },
+                    None => return Err(Error::new(EINVAL))
+                }
+                if ! self.parents_end(parent) {
+                    return Err(Error::new(EINVAL));
+                }
+                Some(parent)
+            },
+            None => None
+        };
 
         let mut async = false;
         let mut resizable = false;
+        let mut transparent = false;
+        let mut borderless = false;
+        let mut fullscreen = false;
+        let mut popup = false;
+        let mut stylus = false;
+        let mut click_through = false;
+        let mut sync_resize = false;
         for flag in flags.chars() {
             match flag {
                 'a' => async = true,
                 'r' => resizable = true,
+                't' => transparent = true,
+                'b' => borderless = true,
+                'f' => fullscreen = true,
+                'p' => popup = true,
+                's' => stylus = true,
+                'c' => click_through = true,
+                'y' => sync_resize = true,
                 _ => ()
             }
         }
@@ -606,6 +4006,11 @@ impl SchemeMut for OrbitalScheme {
         let mut y = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
         let width = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
         let height = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
+        if width < 0 || height < 0 {
+            return Err(Error::new(EINVAL));
+        }
+        let screen_rect = self.screen_rect();
+        let (width, height) = fit_size(&screen_rect, width, height);
 
         let mut title = parts.next().unwrap_or("").to_string();
         for part in parts {
@@ -613,51 +4018,270 @@ impl SchemeMut for OrbitalScheme {
             title.push_str(part);
         }
 
-        let id = self.next_id as usize;
-        self.next_id += 1;
-        if self.next_id < 0 {
-            self.next_id = 1;
+        let id = self.alloc_id();
+
+        // Windows placed by the session or a rule are not placed by the window policy
+        let mut placed = false;
+        let mut restored_size = None;
+        // Work area to center or smartly place the window on
+        let mut auto_place = None;
+        let parent_rect = parent.and_then(|parent| self.windows.get(&parent)).map(|window| window.rect());
+        if let (true, Some(parent_rect)) = (x < 0 && y < 0, parent_rect) {
+            // Center dialogs over their parent
+            x = parent_rect.left() + (parent_rect.width() - width)/2;
+            y = parent_rect.top() + (parent_rect.height() - height)/2;
+        } else if x < 0 && y < 0 && ! popup {
+            match self.session.as_ref().and_then(|session| session.get(&title)) {
+                Some(rect) => {
+                    // Restore where the window was when it last closed
+                    x = rect.left();
+                    y = rect.top();
+                    restored_size = Some((rect.width(), rect.height()));
+                    placed = true;
+                },
+                None if self.placement != Placement::Cascade => {
+                    // Placed once its decorations and workspace are known
+                    let work_rect = self.work_rect(self.cursor_x, self.cursor_y);
+                    x = work_rect.left();
+                    y = work_rect.top();
+                    auto_place = Some(work_rect);
+                },
+                None => {
+                    // Cascade new windows on the work area of the display containing the cursor
+                    let work_rect = self.work_rect(self.cursor_x, self.cursor_y);
+                    x = work_rect.left() + self.next_x;
+                    y = work_rect.top() + self.next_y;
+
+                    self.next_x += 20;
+                    if self.next_x + 20 >= work_rect.width() {
+                        self.next_x = 20;
+                    }
+                    self.next_y += 20;
+                    if self.next_y + 20 >= work_rect.height() {
+                        self.next_y = 20;
+                    }
+                }
+            }
         }
 
-        if x < 0 && y < 0 {
-            x = self.next_x;
-            y = self.next_y;
+        // Keep positions near the screen, so window edges can not overflow
+        let (x, y) = keep_near(&screen_rect, x, y, width, height);
 
-            self.next_x += 20;
-            if self.next_x + 20 >= self.image.width() {
-                self.next_x = 20;
+        // Popups have no decorations, and are placed exactly where requested
+        let mut window = Window::new(x, y, width, height, title, async, resizable, transparent, borderless || popup, self.decorations(), self.theme, &self.font);
+        window.owner = self.caller_pid;
+        window.owner_uid = uid;
+        window.workspace = self.workspace;
+        window.popup = popup;
+        window.stylus = stylus;
+        window.click_through = click_through;
+        window.sync_resize = sync_resize;
+        if let (true, Some((w, h))) = (resizable, restored_size) {
+            let (w, h) = fit_size(&screen_rect, w, h);
+            let (w, h) = window.clamp_size(w, h);
+            if w != width || h != height {
+                window.event(ResizeEvent {
+                    width: w as u32,
+                    height: h as u32
+                }.to_event());
             }
-            self.next_y += 20;
-            if self.next_y + 20 >= self.image.height() {
-                self.next_y = 20;
+        }
+        if let Some(parent) = parent {
+            if let Some(parent_window) = self.windows.get(&parent) {
+                window.workspace = parent_window.workspace;
+                window.layer = parent_window.layer;
             }
         }
+        window.parent = parent;
 
-        if let Some(id) = self.order.front() {
-            if let Some(window) = self.windows.get(&id){
-                schedule(&mut self.redraws, window.title_rect());
-                schedule(&mut self.redraws, window.rect());
+        // The first window of a launched app ends its startup
+        let startup = startup_id.and_then(|startup_id| {
+            self.startups.iter().find(|startup| startup.id == startup_id)
+        }).map(|startup| (startup.id.clone(), startup.actions.clone()));
+        let startup_actions = match startup {
+            Some((startup_id, actions)) => {
+                self.set_startup(&startup_id, None);
+                actions
+            },
+            None => Vec::new()
+        };
+
+        // Actions asked for by the launcher, then rules from the config, apply to top level
+        // windows before they are placed
+        let mut maximized = false;
+        if parent.is_none() && ! popup {
+            let mut actions = startup_actions;
+            actions.extend(self.rules.iter()
+                .filter(|rule| rule.matches(&window.title))
+                .flat_map(|rule| rule.actions.iter().cloned()));
+            for action in actions {
+                match action {
+                    RuleAction::Position(x, y) => {
+                        let (x, y) = keep_near(&screen_rect, x, y, width, height);
+                        window.x = x;
+                        window.y = y;
+                        window.event(MoveEvent {
+                            x: window.x,
+                            y: window.y
+                        }.to_event());
+                        placed = true;
+                    },
+                    RuleAction::Size(w, h) => {
+                        let (w, h) = fit_size(&screen_rect, w, h);
+                        let (w, h) = window.clamp_size(w, h);
+                        window.event(ResizeEvent {
+                            width: w as u32,
+                            height: h as u32
+                        }.to_event());
+                    },
+                    RuleAction::Workspace(workspace) => if workspace < WORKSPACES {
+                        window.workspace = workspace;
+                    },
+                    RuleAction::OnTop => window.layer = Layer::Dock,
+                    RuleAction::Borderless => window.borderless = true,
+                    RuleAction::Maximized => maximized = true
+                }
+            }
+        }
+
+        if let (false, Some(work_rect)) = (placed, auto_place) {
+            let bounds = window.bounds();
+            let (left, top) = if self.placement == Placement::Center {
+                placement::center(bounds.width(), bounds.height(), &work_rect)
+            } else {
+                let workspace = window.workspace;
+                let occupied: Vec<Rect> = self.windows.values()
+                    .filter(|other| other.visible(workspace) && ! other.popup)
+                    .filter(|other| other.layer != Layer::Desktop && other.fullscreen.is_none())
+                    .map(|other| other.bounds())
+                    .collect();
+                placement::smart(bounds.width(), bounds.height(), &work_rect, &occupied)
+            };
+            window.x += left - bounds.left();
+            window.y += top - bounds.top();
+        }
+
+        // In kiosk mode, other apps can not open top level windows over the kiosk app
+        let mut kiosk_app = false;
+        if let Some(ref kiosk) = self.kiosk {
+            if kiosk.matches(&window) && kiosk.window.is_none() {
+                kiosk_app = true;
+            } else if ! kiosk.allows(&window) {
+                match kiosk.others {
+                    KioskOthers::Reject => return Err(Error::new(EACCES)),
+                    KioskOthers::Minimize => window.minimized = true
+                }
             }
         }
 
-        let window = Window::new(x, y, width, height, title, async, resizable, &self.font);
+        if ! popup {
+            let bounds = window.bounds();
+            let work_rect = self.work_rect(bounds.left() + bounds.width()/2, bounds.top() + bounds.height()/2);
+            if placed {
+                // Restored or placed by a rule
+            } else if let Some((x, y)) = self.policy.place(&window, &work_rect) {
+                window.x = x;
+                window.y = y;
+            }
+            self.place(&mut window);
+        }
+        window.event(self.input_source.to_event());
+        window.event(reduce_motion_event(self.reduce_motion));
         schedule(&mut self.redraws, window.title_rect());
         schedule(&mut self.redraws, window.rect());
-        self.order.push_front(id);
+        self.hooks.run("open", Some((id, &window.title)));
+        self.policy.opened(id, &window);
+        self.order.set_rank(id, self.policy.stack_rank(&window, window.stack_rank()));
+        self.order.set_parent(id, window.parent);
+        if popup {
+            // Popups are stacked by their layer, and never take focus
+            self.order.lower(id);
+        } else if self.policy.focus_on_open(&window) && ! window.minimized
+                  && self.policy.focus(id, &window)
+                  && self.kiosk.as_ref().map_or(true, |kiosk| kiosk.allows_focus(id, &window)) {
+            if let Some(id) = self.order.focused() {
+                if let Some(window) = self.windows.get(&id){
+                    schedule(&mut self.redraws, window.title_rect());
+                }
+            }
+            self.hooks.run("focus", Some((id, &window.title)));
+            self.order.raise(id);
+        } else {
+            window.event(FocusEvent {
+                focused: false
+            }.to_event());
+            self.order.insert_behind(id);
+        }
+        if self.window_animations && ! self.reduce_motion && ! popup && window.visible(self.workspace) {
+            self.animations.push(WindowAnimation::open(id, window.rect()));
+        }
         self.windows.insert(id, window);
 
+        if let Some(window) = self.windows.get(&id) {
+            let line = format!("create {}", window_list::describe(id, window, self.order.is_focused(id)));
+            for list in self.window_lists.values_mut() {
+                list.push(&line);
+            }
+        }
+
+        if kiosk_app {
+            if let Some(ref mut kiosk) = self.kiosk {
+                kiosk.window = Some(id);
+                kiosk.owner = self.windows.get(&id).map(|window| window.owner);
+            }
+        }
+        if maximized {
+            self.toggle_maximize(id);
+        }
+        // Focused like any new window, only if the policy lets it take focus when it opens
+        if fullscreen || kiosk_app {
+            self.set_fullscreen(id, true);
+        }
+
         Ok(id)
     }
 
     fn read(&mut self, id: usize, buf: &mut [u8]) -> Result<usize> {
         if let Some(mut window) = self.windows.get_mut(&id) {
             window.read(buf)
+        } else if let Some(mut control) = self.controls.get_mut(&id) {
+            Ok(control.read(buf))
+        } else if let Some(mut handle) = self.clipboards.get_mut(&id) {
+            Ok(handle.read(buf))
+        } else if let Some(mut capture) = self.captures.get_mut(&id) {
+            capture.read(buf)
+        } else if let Some(mut handle) = self.notifications.get_mut(&id) {
+            handle.read(buf)
+        } else if let Some(mut list) = self.window_lists.get_mut(&id) {
+            list.read(buf)
         } else {
             Err(Error::new(EBADF))
         }
     }
 
     fn write(&mut self, id: usize, buf: &[u8]) -> Result<usize> {
+        // Fullscreen changes focus, so handle them before borrowing the window
+        if buf.starts_with(b"F,") && self.windows.contains_key(&id) {
+            return match str::from_utf8(&buf[2..]) {
+                Ok("1") => {
+                    self.set_fullscreen(id, true);
+                    self.focus(id);
+                    Ok(buf.len())
+                },
+                Ok("0") => {
+                    // The kiosk app stays fullscreen
+                    if self.kiosk.as_ref().map_or(true, |kiosk| kiosk.window != Some(id)) {
+                        self.set_fullscreen(id, false);
+                    }
+                    Ok(buf.len())
+                },
+                _ => Err(Error::new(EINVAL))
+            };
+        }
+
+        let screen_rect = self.screen_rect();
+        let work_rects = self.work_rects();
+        let focused = self.order.is_focused(id);
         if let Some(mut window) = self.windows.get_mut(&id) {
             if let Ok(msg) = str::from_utf8(buf) {
                 let mut parts = msg.split(',');
@@ -669,9 +4293,21 @@ impl SchemeMut for OrbitalScheme {
                         let x = parts.next().unwrap_or("").parse::<i32>().unwrap_or(window.x);
                         let y = parts.next().unwrap_or("").parse::<i32>().unwrap_or(window.y);
 
+                        // Keep positions near the screen, so window edges can not overflow
+                        let (x, y) = keep_near(&screen_rect, x, y, window.width(), window.height());
                         window.x = x;
                         window.y = y;
 
+                        // Keep the title bar on a display, telling the client where the window went
+                        if let Some((x, y)) = window.reachable_position(&work_rects) {
+                            window.x = x;
+                            window.y = y;
+                            window.event(MoveEvent {
+                                x: window.x,
+                                y: window.y
+                            }.to_event());
+                        }
+
                         schedule(&mut self.redraws, window.title_rect());
                         schedule(&mut self.redraws, window.rect());
 
@@ -683,27 +4319,325 @@ impl SchemeMut for OrbitalScheme {
 
                         let w = parts.next().unwrap_or("").parse::<i32>().unwrap_or(window.width());
                         let h = parts.next().unwrap_or("").parse::<i32>().unwrap_or(window.height());
+                        if w < 0 || h < 0 {
+                            return Err(Error::new(EINVAL));
+                        }
 
-                        window.set_size(w, h);
+                        // Limit sizes to the size hints and the screen, telling the client if it did not get the size it asked for
+                        let (hint_w, hint_h) = window.clamp_size(w, h);
+                        let (clamped_w, clamped_h) = fit_size(&screen_rect, hint_w, hint_h);
+                        // With a serial the old size stays on screen until the client has drawn the new one
+                        let synced = match parts.next().and_then(|serial| serial.parse::<u32>().ok()) {
+                            Some(serial) if window.sync_resize => window.begin_resize(serial, clamped_w, clamped_h),
+                            _ => false
+                        };
+                        if ! synced {
+                            window.set_size(clamped_w, clamped_h);
+                        }
+                        if clamped_w != w || clamped_h != h {
+                            let (x, y) = (window.x, window.y);
+                            window.request_resize(x, y, clamped_w, clamped_h);
+                        }
 
                         schedule(&mut self.redraws, window.title_rect());
                         schedule(&mut self.redraws, window.rect());
 
                         Ok(buf.len())
                     },
+                    Some("A") => {
+                        // Animate to a geometry, moving the window each frame and asking the
+                        // client to resize once at the end
+                        let x = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let y = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let w = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let h = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let duration = try!(parts.next().unwrap_or("").parse::<u64>().or(Err(Error::new(EINVAL))));
+                        if w < 0 || h < 0 {
+                            return Err(Error::new(EINVAL));
+                        }
+
+                        // Keep the size the window will have near the screen, not the one asked for
+                        let (hint_w, hint_h) = window.clamp_size(w, h);
+                        let (fit_w, fit_h) = fit_size(&screen_rect, hint_w, hint_h);
+                        let (x, y) = keep_near(&screen_rect, x, y, fit_w, fit_h);
+                        let mut animation = MoveAnimation::new(id, window.x, window.y, x, y, self.motion(Duration::from_millis(duration)));
+                        animation.resize = Some((fit_w, fit_h));
+                        self.moves.retain(|animation| animation.id != id);
+                        self.moves.push(animation);
+
+                        Ok(buf.len())
+                    },
+                    Some("B") => {
+                        let w = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let h = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        if w < 0 || h < 0 {
+                            return Err(Error::new(EINVAL));
+                        }
+
+                        // Buffers larger than the window may be scrolled with V, limited to a few screens
+                        window.set_buffer_size(min(w, screen_rect.width() * 4), min(h, screen_rect.height() * 4));
+
+                        schedule(&mut self.redraws, window.rect());
+
+                        Ok(buf.len())
+                    },
+                    Some("H") => {
+                        let min_w = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let min_h = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let max_w = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let max_h = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        if min_w < 0 || min_h < 0 || max_w < 0 || max_h < 0 {
+                            return Err(Error::new(EINVAL));
+                        }
+
+                        // A maximum of 0 means no limit
+                        let max_w = if max_w == 0 { i32::max_value() } else { max_w };
+                        let max_h = if max_h == 0 { i32::max_value() } else { max_h };
+                        if min_w > max_w || min_h > max_h {
+                            return Err(Error::new(EINVAL));
+                        }
+                        window.min_size = (min_w, min_h);
+                        window.max_size = (max_w, max_h);
+
+                        // Ask the client to resize if its current size is outside the hints
+                        let (w, h) = window.clamp_size(window.width(), window.height());
+                        if w != window.width() || h != window.height() {
+                            window.event(ResizeEvent {
+                                width: w as u32,
+                                height: h as u32
+                            }.to_event());
+                        }
+
+                        Ok(buf.len())
+                    },
+                    Some("W") => {
+                        // Draw into one buffer, or two that are flipped on fsync
+                        match parts.next() {
+                            Some("1") => window.set_double_buffered(false),
+                            Some("2") => window.set_double_buffered(true),
+                            _ => return Err(Error::new(EINVAL))
+                        }
+
+                        schedule(&mut self.redraws, window.rect());
+
+                        Ok(buf.len())
+                    },
+                    Some("R") => {
+                        // Rects of four numbers each, or none to take the mouse everywhere again
+                        let numbers = try!(parts.filter(|part| ! part.is_empty())
+                                                .map(|part| part.parse::<i32>().or(Err(Error::new(EINVAL))))
+                                                .collect::<Result<Vec<i32>>>());
+                        if numbers.len() % 4 != 0 {
+                            return Err(Error::new(EINVAL));
+                        }
+                        let mut region = Vec::new();
+                        for rect in numbers.chunks(4) {
+                            if rect[2] < 0 || rect[3] < 0 {
+                                return Err(Error::new(EINVAL));
+                            }
+                            region.push(Rect::new(rect[0], rect[1], rect[2], rect[3]));
+                        }
+                        window.input_region = region;
+
+                        Ok(buf.len())
+                    },
+                    Some("V") => {
+                        let x = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let y = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+
+                        // Scrolling only needs the compositor to redraw, not the client
+                        window.set_viewport(x, y);
+
+                        schedule(&mut self.redraws, window.rect());
+
+                        Ok(buf.len())
+                    },
+                    Some("D") => {
+                        let x = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let y = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let w = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        let h = try!(parts.next().unwrap_or("").parse::<i32>().or(Err(Error::new(EINVAL))));
+                        if w < 0 || h < 0 {
+                            return Err(Error::new(EINVAL));
+                        }
+
+                        // Damage is kept until the next fsync, which redraws only these areas
+                        let damage = Rect::new(x, y, w, h).intersection(&Rect::new(0, 0, window.width(), window.height()));
+                        if ! damage.is_empty() {
+                            window.damage.push(damage);
+                        }
+
+                        Ok(buf.len())
+                    },
+                    Some("O") => {
+                        let opacity = try!(parts.next().unwrap_or("").parse::<u8>().or(Err(Error::new(EINVAL))));
+                        window.client_opacity = opacity;
+
+                        schedule(&mut self.redraws, window.rect());
+
+                        Ok(buf.len())
+                    },
                     Some("T") => {
                         window.title = parts.next().unwrap_or("").to_string();
                         window.render_title(&self.font);
+                        for list in self.window_lists.values_mut() {
+                            list.push(&format!("title {} {}", id, window.title.replace('\n', " ")));
+                        }
 
                         schedule(&mut self.redraws, window.title_rect());
 
                         Ok(buf.len())
                     },
+                    Some("C") => {
+                        // An empty color goes back to the theme's colors
+                        window.accent = match parts.next().unwrap_or("") {
+                            "" => None,
+                            color => Some(try!(theme::parse_color(color).ok_or(Error::new(EINVAL))))
+                        };
+                        window.set_theme(self.theme, &self.font);
+
+                        schedule(&mut self.redraws, window.title_rect());
+
+                        Ok(buf.len())
+                    },
+                    Some("L") => {
+                        window.layer = try!(Layer::from_str(parts.next().unwrap_or("")).ok_or(Error::new(EINVAL)));
+                        let rank = self.policy.stack_rank(window, window.stack_rank());
+                        self.order.set_rank(id, rank);
+
+                        schedule(&mut self.redraws, window.title_rect());
+                        schedule(&mut self.redraws, window.rect());
+
+                        Ok(buf.len())
+                    },
+                    Some("I") => {
+                        match parts.next() {
+                            Some("1") => window.inhibit_idle = true,
+                            Some("0") => window.inhibit_idle = false,
+                            _ => return Err(Error::new(EINVAL))
+                        }
+
+                        Ok(buf.len())
+                    },
+                    Some("G") => {
+                        match parts.next() {
+                            Some("1") => self.gamepad_grab = Some(id),
+                            Some("0") => if self.gamepad_grab == Some(id) {
+                                self.gamepad_grab = None;
+                            },
+                            _ => return Err(Error::new(EINVAL))
+                        }
+
+                        Ok(buf.len())
+                    },
+                    Some("U") => {
+                        match parts.next() {
+                            // Focused windows already have the user's attention
+                            Some("1") => if ! focused && window.attention.is_none() {
+                                // Without motion the title bar is tinted steadily instead of pulsing
+                                let period = if self.reduce_motion { 0 } else { ATTENTION_PERIOD };
+                                window.attention = Some(Animation::new(Duration::from_millis(period)));
+                                self.hooks.run("attention", Some((id, &window.title)));
+                            },
+                            Some("0") => {
+                                window.attention = None;
+                                schedule(&mut self.redraws, window.title_rect());
+                            },
+                            _ => return Err(Error::new(EINVAL))
+                        }
+
+                        Ok(buf.len())
+                    },
                     _ => Err(Error::new(EINVAL))
                 }
             } else {
                 Err(Error::new(EINVAL))
             }
+        } else if let Some(mut handle) = self.clipboards.get_mut(&id) {
+            Ok(handle.write(buf))
+        } else if let Some(mut handle) = self.notifications.get_mut(&id) {
+            let result = handle.post(buf, self.notification_timeout);
+            let filter = self.quality.filter();
+            if let Some(ref mut notification) = handle.notification {
+                notification.icon = notification.icon.take().map(|icon| {
+                    scale_image(&icon, NOTIFICATION_ICON, NOTIFICATION_ICON, filter)
+                });
+            }
+            result
+        } else if self.controls.get(&id).map_or(false, |control| control.path == "control") {
+            let root = self.controls.get(&id).map_or(false, |control| control.uid == 0);
+            match str::from_utf8(buf).map(|msg| msg.trim()) {
+                Ok(msg) if (msg == "reload" || msg == "record" || msg.starts_with("theme ")) && ! root => {
+                    // These change the whole session, and recordings show every window
+                    Err(Error::new(EACCES))
+                },
+                Ok("reload") => {
+                    self.reload_config();
+                    Ok(buf.len())
+                },
+                Ok("record") => {
+                    self.toggle_recording();
+                    Ok(buf.len())
+                },
+                Ok(msg) if msg.starts_with("natural_scrolling ") => {
+                    // An optional device name, then whether it scrolls naturally
+                    let args: Vec<&str> = msg[18..].split_whitespace().collect();
+                    let (device, value) = match args.len() {
+                        1 => (None, args[0]),
+                        2 => (Some(args[0]), args[1]),
+                        _ => return Err(Error::new(EINVAL))
+                    };
+                    let natural = try!(value.parse::<bool>().or(Err(Error::new(EINVAL))));
+                    self.scroll_direction.set(device, natural);
+                    Ok(buf.len())
+                },
+                Ok(msg) if msg.starts_with("theme ") => {
+                    let mode = msg[6..].trim();
+                    self.theme_schedule.forced = if mode == "auto" {
+                        None
+                    } else {
+                        Some(try!(ThemeMode::from_str(mode).ok_or(Error::new(EINVAL))))
+                    };
+                    self.update_theme_mode();
+                    Ok(buf.len())
+                },
+                Ok(msg) if msg.starts_with("layout ") && ! root => {
+                    // Layouts are files written and read by the compositor, and move any window
+                    Err(Error::new(EACCES))
+                },
+                Ok(msg) if msg.starts_with("layout export ") => {
+                    if self.layout().save(msg[14..].trim()) {
+                        Ok(buf.len())
+                    } else {
+                        Err(Error::new(EIO))
+                    }
+                },
+                Ok(msg) if msg.starts_with("layout apply ") => {
+                    let layout = try!(Layout::load(msg[13..].trim()).ok_or(Error::new(EIO)));
+                    self.apply_layout(&layout);
+                    Ok(buf.len())
+                },
+                Ok(msg) if msg.starts_with("startup ") => {
+                    let (id, startup) = try!(Startup::parse(&msg[8..]));
+                    self.set_startup(&id, startup);
+                    Ok(buf.len())
+                },
+                Ok(msg) if msg.starts_with("preview ") => {
+                    let preview = try!(Preview::parse(&msg[8..]));
+                    if let Some(ref preview) = preview {
+                        if ! self.windows.contains_key(&preview.window) {
+                            return Err(Error::new(EINVAL));
+                        }
+                    }
+                    self.preview = preview;
+                    Ok(buf.len())
+                },
+                _ => Err(Error::new(EINVAL))
+            }
+        } else if self.controls.get(&id).map_or(false, |control| control.path == "manage") {
+            let msg = try!(str::from_utf8(buf).or(Err(Error::new(EINVAL))));
+            try!(self.manage(msg.trim()));
+            Ok(buf.len())
         } else {
             Err(Error::new(EBADF))
         }
@@ -727,15 +4661,59 @@ impl SchemeMut for OrbitalScheme {
 
     fn fpath(&mut self, id: usize, buf: &mut [u8]) -> Result<usize> {
         if let Some(window) = self.windows.get(&id) {
-            window.path(buf)
+            window.path(id, buf)
+        } else if self.controls.contains_key(&id) || self.clipboards.contains_key(&id) || self.captures.contains_key(&id)
+        || self.notifications.contains_key(&id) || self.window_lists.contains_key(&id) {
+            let path_string = match (self.controls.get(&id), self.captures.get(&id), self.window_lists.get(&id)) {
+                (Some(control), _, _) => format!("orbital:{}", control.path),
+                (None, Some(capture), _) => format!("orbital:{}", capture.path()),
+                (None, None, Some(list)) => format!("orbital:{}", list.path()),
+                (None, None, None) => if self.notifications.contains_key(&id) {
+                    "orbital:notify".to_string()
+                } else {
+                    "orbital:clipboard".to_string()
+                }
+            };
+            let path = path_string.as_bytes();
+            let mut i = 0;
+            while i < buf.len() && i < path.len() {
+                buf[i] = path[i];
+                i += 1;
+            }
+            Ok(i)
         } else {
             Err(Error::new(EBADF))
         }
     }
 
     fn fsync(&mut self, id: usize) -> Result<usize> {
-        if let Some(window) = self.windows.get(&id) {
-            schedule(&mut self.redraws, window.rect());
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            // Capture the composite that shows the frame the client just finished
+            for capture in self.captures.values_mut() {
+                if capture.window == Some(id) {
+                    capture.armed = true;
+                }
+            }
+            window.latency.response();
+            let bounds = window.bounds();
+            if window.commit_resize() {
+                // The new size and contents replace the old ones at once
+                schedule(&mut self.redraws, bounds);
+                schedule(&mut self.redraws, window.bounds());
+            } else {
+                // A double buffered client finished drawing into the back buffer, so show it
+                window.flip();
+            }
+            for damage in window.take_damage() {
+                schedule(&mut self.redraws, window.zoom_rect(&damage).offset(window.x, window.y));
+            }
+            // The thumbnail in the overview shows the new contents too
+            if let Some(cell) = self.overview.as_ref().and_then(|overview| overview.cell(id)) {
+                schedule(&mut self.redraws, cell);
+            }
+            Ok(0)
+        } else if self.clipboards.contains_key(&id) {
+            self.commit_clipboard(id);
             Ok(0)
         } else {
             Err(Error::new(EBADF))
@@ -743,21 +4721,139 @@ impl SchemeMut for OrbitalScheme {
     }
 
     fn close(&mut self, id: usize) -> Result<usize> {
-        self.order.retain(|&e| e != id);
+        if self.controls.remove(&id).is_some() || self.captures.remove(&id).is_some()
+        || self.notifications.remove(&id).is_some() || self.window_lists.remove(&id).is_some() {
+            return Ok(0);
+        }
 
-        if let Some(id) = self.order.front() {
-            if let Some(window) = self.windows.get(&id){
-                schedule(&mut self.redraws, window.title_rect());
-                schedule(&mut self.redraws, window.rect());
-            }
+        if self.clipboards.contains_key(&id) {
+            self.commit_clipboard(id);
+            self.clipboards.remove(&id);
+            return Ok(0);
         }
 
+        let focused = self.order.remove(id);
+
         if let Some(window) = self.windows.remove(&id) {
             schedule(&mut self.redraws, window.title_rect());
             schedule(&mut self.redraws, window.rect());
+            if let Some(cell) = self.overview.as_ref().and_then(|overview| overview.cell(id)) {
+                schedule(&mut self.redraws, cell);
+            }
+            self.hooks.run("close", Some((id, &window.title)));
+            if let Some(ref mut session) = self.session {
+                if window.parent.is_none() && ! window.popup {
+                    session.save(&window.title, window.restore.or(window.fullscreen).unwrap_or(window.rect()));
+                }
+            }
+            for list in self.window_lists.values_mut() {
+                list.push(&format!("destroy {}", id));
+            }
+            self.policy.closed(id);
+            if self.gamepad_grab == Some(id) {
+                self.gamepad_grab = None;
+            }
+            if self.pointer_window == Some(id) {
+                self.set_pointer_window(None);
+            }
+            if self.inspected == Some(id) {
+                self.inspected = None;
+            }
+            for seat in self.seats.iter_mut() {
+                if seat.focused == Some(id) {
+                    seat.focused = None;
+                }
+            }
+            if let Some(ref mut kiosk) = self.kiosk {
+                if kiosk.window == Some(id) {
+                    kiosk.window = None;
+                }
+            }
+            // Its dialogs become top level windows, so a new window given its id is not their parent
+            for other in self.windows.values_mut() {
+                if other.parent == Some(id) {
+                    other.parent = None;
+                }
+            }
+            for capture in self.captures.values_mut() {
+                if capture.window == Some(id) {
+                    capture.cancel();
+                }
+            }
+            if self.preview.as_ref().map_or(false, |preview| preview.window == id) {
+                self.preview = None;
+            }
+
+            if focused {
+                // Closing a dialog focuses its parent
+                let workspace = self.workspace;
+                let parent = window.parent.and_then(|parent| {
+                    self.windows.get(&parent).and_then(|parent_window| {
+                        if parent_window.visible(workspace) { Some(parent) } else { None }
+                    })
+                });
+                match parent {
+                    Some(parent) => self.refocus_window(parent),
+                    None => self.refocus()
+                }
+            }
+
+            // Keep the window to fade it out
+            self.animations.retain(|animation| animation.id != id);
+            if self.window_animations && ! self.reduce_motion && window.visible(self.workspace) {
+                self.animations.push(WindowAnimation::close(id, window.rect()));
+                self.closed.insert(id, window);
+            }
+
             Ok(0)
         } else {
             Err(Error::new(EBADF))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rect::Rect;
+
+    use super::{fit_size, keep_near};
+
+    const LIMITS: [i32; 7] = [i32::min_value(), -1_000_000, -1, 0, 1, 1_000_000, i32::max_value()];
+
+    #[test]
+    fn positions_stay_near_the_screen() {
+        let screen_rect = Rect::new(-1920, 0, 3840, 1080);
+        assert_eq!(keep_near(&screen_rect, 100, 200, 640, 480), (100, 200));
+        assert_eq!(keep_near(&screen_rect, i32::max_value(), i32::max_value(), 640, 480), (1920, 1080));
+        assert_eq!(keep_near(&screen_rect, i32::min_value(), i32::min_value(), 640, 480), (-2560, -480));
+        // Sizes past the screen do not overflow the far edge of the screen
+        assert_eq!(keep_near(&screen_rect, i32::min_value(), 0, i32::max_value(), 0), (i32::min_value(), 0));
+    }
+
+    #[test]
+    fn sizes_fit_the_screen() {
+        let screen_rect = Rect::new(0, 0, 1920, 1080);
+        assert_eq!(fit_size(&screen_rect, 640, 480), (640, 480));
+        assert_eq!(fit_size(&screen_rect, i32::max_value(), 480), (1920, 480));
+        assert_eq!(fit_size(&screen_rect, 640, i32::max_value()), (640, 1080));
+    }
+
+    #[test]
+    fn window_edges_never_overflow() {
+        let screens = [Rect::new(0, 0, 1920, 1080), Rect::new(-1920, -1080, 3840, 2160), Rect::new(100_000, -100_000, 800, 600)];
+        for screen_rect in screens.iter() {
+            for &x in LIMITS.iter() {
+                for &y in LIMITS.iter() {
+                    for &size in LIMITS.iter().filter(|&&size| size >= 0) {
+                        let (width, height) = fit_size(screen_rect, size, size);
+                        let (x, y) = keep_near(screen_rect, x, y, width, height);
+                        let right = x.checked_add(width).expect("right edge overflowed");
+                        let bottom = y.checked_add(height).expect("bottom edge overflowed");
+                        assert!(right >= screen_rect.left() && x <= screen_rect.right());
+                        assert!(bottom >= screen_rect.top() && y <= screen_rect.bottom());
+                    }
+                }
+            }
+        }
+    }
+}
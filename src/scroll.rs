@@ -0,0 +1,101 @@
+use orbclient::{Event, EventOption, ScrollEvent};
+use std::collections::BTreeMap;
+use std::mem;
+
+/// Direction of scrolling for each input device
+///
+/// With natural scrolling, the content moves with the fingers instead of the scroll bar, so the
+/// deltas of scroll events are inverted before they are handled or sent to windows. It is set for
+/// all devices with `natural_scrolling`, and for a device by its name with
+/// `natural_scrolling_device=<name>:<true or false>`, where events from displays are named
+/// `display`. Writing `natural_scrolling [<name>] <true or false>` to `orbital:control` changes it
+/// while running, and the change is kept when the config is reloaded.
+pub struct ScrollDirection {
+    natural: bool,
+    devices: BTreeMap<String, bool>,
+    /// Settings changed while running, which take precedence over the config
+    runtime_natural: Option<bool>,
+    runtime_devices: BTreeMap<String, bool>,
+}
+
+impl ScrollDirection {
+    pub fn new(natural: bool, devices: &[String]) -> ScrollDirection {
+        let mut direction = ScrollDirection {
+            natural: natural,
+            devices: BTreeMap::new(),
+            runtime_natural: None,
+            runtime_devices: BTreeMap::new()
+        };
+        for device in devices.iter() {
+            let mut parts = device.rsplitn(2, ':');
+            let value = parts.next().unwrap_or("").parse::<bool>();
+            match (parts.next(), value) {
+                (Some(name), Ok(natural)) if ! name.is_empty() => {
+                    direction.devices.insert(name.to_string(), natural);
+                },
+                _ => println!("orbital: invalid natural_scrolling_device '{}'", device)
+            }
+        }
+        direction
+    }
+
+    /// Use the settings of a reloaded config, keeping those changed while running
+    pub fn reload(&mut self, natural: bool, devices: &[String]) {
+        let mut direction = ScrollDirection::new(natural, devices);
+        direction.runtime_natural = self.runtime_natural;
+        mem::swap(&mut direction.runtime_devices, &mut self.runtime_devices);
+        *self = direction;
+    }
+
+    /// Set natural scrolling for a device, or for devices without their own setting
+    pub fn set(&mut self, device: Option<&str>, natural: bool) {
+        match device {
+            Some(name) => {
+                self.runtime_devices.insert(name.to_string(), natural);
+            },
+            None => self.runtime_natural = Some(natural)
+        }
+    }
+
+    /// Does a device scroll naturally
+    pub fn natural(&self, device: &str) -> bool {
+        self.runtime_devices.get(device).or(self.devices.get(device)).cloned()
+            .or(self.runtime_natural)
+            .unwrap_or(self.natural)
+    }
+
+    /// Invert a scroll event from a device that scrolls naturally, passing other events through
+    pub fn apply(&self, device: &str, event: Event) -> Event {
+        match event.to_option() {
+            EventOption::Scroll(scroll) if self.natural(device) => ScrollEvent {
+                x: -scroll.x,
+                y: -scroll.y
+            }.to_event(),
+            _ => event
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScrollDirection;
+
+    #[test]
+    fn device_settings_override_the_default() {
+        let direction = ScrollDirection::new(false, &["touchpad:true".to_string()]);
+        assert!(direction.natural("touchpad"));
+        assert!(! direction.natural("display"));
+    }
+
+    #[test]
+    fn runtime_settings_survive_a_reload() {
+        let mut direction = ScrollDirection::new(false, &[]);
+        direction.set(None, true);
+        direction.set(Some("mouse"), false);
+        direction.reload(false, &["touchpad:false".to_string()]);
+        assert!(direction.natural("display"));
+        assert!(! direction.natural("mouse"));
+        // Devices set in the config still have their own setting
+        assert!(! direction.natural("touchpad"));
+    }
+}
@@ -0,0 +1,51 @@
+/// An extra seat, a keyboard and mouse used by another person, set with `seat=` in the config
+///
+/// Each `seat=name:device,device` line lists the hot-plugged input devices of a seat. Devices
+/// not listed, and those of the displays, belong to the first seat, which uses the compositor's
+/// own cursor, focus and shortcuts. Extra seats have a cursor and a focused window of their own:
+/// clicking focuses a window for that seat, and its keys and gamepads go to that window, while
+/// its styluses draw into the window under them. Moving, resizing and shortcuts stay with the
+/// first seat. Keys arrive already translated by the keyboard driver, so all seats share the
+/// driver's keymap.
+pub struct Seat {
+    pub name: String,
+    devices: Vec<String>,
+    pub x: i32,
+    pub y: i32,
+    pub left_button: bool,
+    pub middle_button: bool,
+    pub right_button: bool,
+    /// Window receiving the keys of the seat
+    pub focused: Option<usize>,
+}
+
+impl Seat {
+    /// Parse a seat written as `name:device,device`
+    pub fn from_str(string: &str) -> Option<Seat> {
+        let mut parts = string.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let devices: Vec<String> = parts.next().unwrap_or("").split(',')
+            .map(|device| device.trim().to_string())
+            .filter(|device| ! device.is_empty())
+            .collect();
+        if name.is_empty() || devices.is_empty() {
+            return None;
+        }
+
+        Some(Seat {
+            name: name.to_string(),
+            devices: devices,
+            x: 0,
+            y: 0,
+            left_button: false,
+            middle_button: false,
+            right_button: false,
+            focused: None
+        })
+    }
+
+    /// Is an input device part of this seat
+    pub fn owns(&self, device: &str) -> bool {
+        self.devices.iter().any(|name| name == device)
+    }
+}
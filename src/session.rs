@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Write};
+
+use rect::Rect;
+
+/// Last geometry of top level windows by title, kept in the file set with `session_file`
+///
+/// Each line of the file is `<x> <y> <width> <height> <title>`. A window is saved as it closes,
+/// with its geometry before it was maximized or fullscreened, and a window opening with the same
+/// title and no position of its own is restored to it instead of being cascaded.
+pub struct Session {
+    path: String,
+    geometry: BTreeMap<String, Rect>,
+}
+
+impl Session {
+    /// Read the saved geometry, starting empty if there is no file yet
+    pub fn load(path: &str) -> Session {
+        let mut geometry = BTreeMap::new();
+
+        let mut string = String::new();
+        if let Ok(mut file) = File::open(path) {
+            if let Err(err) = file.read_to_string(&mut string) {
+                println!("orbital: failed to read session '{}': {}", path, err);
+            }
+        }
+
+        for line in string.lines() {
+            let mut parts = line.splitn(5, ' ');
+            let mut next = || parts.next().unwrap_or("").parse::<i32>().ok();
+            if let (Some(x), Some(y), Some(w), Some(h)) = (next(), next(), next(), next()) {
+                if let Some(title) = parts.next() {
+                    if w > 0 && h > 0 {
+                        geometry.insert(title.to_string(), Rect::new(x, y, w, h));
+                    }
+                }
+            }
+        }
+
+        Session {
+            path: path.to_string(),
+            geometry: geometry
+        }
+    }
+
+    /// Saved geometry of windows with a title
+    pub fn get(&self, title: &str) -> Option<Rect> {
+        self.geometry.get(title).cloned()
+    }
+
+    /// Remember the geometry of a closing window, and write the file
+    pub fn save(&mut self, title: &str, rect: Rect) {
+        if title.is_empty() || title.contains('\n') || rect.is_empty() {
+            return;
+        }
+        if self.geometry.get(title) == Some(&rect) {
+            return;
+        }
+        self.geometry.insert(title.to_string(), rect);
+
+        let mut data = String::new();
+        for (title, rect) in self.geometry.iter() {
+            data.push_str(&format!("{} {} {} {} {}\n", rect.left(), rect.top(), rect.width(), rect.height(), title));
+        }
+        if let Err(err) = File::create(&self.path).and_then(|mut file| file.write_all(data.as_bytes())) {
+            println!("orbital: failed to write session '{}': {}", self.path, err);
+        }
+    }
+}
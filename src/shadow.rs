@@ -0,0 +1,82 @@
+use image::ImageRef;
+use rect::Rect;
+
+/// Alpha of the shadow next to the window
+const SHADOW_ALPHA: f32 = 96.0;
+
+/// Soft shadow drawn around the focused window
+pub struct Shadow {
+    size: i32,
+    /// Shadow alpha by distance from the window, following a gaussian falloff
+    profile: Vec<u32>,
+}
+
+impl Shadow {
+    pub fn new(size: i32) -> Shadow {
+        let mut profile = Vec::new();
+        for d in 0..size {
+            let t = d as f32 / size as f32;
+            profile.push((SHADOW_ALPHA * (-4.5 * t * t).exp()) as u32);
+        }
+
+        Shadow {
+            size: size,
+            profile: profile
+        }
+    }
+
+    /// Area covered by the shadow of a window, including the window
+    pub fn rect(&self, window_rect: &Rect) -> Rect {
+        Rect::new(window_rect.left() - self.size, window_rect.top() - self.size,
+                  window_rect.width() + self.size * 2, window_rect.height() + self.size * 2)
+    }
+
+    /// Darken the area around a window inside a clip rect
+    pub fn draw(&self, image: &mut ImageRef, window_rect: &Rect, clip: &Rect) {
+        let origin = image.bounds();
+        let intersect = clip.intersection(&self.rect(window_rect)).intersection(&origin);
+        if intersect.is_empty() {
+            return;
+        }
+
+        let stride = image.width();
+        let data = image.data_mut();
+        for y in intersect.top()..intersect.bottom() {
+            let dy = if y < window_rect.top() {
+                window_rect.top() - 1 - y
+            } else if y >= window_rect.bottom() {
+                y - window_rect.bottom()
+            } else {
+                -1
+            };
+
+            for x in intersect.left()..intersect.right() {
+                let dx = if x < window_rect.left() {
+                    window_rect.left() - 1 - x
+                } else if x >= window_rect.right() {
+                    x - window_rect.right()
+                } else {
+                    -1
+                };
+
+                // Inside the window, which covers the shadow
+                if dx < 0 && dy < 0 {
+                    continue;
+                }
+
+                let alpha = match (dx >= 0, dy >= 0) {
+                    (true, true) => self.profile[dx as usize] * self.profile[dy as usize] / SHADOW_ALPHA as u32,
+                    (true, false) => self.profile[dx as usize],
+                    _ => self.profile[dy as usize]
+                };
+
+                let pixel = &mut data[((y - origin.top()) * stride + x - origin.left()) as usize];
+                let n_alpha = 255 - alpha;
+                let r = (((pixel.data >> 16) & 0xFF) * n_alpha) >> 8;
+                let g = (((pixel.data >> 8) & 0xFF) * n_alpha) >> 8;
+                let b = ((pixel.data & 0xFF) * n_alpha) >> 8;
+                pixel.data = 0xFF000000 | (r << 16) | (g << 8) | b;
+            }
+        }
+    }
+}
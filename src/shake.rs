@@ -0,0 +1,60 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Distance the cursor must travel in one direction for a change of direction to count
+const SHAKE_DISTANCE: i32 = 32;
+/// Changes of direction needed within `SHAKE_WINDOW` to count as a shake
+const SHAKE_REVERSALS: usize = 4;
+/// Time in milliseconds in which the changes of direction must happen
+const SHAKE_WINDOW: u64 = 600;
+
+/// Detects the cursor being shaken left and right to find it
+pub struct Shake {
+    /// Horizontal position where the cursor last changed direction
+    start_x: i32,
+    last_x: i32,
+    /// Direction of the current movement, -1 for left, 1 for right, 0 before it moves
+    direction: i32,
+    reversals: VecDeque<Instant>,
+}
+
+impl Shake {
+    pub fn new() -> Shake {
+        Shake {
+            start_x: 0,
+            last_x: 0,
+            direction: 0,
+            reversals: VecDeque::new()
+        }
+    }
+
+    /// Track a cursor movement, returning true when it completes a shake
+    pub fn motion(&mut self, x: i32) -> bool {
+        let dx = x - self.last_x;
+        self.last_x = x;
+        if dx == 0 {
+            return false;
+        }
+
+        let direction = if dx > 0 { 1 } else { -1 };
+        if direction != self.direction {
+            // Only count direction changes after moving far enough, so jitter is not a shake
+            if self.direction != 0 && (x - dx - self.start_x).abs() >= SHAKE_DISTANCE {
+                self.reversals.push_back(Instant::now());
+            }
+            self.start_x = x - dx;
+            self.direction = direction;
+        }
+
+        while self.reversals.front().map_or(false, |time| time.elapsed() > Duration::from_millis(SHAKE_WINDOW)) {
+            self.reversals.pop_front();
+        }
+
+        if self.reversals.len() >= SHAKE_REVERSALS {
+            self.reversals.clear();
+            true
+        } else {
+            false
+        }
+    }
+}
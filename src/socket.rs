@@ -40,6 +40,10 @@ impl Socket {
         unsafe { (*self.file.get()).write(buf) }
     }
 
+    pub fn send_type<T: Copy>(&self, buf: &[T]) -> Result<usize> {
+        self.send(unsafe { slice::from_raw_parts(buf.as_ptr() as *const u8, buf.len() * mem::size_of::<T>()) }).map(|count| count/mem::size_of::<T>())
+    }
+
     pub fn sync(&self) -> Result<()> {
         unsafe { (*self.file.get()).sync_data() }
     }
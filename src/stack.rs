@@ -0,0 +1,379 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::collections::vec_deque::Iter;
+
+/// Rank of windows whose layer has not been set, that of normal windows
+const DEFAULT_RANK: u8 = 1;
+
+/// Stacking and focus order of windows
+///
+/// Every window id appears exactly once in each order. Windows are stacked by the rank of their
+/// layer, highest first, and then most recently raised first, with dialogs directly above their
+/// parent. The order is kept as windows are raised, lowered, or change layer, so drawing and hit
+/// testing only walk it. Within a layer, windows are in focus order, so the focused window is
+/// at the top of its layer. The focused window is the most recently raised, unless it was focused
+/// without raising it.
+pub struct Stack {
+    /// Window ids from the top of the stack to the bottom
+    stacked: Vec<usize>,
+    /// Window ids, most recently raised first
+    recent: VecDeque<usize>,
+    ranks: BTreeMap<usize, u8>,
+    /// Parents of dialogs
+    parents: BTreeMap<usize, usize>,
+    /// Window focused without being raised, the most recently raised if none
+    focused: Option<usize>,
+}
+
+impl Stack {
+    pub fn new() -> Stack {
+        Stack {
+            stacked: Vec::new(),
+            recent: VecDeque::new(),
+            ranks: BTreeMap::new(),
+            parents: BTreeMap::new(),
+            focused: None
+        }
+    }
+
+    /// The focused window
+    pub fn focused(&self) -> Option<usize> {
+        self.focused.or(self.recent.front().cloned())
+    }
+
+    pub fn is_focused(&self, id: usize) -> bool {
+        self.focused() == Some(id)
+    }
+
+    /// Window ids, most recently focused first
+    pub fn iter(&self) -> Iter<usize> {
+        self.recent.iter()
+    }
+
+    /// Window ids from the top of the stack to the bottom
+    pub fn stacked(&self) -> &[usize] {
+        &self.stacked
+    }
+
+    /// Windows listed by the window switcher, most recently focused first
+    pub fn switcher<F: Fn(usize) -> bool>(&self, listed: F) -> Vec<usize> {
+        self.recent.iter().cloned().filter(|&id| listed(id)).collect()
+    }
+
+    /// Index of the window selected in a switcher listing `count` windows, after moving forward
+    /// or backward from `selected`, wrapping around at the ends
+    pub fn switch(selected: usize, count: usize, forward: bool) -> usize {
+        if count == 0 {
+            0
+        } else if forward {
+            (selected + 1) % count
+        } else {
+            (selected + count - 1) % count
+        }
+    }
+
+    /// Set the rank of a window's layer, moving it into that layer if it changed
+    pub fn set_rank(&mut self, id: usize, rank: u8) {
+        if self.ranks.insert(id, rank) != Some(rank) && self.stacked.contains(&id) {
+            self.place(id);
+            self.keep_dialogs_above();
+        }
+    }
+
+    /// Make a window a dialog stacked directly above its parent
+    pub fn set_parent(&mut self, id: usize, parent: Option<usize>) {
+        match parent {
+            Some(parent) => self.parents.insert(id, parent),
+            None => self.parents.remove(&id)
+        };
+        self.keep_dialogs_above();
+    }
+
+    /// Move a window to the top of its layer and focus it, adding it if it is new
+    pub fn raise(&mut self, id: usize) {
+        self.recent.retain(|&e| e != id);
+        self.recent.push_front(id);
+        self.stacked.retain(|&e| e != id);
+        let i = self.layer_top(self.rank(id));
+        self.stacked.insert(i, id);
+        self.focused = None;
+        self.keep_dialogs_above();
+    }
+
+    /// Focus a window without moving it
+    pub fn focus(&mut self, id: usize) {
+        self.focused = if self.recent.front() == Some(&id) { None } else { Some(id) };
+    }
+
+    /// Move a window to the bottom of its layer, adding it if it is new
+    pub fn lower(&mut self, id: usize) {
+        self.recent.retain(|&e| e != id);
+        self.recent.push_back(id);
+        self.stacked.retain(|&e| e != id);
+        let i = self.layer_bottom(self.rank(id));
+        self.stacked.insert(i, id);
+        if self.focused == Some(id) {
+            self.focused = None;
+        }
+        self.keep_dialogs_above();
+    }
+
+    /// Add a window just behind the most recently raised window, or at the top of its layer if
+    /// that window is in another layer
+    pub fn insert_behind(&mut self, id: usize) {
+        self.recent.retain(|&e| e != id);
+        self.stacked.retain(|&e| e != id);
+        let rank = self.rank(id);
+        let front = self.recent.front().cloned();
+        let i = match front.and_then(|front| self.stacked.iter().position(|&e| e == front)) {
+            Some(front_i) if self.rank(self.stacked[front_i]) == rank => front_i + 1,
+            _ => self.layer_top(rank)
+        };
+        self.stacked.insert(i, id);
+        let recent_i = if self.recent.is_empty() { 0 } else { 1 };
+        self.recent.insert(recent_i, id);
+        self.keep_dialogs_above();
+    }
+
+    /// Remove a closed window, returning true if it was focused
+    pub fn remove(&mut self, id: usize) -> bool {
+        let focused = self.is_focused(id);
+        self.recent.retain(|&e| e != id);
+        self.stacked.retain(|&e| e != id);
+        self.ranks.remove(&id);
+        self.parents.remove(&id);
+        // Its dialogs become top level windows, stacked by focus order
+        let dialogs: Vec<usize> = self.parents.iter().filter(|&(_, &parent)| parent == id).map(|(&dialog, _)| dialog).collect();
+        for dialog in dialogs {
+            self.parents.remove(&dialog);
+            self.place(dialog);
+        }
+        self.keep_dialogs_above();
+        if self.focused == Some(id) {
+            self.focused = None;
+        }
+        focused
+    }
+
+    /// Move a window into its layer, below the windows in it that were focused more recently
+    fn place(&mut self, id: usize) {
+        self.stacked.retain(|&e| e != id);
+        let rank = self.rank(id);
+        let recent_i = self.recent.iter().position(|&e| e == id).unwrap_or(self.recent.len());
+        let i = self.stacked.iter().position(|&e| {
+            let e_rank = self.rank(e);
+            e_rank < rank || (e_rank == rank && self.recent.iter().position(|&r| r == e).map_or(true, |e_i| e_i > recent_i))
+        }).unwrap_or(self.stacked.len());
+        self.stacked.insert(i, id);
+    }
+
+    fn rank(&self, id: usize) -> u8 {
+        self.ranks.get(&id).cloned().unwrap_or(DEFAULT_RANK)
+    }
+
+    /// Index of the top of a layer, where a window raised in it goes
+    fn layer_top(&self, rank: u8) -> usize {
+        self.stacked.iter().position(|&e| self.rank(e) <= rank).unwrap_or(self.stacked.len())
+    }
+
+    /// Index just below the bottom of a layer, where a window lowered in it goes
+    fn layer_bottom(&self, rank: u8) -> usize {
+        self.stacked.iter().position(|&e| self.rank(e) < rank).unwrap_or(self.stacked.len())
+    }
+
+    /// Move dialogs stacked below their parent to directly above it, giving up on cycles
+    fn keep_dialogs_above(&mut self) {
+        let mut moves = 0;
+        let mut i = 0;
+        while i < self.stacked.len() && moves <= self.stacked.len() * self.stacked.len() {
+            let parent_i = self.parents.get(&self.stacked[i])
+                .and_then(|&parent| self.stacked.iter().position(|&e| e == parent));
+            match parent_i {
+                Some(parent_i) if parent_i < i => {
+                    let id = self.stacked.remove(i);
+                    self.stacked.insert(parent_i, id);
+                    moves += 1;
+                },
+                _ => i += 1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Stack;
+
+    /// Every window is listed once in each order, and the focused window is at the top of its
+    /// layer when it was raised
+    fn check(stack: &Stack) {
+        let stacked = stack.stacked();
+        for (i, id) in stacked.iter().enumerate() {
+            assert!(! stacked[i + 1 ..].contains(id), "{} stacked twice in {:?}", id, stacked);
+        }
+        assert_eq!(stack.iter().count(), stacked.len());
+        for id in stack.iter() {
+            assert!(stacked.contains(id), "{} not stacked in {:?}", id, stacked);
+        }
+        for pair in stacked.windows(2) {
+            assert!(stack.rank(pair[0]) >= stack.rank(pair[1]) || stack.parents.contains_key(&pair[0]),
+                    "layers out of order in {:?}", stacked);
+        }
+        if let (None, Some(focused)) = (stack.focused, stack.focused()) {
+            let rank = stack.rank(focused);
+            let top = stacked.iter().find(|&&id| stack.rank(id) == rank && ! stack.parents.contains_key(&id));
+            assert_eq!(top, Some(&focused));
+        }
+    }
+
+    fn stack_of(ids: &[usize]) -> Stack {
+        let mut stack = Stack::new();
+        for &id in ids.iter().rev() {
+            stack.raise(id);
+        }
+        check(&stack);
+        stack
+    }
+
+    #[test]
+    fn raise_moves_to_top_and_focuses() {
+        let mut stack = stack_of(&[1, 2, 3]);
+        stack.raise(3);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[3, 1, 2]);
+        assert_eq!(stack.focused(), Some(3));
+
+        stack.raise(3);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[3, 1, 2]);
+    }
+
+    #[test]
+    fn lower_moves_to_bottom_and_unfocuses() {
+        let mut stack = stack_of(&[1, 2, 3]);
+        stack.lower(1);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[2, 3, 1]);
+        assert_eq!(stack.focused(), Some(2));
+        assert_eq!(stack.iter().cloned().collect::<Vec<usize>>(), vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn focus_without_raising() {
+        let mut stack = stack_of(&[1, 2, 3]);
+        stack.focus(2);
+        check(&stack);
+        assert_eq!(stack.focused(), Some(2));
+        assert!(stack.is_focused(2));
+        assert_eq!(stack.stacked(), &[1, 2, 3]);
+
+        stack.lower(2);
+        check(&stack);
+        assert_eq!(stack.focused(), Some(1));
+
+        stack.focus(1);
+        assert_eq!(stack.focused, None);
+    }
+
+    #[test]
+    fn insert_behind_the_front_window() {
+        let mut stack = stack_of(&[1, 2]);
+        stack.insert_behind(3);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[1, 3, 2]);
+        assert_eq!(stack.focused(), Some(1));
+
+        let mut empty = Stack::new();
+        empty.insert_behind(1);
+        check(&empty);
+        assert_eq!(empty.focused(), Some(1));
+    }
+
+    #[test]
+    fn remove_closed_windows() {
+        let mut stack = stack_of(&[1, 2, 3]);
+        assert!(stack.remove(1));
+        check(&stack);
+        assert_eq!(stack.focused(), Some(2));
+        assert!(! stack.remove(3));
+        assert!(! stack.remove(4));
+        check(&stack);
+        assert_eq!(stack.stacked(), &[2]);
+    }
+
+    #[test]
+    fn layers_stack_above_each_other() {
+        let mut stack = Stack::new();
+        stack.set_rank(1, 0);
+        stack.set_rank(3, 2);
+        for id in 1..5 {
+            stack.raise(id);
+            check(&stack);
+        }
+        assert_eq!(stack.stacked(), &[3, 4, 2, 1]);
+
+        // Raising stays inside the layer, and lowering stays above lower layers
+        stack.raise(2);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[3, 2, 4, 1]);
+        stack.lower(3);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[3, 2, 4, 1]);
+        stack.lower(2);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[3, 4, 2, 1]);
+    }
+
+    #[test]
+    fn changing_layer_keeps_focus_order() {
+        let mut stack = stack_of(&[1, 2, 3]);
+        stack.set_rank(3, 2);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[3, 1, 2]);
+        stack.set_rank(3, 1);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[1, 2, 3]);
+        stack.set_rank(1, 0);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[2, 3, 1]);
+    }
+
+    #[test]
+    fn dialogs_stay_above_their_parent() {
+        let mut stack = stack_of(&[1, 2]);
+        stack.set_parent(3, Some(2));
+        stack.insert_behind(3);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[1, 3, 2]);
+
+        stack.raise(2);
+        check(&stack);
+        assert_eq!(stack.stacked(), &[3, 2, 1]);
+
+        // Closing the parent makes the dialog a top level window
+        stack.remove(2);
+        check(&stack);
+        assert!(stack.parents.is_empty());
+        assert_eq!(stack.stacked(), &[1, 3]);
+    }
+
+    #[test]
+    fn parent_cycles_end() {
+        let mut stack = stack_of(&[1, 2]);
+        stack.set_parent(1, Some(2));
+        stack.set_parent(2, Some(1));
+        stack.raise(1);
+        assert_eq!(stack.stacked().len(), 2);
+    }
+
+    #[test]
+    fn switcher_lists_recent_windows() {
+        let mut stack = stack_of(&[1, 2, 3]);
+        stack.raise(3);
+        assert_eq!(stack.switcher(|id| id != 2), vec![3, 1]);
+
+        assert_eq!(Stack::switch(0, 3, true), 1);
+        assert_eq!(Stack::switch(2, 3, true), 0);
+        assert_eq!(Stack::switch(0, 3, false), 2);
+        assert_eq!(Stack::switch(0, 0, true), 0);
+    }
+}
@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+use syscall::error::{Error, Result, EINVAL};
+
+use rules::RuleAction;
+
+/// Time in milliseconds an app has to open its first window after it is launched
+const STARTUP_TIMEOUT: u64 = 10000;
+
+/// An app being launched, announced by a launcher writing `startup <id> <actions>` to
+/// `orbital:control`
+///
+/// The launcher passes the id to the app in `ORBITAL_STARTUP_ID`, and the app adds `!` and the id
+/// to the flags of its first window, such as `r!launcher-3`. Until that window opens or the
+/// startup times out, the cursor shows `cursor_busy`. The actions, written as in the `[rules]`
+/// config section, apply to the window as it opens, such as `workspace:2 position:100,100`.
+/// Writing `startup <id> cancel` ends a startup whose app failed to run.
+pub struct Startup {
+    pub id: String,
+    pub actions: Vec<RuleAction>,
+    expires: Instant,
+}
+
+impl Startup {
+    pub fn new(id: String, actions: Vec<RuleAction>) -> Startup {
+        Startup {
+            id: id,
+            actions: actions,
+            expires: Instant::now() + Duration::from_millis(STARTUP_TIMEOUT)
+        }
+    }
+
+    /// Parse the arguments of a startup announcement, returning the id and None to cancel it
+    pub fn parse(args: &str) -> Result<(String, Option<Startup>)> {
+        let mut parts = args.split_whitespace();
+        let id = try!(parts.next().ok_or(Error::new(EINVAL))).to_string();
+
+        let mut actions = Vec::new();
+        for action_str in parts {
+            if action_str == "cancel" {
+                return Ok((id, None));
+            }
+            actions.push(try!(RuleAction::from_str(action_str).ok_or(Error::new(EINVAL))));
+        }
+
+        Ok((id.clone(), Some(Startup::new(id, actions))))
+    }
+
+    /// Has the app taken too long to open a window
+    pub fn expired(&self) -> bool {
+        Instant::now() >= self.expires
+    }
+}
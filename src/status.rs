@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+/// How long an error keeps the compositor reported as degraded
+const ERROR_HOLD: u64 = 60;
+
+/// Startup progress and recent errors, read from `orbital:status` by init and service monitors
+pub struct Status {
+    /// Initialization phase, `starting` until the first frame is shown and `running` after
+    pub phase: &'static str,
+    started: Instant,
+    frames: u64,
+    last_frame: Option<Instant>,
+    last_error: Option<(Instant, String)>,
+}
+
+impl Status {
+    pub fn new() -> Status {
+        Status {
+            phase: "starting",
+            started: Instant::now(),
+            frames: 0,
+            last_frame: None,
+            last_error: None
+        }
+    }
+
+    /// Record a frame shown on the displays
+    pub fn frame(&mut self) {
+        self.phase = "running";
+        self.frames += 1;
+        self.last_frame = Some(Instant::now());
+    }
+
+    /// Log an error and remember it as the last one
+    pub fn error(&mut self, message: String) {
+        println!("orbital: {}", message);
+        self.last_error = Some((Instant::now(), message));
+    }
+
+    /// Status lines, with `health=degraded` for a minute after an error
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        let degraded = self.last_error.as_ref().map_or(false, |&(time, _)| time.elapsed() < Duration::from_secs(ERROR_HOLD));
+        report.push_str(&format!("phase={}\n", self.phase));
+        report.push_str(&format!("health={}\n", if degraded { "degraded" } else { "ok" }));
+        report.push_str(&format!("uptime={}\n", self.started.elapsed().as_secs()));
+        report.push_str(&format!("frames={}\n", self.frames));
+        match self.last_frame {
+            Some(time) => {
+                let elapsed = time.elapsed();
+                report.push_str(&format!("last_frame={}\n", elapsed.as_secs() * 1000 + (elapsed.subsec_nanos() / 1000000) as u64));
+            },
+            None => report.push_str("last_frame=none\n")
+        }
+        match self.last_error {
+            Some((time, ref message)) => report.push_str(&format!("last_error={} {}\n", time.elapsed().as_secs(), message)),
+            None => report.push_str("last_error=none\n")
+        }
+        report
+    }
+}
@@ -0,0 +1,60 @@
+use orbclient::{Event, MouseEvent};
+
+/// Event code of stylus events, which orbclient does not define
+///
+/// `a` holds the position, x in the high 32 bits and y in the low 32 bits. `b` holds the
+/// pressure in bits 0-15, the tilt in degrees as signed bytes in bits 16-23 for x and 24-31 for
+/// y, and the eraser and barrel button in bits 32 and 33.
+pub const EVENT_STYLUS: i64 = 0x5354;
+
+/// A stylus moving over or touching the screen
+#[derive(Clone, Copy)]
+pub struct StylusEvent {
+    pub x: i32,
+    pub y: i32,
+    /// Tip pressure, zero while hovering
+    pub pressure: u16,
+    pub tilt_x: i8,
+    pub tilt_y: i8,
+    /// The stylus is upside down, erasing with its other end
+    pub eraser: bool,
+    pub barrel: bool,
+}
+
+impl StylusEvent {
+    pub fn from_event(event: Event) -> StylusEvent {
+        StylusEvent {
+            x: (event.a >> 32) as i32,
+            y: event.a as i32,
+            pressure: event.b as u16,
+            tilt_x: (event.b >> 16) as i8,
+            tilt_y: (event.b >> 24) as i8,
+            eraser: event.b & (1 << 32) != 0,
+            barrel: event.b & (1 << 33) != 0
+        }
+    }
+
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: EVENT_STYLUS,
+            a: ((self.x as i64) << 32) | (self.y as u32 as i64),
+            b: self.pressure as i64
+                | ((self.tilt_x as u8 as i64) << 16)
+                | ((self.tilt_y as u8 as i64) << 24)
+                | if self.eraser { 1 << 32 } else { 0 }
+                | if self.barrel { 1 << 33 } else { 0 }
+        }
+    }
+
+    /// Mouse event for windows that do not handle the stylus, pressing the left button with the
+    /// tip and the right button with the barrel button
+    pub fn to_mouse(&self) -> MouseEvent {
+        MouseEvent {
+            x: self.x,
+            y: self.y,
+            left_button: self.pressure > 0,
+            middle_button: false,
+            right_button: self.barrel
+        }
+    }
+}
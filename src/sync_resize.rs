@@ -0,0 +1,17 @@
+use orbclient::Event;
+
+/// Event code sent just before each `ResizeEvent` to windows opened with the `y` flag, numbering
+/// the request
+///
+/// The `a` field is the serial. The client answers with `S,<width>,<height>,<serial>` and draws
+/// into the buffers it then maps, and the window keeps its old size and contents on screen until
+/// the next fsync shows the new ones together.
+pub const EVENT_RESIZE_SERIAL: i64 = 0x5253;
+
+pub fn resize_serial_event(serial: u32) -> Event {
+    Event {
+        code: EVENT_RESIZE_SERIAL,
+        a: serial as i64,
+        b: 0
+    }
+}
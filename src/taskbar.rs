@@ -0,0 +1,119 @@
+use std::cmp::min;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rect::Rect;
+
+/// Height of the task bar
+const TASKBAR_HEIGHT: i32 = 28;
+/// Width of the clock at the end of the task bar
+const CLOCK_WIDTH: i32 = 64;
+/// Widest a window button grows to when there are few windows
+const BUTTON_WIDTH: i32 = 200;
+
+/// Edge of the first display the task bar is drawn along, set with `taskbar`
+#[derive(Clone, Copy, PartialEq)]
+pub enum TaskbarPosition {
+    None,
+    Top,
+    Bottom,
+}
+
+impl TaskbarPosition {
+    pub fn from_str(string: &str) -> TaskbarPosition {
+        match string {
+            "top" => TaskbarPosition::Top,
+            "bottom" => TaskbarPosition::Bottom,
+            _ => TaskbarPosition::None
+        }
+    }
+}
+
+/// The button of a window on the task bar
+#[derive(Clone, PartialEq)]
+pub struct TaskButton {
+    pub id: usize,
+    pub title: String,
+    pub focused: bool,
+    pub minimized: bool,
+}
+
+/// A bar along an edge of the first display with a button for each window on the workspace,
+/// and a clock
+///
+/// Clicking a button focuses its window, restoring it if it is minimized. The bar is drawn above
+/// other windows, but windows are maximized and placed beside it, and fullscreen windows hide it.
+pub struct Taskbar {
+    pub position: TaskbarPosition,
+    /// Buttons and clock last drawn, to redraw the bar when they change
+    drawn: Option<(Vec<TaskButton>, String)>,
+}
+
+impl Taskbar {
+    pub fn new(position: TaskbarPosition) -> Taskbar {
+        Taskbar {
+            position: position,
+            drawn: None
+        }
+    }
+
+    /// Area of the bar on a display
+    pub fn rect(&self, display: &Rect) -> Rect {
+        match self.position {
+            TaskbarPosition::None => Rect::new(-1, -1, 0, 0),
+            TaskbarPosition::Top => Rect::new(display.left(), display.top(), display.width(), TASKBAR_HEIGHT),
+            TaskbarPosition::Bottom => Rect::new(display.left(), display.bottom() - TASKBAR_HEIGHT, display.width(), TASKBAR_HEIGHT)
+        }
+    }
+
+    /// Area of a display left for windows beside the bar
+    pub fn work_rect(&self, display: &Rect) -> Rect {
+        match self.position {
+            TaskbarPosition::None => *display,
+            TaskbarPosition::Top => Rect::new(display.left(), display.top() + TASKBAR_HEIGHT, display.width(), display.height() - TASKBAR_HEIGHT),
+            TaskbarPosition::Bottom => Rect::new(display.left(), display.top(), display.width(), display.height() - TASKBAR_HEIGHT)
+        }
+    }
+
+    /// Areas of the window buttons in a bar, which shrink to fit beside the clock
+    pub fn button_rects(&self, rect: &Rect, count: usize) -> Vec<Rect> {
+        if count == 0 {
+            return Vec::new();
+        }
+
+        let w = min(BUTTON_WIDTH, (rect.width() - CLOCK_WIDTH) / count as i32);
+        (0..count).map(|i| {
+            Rect::new(rect.left() + i as i32 * w + 2, rect.top() + 2, w - 4, rect.height() - 4)
+        }).collect()
+    }
+
+    /// Area of the clock in a bar
+    pub fn clock_rect(&self, rect: &Rect) -> Rect {
+        Rect::new(rect.right() - CLOCK_WIDTH, rect.top(), CLOCK_WIDTH, rect.height())
+    }
+
+    /// Remember the buttons and clock about to be drawn, returns true if they changed
+    pub fn update(&mut self, buttons: Vec<TaskButton>, clock: String) -> bool {
+        let changed = self.drawn.as_ref().map_or(true, |&(ref drawn_buttons, ref drawn_clock)| {
+            *drawn_buttons != buttons || *drawn_clock != clock
+        });
+        self.drawn = Some((buttons, clock));
+        changed
+    }
+
+    /// Has the time shown by the clock passed
+    pub fn clock_changed(&self) -> bool {
+        self.drawn.as_ref().map_or(false, |&(_, ref drawn_clock)| *drawn_clock != clock())
+    }
+}
+
+/// Minutes since midnight UTC
+pub fn minutes_of_day() -> u32 {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+    (time / 60 % (24 * 60)) as u32
+}
+
+/// Time of day in UTC, as hours and minutes
+pub fn clock() -> String {
+    let minutes = minutes_of_day();
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
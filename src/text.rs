@@ -0,0 +1,71 @@
+use orbclient::Color;
+use orbfont::Font;
+use std::collections::VecDeque;
+
+use image::Image;
+
+struct TextEntry {
+    text: String,
+    height: f32,
+    color: Color,
+    background: Color,
+    image: Image,
+}
+
+/// Rendered text images, most recently used first, bounded by total size in bytes
+pub struct TextCache {
+    entries: VecDeque<TextEntry>,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+impl TextCache {
+    pub fn new(max_bytes: usize) -> TextCache {
+        TextCache {
+            entries: VecDeque::new(),
+            bytes: 0,
+            max_bytes: max_bytes
+        }
+    }
+
+    /// Bytes used by cached images
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Get the rendered image of text on a solid background, rendering it if it is not cached
+    pub fn render(&mut self, font: &Font, text: &str, height: f32, color: Color, background: Color) -> &Image {
+        let found = self.entries.iter().position(|entry| {
+            entry.text == text && entry.height == height
+            && entry.color.data == color.data && entry.background.data == background.data
+        });
+
+        if let Some(i) = found {
+            if let Some(entry) = self.entries.remove(i) {
+                self.entries.push_front(entry);
+            }
+        } else {
+            let rendered = font.render(text, height);
+            let mut image = Image::from_color(rendered.width() as i32, rendered.height() as i32, background);
+            rendered.draw(&mut image, 0, 0, color);
+
+            self.bytes += image.bytes();
+            self.entries.push_front(TextEntry {
+                text: text.to_string(),
+                height: height,
+                color: color,
+                background: background,
+                image: image
+            });
+
+            // Evict least recently used entries, always keeping the new one
+            while self.bytes > self.max_bytes && self.entries.len() > 1 {
+                if let Some(entry) = self.entries.pop_back() {
+                    self.bytes -= entry.image.bytes();
+                }
+            }
+        }
+
+        &self.entries[0].image
+    }
+}
@@ -5,3 +5,107 @@ pub const BAR_COLOR: Color = Color::rgb(47, 52, 63);
 pub const BAR_HIGHLIGHT_COLOR: Color = Color::rgb(0x44, 0x4a, 0x58);
 pub const TEXT_COLOR: Color = Color::rgb(0xD3, 0xDA, 0xE3);
 pub const TEXT_HIGHLIGHT_COLOR: Color = Color::rgb(235, 241, 255);
+pub const CLOSE_ARMED_COLOR: Color = Color::rgb(0xC0, 0x39, 0x2B);
+pub const ATTENTION_COLOR: Color = Color::rgb(0xE6, 0x7E, 0x22);
+
+/// Parse a color written as `#RRGGBB` or `#AARRGGBB`
+pub fn parse_color(string: &str) -> Option<Color> {
+    if ! string.starts_with('#') {
+        return None;
+    }
+
+    let value = match u32::from_str_radix(&string[1..], 16) {
+        Ok(value) => value,
+        Err(_) => return None
+    };
+    match string.len() {
+        7 => Some(Color { data: 0xFF000000 | value }),
+        9 => Some(Color { data: value }),
+        _ => None
+    }
+}
+
+/// Perceived brightness of a color, from 0 to 255
+fn luma(color: Color) -> u32 {
+    (((color.data >> 16) & 0xFF) * 299 + ((color.data >> 8) & 0xFF) * 587 + (color.data & 0xFF) * 114) / 1000
+}
+
+/// Mix two opaque colors, taking `amount` out of 255 from the second
+fn mix(a: Color, b: Color, amount: u32) -> Color {
+    let channel = |shift: u32| ((((a.data >> shift) & 0xFF) * (255 - amount) + ((b.data >> shift) & 0xFF) * amount) / 255) << shift;
+    Color { data: 0xFF000000 | channel(16) | channel(8) | channel(0) }
+}
+
+/// Colors used to draw decorations and compositor UI
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub background: Color,
+    pub bar: Color,
+    pub bar_highlight: Color,
+    pub text: Color,
+    pub text_highlight: Color,
+    pub close_armed: Color,
+    /// Pulsed over the title bar of windows asking for attention
+    pub attention: Color,
+}
+
+impl Theme {
+    /// The default colors, replaced by any set in the `[theme]` config section
+    pub fn new(colors: &[(String, String)]) -> Theme {
+        let mut theme = Theme {
+            background: BACKGROUND_COLOR,
+            bar: BAR_COLOR,
+            bar_highlight: BAR_HIGHLIGHT_COLOR,
+            text: TEXT_COLOR,
+            text_highlight: TEXT_HIGHLIGHT_COLOR,
+            close_armed: CLOSE_ARMED_COLOR,
+            attention: ATTENTION_COLOR
+        };
+
+        for &(ref name, ref value) in colors.iter() {
+            let color = match parse_color(value) {
+                Some(color) => color,
+                None => {
+                    println!("orbital: invalid color '{}' for '{}'", value, name);
+                    continue;
+                }
+            };
+
+            match name.as_str() {
+                "background" => theme.background = color,
+                "bar" => theme.bar = color,
+                "bar_highlight" => theme.bar_highlight = color,
+                "text" => theme.text = color,
+                "text_highlight" => theme.text_highlight = color,
+                "close_armed" => theme.close_armed = color,
+                "attention" => theme.attention = color,
+                _ => println!("orbital: unknown theme color '{}'", name)
+            }
+        }
+
+        theme
+    }
+
+    /// Title bar colors for a window with an accent color set by its client
+    ///
+    /// The focused title bar uses the accent, the unfocused one mixes it with the bar color, and
+    /// the text is the theme's text or background color, whichever is readable on the bar.
+    pub fn with_accent(&self, accent: Color) -> Theme {
+        let accent = Color { data: 0xFF000000 | accent.data };
+        let readable = |bar: Color, light: Color| {
+            let bar_luma = luma(bar) as i32;
+            if (luma(light) as i32 - bar_luma).abs() >= (luma(self.background) as i32 - bar_luma).abs() {
+                light
+            } else {
+                self.background
+            }
+        };
+
+        let mut theme = *self;
+        theme.bar_highlight = accent;
+        theme.bar = mix(accent, self.bar, 128);
+        theme.text_highlight = readable(theme.bar_highlight, self.text_highlight);
+        theme.text = readable(theme.bar, self.text);
+        theme
+    }
+}
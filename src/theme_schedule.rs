@@ -0,0 +1,141 @@
+use config::Config;
+use taskbar::minutes_of_day;
+use theme::Theme;
+
+/// Light or dark variant of the theme
+#[derive(Clone, Copy, PartialEq)]
+pub enum ThemeMode {
+    Light,
+    Dark,
+}
+
+impl ThemeMode {
+    pub fn from_str(string: &str) -> Option<ThemeMode> {
+        match string {
+            "light" => Some(ThemeMode::Light),
+            "dark" => Some(ThemeMode::Dark),
+            _ => None
+        }
+    }
+}
+
+/// Parse a time of day written as `HH:MM`, in minutes since midnight
+fn parse_time(string: &str) -> Option<u32> {
+    let mut parts = string.splitn(2, ':');
+    let hours = parts.next().unwrap_or("").parse::<u32>();
+    let minutes = parts.next().unwrap_or("").parse::<u32>();
+    match (hours, minutes) {
+        (Ok(hours), Ok(minutes)) if hours < 24 && minutes < 60 => Some(hours * 60 + minutes),
+        _ => None
+    }
+}
+
+/// Parse an offset from UTC written as `+HH:MM` or `-HH:MM`, in minutes
+fn parse_offset(string: &str) -> Option<i32> {
+    let (sign, time) = if string.starts_with('+') {
+        (1, &string[1..])
+    } else if string.starts_with('-') {
+        (-1, &string[1..])
+    } else {
+        return None;
+    };
+    parse_time(time).map(|minutes| sign * minutes as i32)
+}
+
+/// Light and dark variants of the theme and backgrounds, switched between by the time of day
+///
+/// The variants use the colors of the `[theme_light]` or `[theme_dark]` config section over those
+/// of `[theme]`, and the `background_light` or `background_dark` images instead of `background`
+/// if any are set. With `light_time` and `dark_time` set, as `HH:MM` in local time, the light
+/// variant is used from the first until the second. Local time is UTC moved by `utc_offset`, as
+/// there is no time zone database to read it from. Writing `theme light` or
+/// `theme dark` to `orbital:control` picks a variant regardless of the time, and `theme auto`
+/// goes back to following it.
+pub struct ThemeSchedule {
+    /// Minutes after midnight the light and dark variants start
+    times: Option<(u32, u32)>,
+    /// Minutes local time is ahead of UTC
+    utc_offset: i32,
+    base: Theme,
+    light: Theme,
+    dark: Theme,
+    background: Vec<String>,
+    background_light: Vec<String>,
+    background_dark: Vec<String>,
+    /// Variant picked with the control scheme, instead of by the time
+    pub forced: Option<ThemeMode>,
+}
+
+impl ThemeSchedule {
+    pub fn new(config: &Config) -> ThemeSchedule {
+        let times = match (parse_time(&config.light_time), parse_time(&config.dark_time)) {
+            (Some(light), Some(dark)) if light != dark => Some((light, dark)),
+            (None, None) if config.light_time.is_empty() && config.dark_time.is_empty() => None,
+            _ => {
+                println!("orbital: invalid light_time '{}' or dark_time '{}'", config.light_time, config.dark_time);
+                None
+            }
+        };
+
+        let utc_offset = if config.utc_offset.is_empty() {
+            0
+        } else {
+            parse_offset(&config.utc_offset).unwrap_or_else(|| {
+                println!("orbital: invalid utc_offset '{}'", config.utc_offset);
+                0
+            })
+        };
+
+        let variant = |colors: &Vec<(String, String)>| {
+            let mut all = config.theme.clone();
+            all.extend(colors.iter().cloned());
+            Theme::new(&all)
+        };
+
+        ThemeSchedule {
+            times: times,
+            utc_offset: utc_offset,
+            base: Theme::new(&config.theme),
+            light: variant(&config.theme_light),
+            dark: variant(&config.theme_dark),
+            background: config.background.clone(),
+            background_light: config.background_light.clone(),
+            background_dark: config.background_dark.clone(),
+            forced: None
+        }
+    }
+
+    /// Variant to show now, or None for the plain theme when there is no schedule
+    pub fn mode(&self) -> Option<ThemeMode> {
+        self.forced.or_else(|| self.times.map(|(light, dark)| {
+            let day = 24 * 60;
+            // The offset is less than a day either way
+            let now = ((minutes_of_day() as i32 + self.utc_offset + day) % day) as u32;
+            // The light period may wrap past midnight
+            let light_now = if light < dark {
+                now >= light && now < dark
+            } else {
+                now >= light || now < dark
+            };
+            if light_now { ThemeMode::Light } else { ThemeMode::Dark }
+        }))
+    }
+
+    pub fn theme(&self, mode: Option<ThemeMode>) -> Theme {
+        match mode {
+            Some(ThemeMode::Light) => self.light,
+            Some(ThemeMode::Dark) => self.dark,
+            None => self.base
+        }
+    }
+
+    /// Background images for a variant, the plain ones if it has none of its own
+    pub fn backgrounds(&self, mode: Option<ThemeMode>) -> &Vec<String> {
+        let variant = match mode {
+            Some(ThemeMode::Light) => &self.background_light,
+            Some(ThemeMode::Dark) => &self.background_dark,
+            None => &self.background
+        };
+        if variant.is_empty() { &self.background } else { variant }
+    }
+}
@@ -1,41 +1,238 @@
-use orbclient::{Color, Event, Renderer};
+use orbclient::{Color, Event, EventOption, MoveEvent, ResizeEvent};
 use orbfont::Font;
 use std::cmp::{min, max};
 use std::collections::VecDeque;
-use std::mem::size_of;
+use std::mem::{self, size_of};
 use std::{ptr, str};
 
-use image::{Image, ImageRef};
+use animation::Animation;
+use image::{blend_pixel, Image, ImageRef};
+use latency::Latency;
 use rect::Rect;
-use theme::{BAR_COLOR, BAR_HIGHLIGHT_COLOR, TEXT_COLOR, TEXT_HIGHLIGHT_COLOR};
+use sync_resize::resize_serial_event;
+use theme::Theme;
 
-use syscall::error::{Error, Result, EINVAL};
+use syscall::error::{Error, Result, EAGAIN, EINVAL};
 
+/// Largest magnification of a window's contents
+pub const MAX_ZOOM: i32 = 8;
+
+/// Least width of a title bar kept on a display, so it can always be grabbed
+const REACHABLE_WIDTH: i32 = 64;
+
+/// Resize requests remembered until the client answers them
+const MAX_RESIZE_REQUESTS: usize = 16;
+
+/// A size the client acknowledged, drawn into its own buffer until the next fsync shows it
+struct PendingResize {
+    x: i32,
+    y: i32,
+    image: Image,
+    /// Second buffer at the new size, if double buffered
+    back: Option<Image>,
+}
+
+/// Number of synced damage areas kept for the inspector
+const DAMAGE_HISTORY: usize = 16;
+
+/// Sizes of window decorations in pixels
+#[derive(Clone, Copy)]
+pub struct Decorations {
+    /// Height of the title bar
+    pub title: i32,
+    /// Width of each title bar button
+    pub button: i32,
+    /// Width of the resize borders
+    pub border: i32,
+}
+
+impl Decorations {
+    pub fn new(touch: bool, touch_title: i32) -> Decorations {
+        if touch {
+            Decorations {
+                title: touch_title,
+                button: touch_title,
+                border: touch_title / 2
+            }
+        } else {
+            Decorations {
+                title: 28,
+                button: 18,
+                border: 8
+            }
+        }
+    }
+}
+
+/// Stacking layer of a window, set with the `L` write command
+///
+/// Windows are stacked above all windows in lower layers, whatever their focus order.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Layer {
+    /// Below normal windows, such as a desktop with icons
+    Desktop,
+    Normal,
+    /// Above normal windows, such as panels and docks
+    Dock,
+    /// Above all other windows, such as notifications
+    Overlay,
+}
+
+impl Layer {
+    pub fn from_str(string: &str) -> Option<Layer> {
+        match string {
+            "desktop" => Some(Layer::Desktop),
+            "normal" => Some(Layer::Normal),
+            "dock" => Some(Layer::Dock),
+            "overlay" => Some(Layer::Overlay),
+            _ => None
+        }
+    }
+}
 
 pub struct Window {
     pub x: i32,
     pub y: i32,
     pub async: bool,
     pub resizable: bool,
+    /// Contents are blended using their alpha channel, set with the `t` flag, and may show a blurred background
+    ///
+    /// Other windows are copied opaquely, ignoring their alpha channel
+    pub transparent: bool,
+    /// Drawn without a title bar or resize borders, set with the `b` flag
+    pub borderless: bool,
+    /// A menu or tooltip, set with the `p` flag, which is stacked above other windows, never
+    /// takes focus, and is hidden by clicking outside it
+    pub popup: bool,
+    /// Receives stylus events instead of emulated mouse events, set with the `s` flag
+    pub stylus: bool,
+    /// Ignores the mouse, which goes to the windows beneath it, set with the `c` flag for overlays
+    /// such as HUDs
+    pub click_through: bool,
+    /// Areas relative to the window that take the mouse, set with `R,x,y,w,h,...` so clicks
+    /// elsewhere in a shaped window go to the windows beneath it, or empty for the whole window
+    pub input_region: Vec<Rect>,
+    /// Keeps showing its old size until the client has drawn the new one, set with the `y` flag
+    pub sync_resize: bool,
+    /// Serials of resize requests not answered yet, with where the window moves when they are
+    resize_requests: VecDeque<(u32, i32, i32)>,
+    resize_serial: u32,
+    /// Where the last resize request asked the window to go
+    resize_target: Rect,
+    pending_resize: Option<PendingResize>,
+    /// Window a dialog belongs to, set by adding `@` and the parent's id to the flags
+    ///
+    /// Dialogs are stacked above their parent, and focus returns to the parent when they close.
+    pub parent: Option<usize>,
+    /// Current opacity, animated towards the focused or unfocused opacity
+    pub opacity: u8,
+    /// Opacity set by the client or with Win+scroll, multiplied with the current opacity
+    pub client_opacity: u8,
+    /// Hidden until restored from the window switcher
+    pub minimized: bool,
+    /// Workspace the window is shown on
+    pub workspace: usize,
+    pub layer: Layer,
+    /// Smallest size the client can render, set with the `H` write command
+    pub min_size: (i32, i32),
+    /// Largest size the client can render, set with the `H` write command
+    pub max_size: (i32, i32),
+    /// Geometry to restore when a maximized window is restored
+    pub restore: Option<Rect>,
+    /// Geometry to restore when fullscreen is turned off, set while the window covers its display
+    pub fullscreen: Option<Rect>,
+    /// Keeps the session from going idle while the window can be seen, set with the `I` write command
+    pub inhibit_idle: bool,
+    /// Pulsing while the client asks for attention, until the window is focused
+    pub attention: Option<Animation>,
+    pub decorations: Decorations,
+    /// Colors of the title bar, which is rendered again when they change
+    pub theme: Theme,
+    /// Title bar color requested by the client
+    pub accent: Option<Color>,
+    /// Magnification of the contents, changed with Win+= and Win+-
+    pub zoom: i32,
+    /// Point of the contents shown at the top left of a zoomed window, following the cursor
+    zoom_origin: (i32, i32),
     pub title: String,
+    /// Client buffer, which may be larger than the window
     image: Image,
+    /// Buffer the client draws into while `image` is shown, if double buffered with `W,2`
+    ///
+    /// The buffers are mapped separately, the second at offsets past the end of the first, and
+    /// swap roles each fsync, so the client draws into the first after the second. It is kept
+    /// after `W,1` while the client may still have it mapped, until the buffers are replaced by
+    /// a resize.
+    back: Option<Image>,
+    /// Are the buffers flipped on fsync
+    double_buffered: bool,
+    /// Are the buffers swapped, so `back` is the one mapped first
+    flipped: bool,
+    /// Area of the buffer shown in the window, moved by the client to scroll
+    viewport: Rect,
     title_image: Image,
     title_image_unfocused: Image,
     pub events: VecDeque<Event>,
+    /// Process that opened the window, the only one allowed to use it besides root
+    pub owner: usize,
+    /// User the owner ran as when it opened the window
+    pub owner_uid: u32,
+    /// Areas changed since the last sync, relative to the window
+    pub damage: Vec<Rect>,
+    /// Latest areas synced, relative to the window, newest last
+    pub damage_history: VecDeque<Rect>,
+    pub latency: Latency,
 }
 
 impl Window {
-    pub fn new(x: i32, y: i32, w: i32, h: i32, title: String, async: bool, resizable: bool, font: &Font) -> Window {
+    pub fn new(x: i32, y: i32, w: i32, h: i32, title: String, async: bool, resizable: bool, transparent: bool, borderless: bool, decorations: Decorations, theme: Theme, font: &Font) -> Window {
         let mut window = Window {
             x: x,
             y: y,
             async: async,
             resizable: resizable,
+            transparent: transparent,
+            borderless: borderless,
+            popup: false,
+            stylus: false,
+            click_through: false,
+            input_region: Vec::new(),
+            sync_resize: false,
+            resize_requests: VecDeque::new(),
+            resize_serial: 0,
+            resize_target: Rect::new(0, 0, 0, 0),
+            pending_resize: None,
+            parent: None,
+            opacity: 255,
+            client_opacity: 255,
+            minimized: false,
+            workspace: 0,
+            layer: Layer::Normal,
+            min_size: (0, 0),
+            max_size: (i32::max_value(), i32::max_value()),
+            restore: None,
+            fullscreen: None,
+            inhibit_idle: false,
+            attention: None,
+            decorations: decorations,
+            theme: theme,
+            accent: None,
+            zoom: 1,
+            zoom_origin: (0, 0),
             title: title,
             image: Image::new(w, h),
+            back: None,
+            double_buffered: false,
+            flipped: false,
+            viewport: Rect::new(0, 0, w, h),
             title_image: Image::new(0, 0),
             title_image_unfocused: Image::new(0, 0),
-            events: VecDeque::new()
+            events: VecDeque::new(),
+            owner: 0,
+            owner_uid: 0,
+            damage: Vec::new(),
+            damage_history: VecDeque::new(),
+            latency: Latency::new()
         };
 
         window.render_title(font);
@@ -43,12 +240,46 @@ impl Window {
         window
     }
 
+    /// Position in the stack, higher ranks are drawn above lower ranks
+    ///
+    /// Fullscreen windows of any layer are kept above docks and other windows, but below
+    /// overlays.
+    pub fn stack_rank(&self) -> u8 {
+        if self.popup {
+            return 5;
+        }
+        if self.fullscreen.is_some() {
+            return 3;
+        }
+        match self.layer {
+            Layer::Desktop => 0,
+            Layer::Normal => 1,
+            Layer::Dock => 2,
+            Layer::Overlay => 4
+        }
+    }
+
+    /// Is the window shown on the given workspace
+    pub fn visible(&self, workspace: usize) -> bool {
+        ! self.minimized && self.workspace == workspace
+    }
+
+    /// Does the window hide everything beneath it
+    pub fn opaque(&self) -> bool {
+        ! self.transparent && self.opacity == 255 && self.client_opacity == 255
+    }
+
     pub fn width(&self) -> i32 {
-        self.image.width()
+        self.viewport.width()
     }
 
     pub fn height(&self) -> i32 {
-        self.image.height()
+        self.viewport.height()
+    }
+
+    /// Limit a size to the size hints of the client
+    pub fn clamp_size(&self, w: i32, h: i32) -> (i32, i32) {
+        (max(self.min_size.0, min(self.max_size.0, w)), max(self.min_size.1, min(self.max_size.1, h)))
     }
 
     pub fn rect(&self) -> Rect {
@@ -56,124 +287,493 @@ impl Window {
     }
 
     pub fn title_rect(&self) -> Rect {
-        if self.title.is_empty() {
+        if self.title.is_empty() || self.borderless || self.fullscreen.is_some() {
             Rect::new(-1, -1, 0, 0)
         } else {
-            Rect::new(self.x, self.y - 28, self.width(), 28)
+            Rect::new(self.x, self.y - self.decorations.title, self.width(), self.decorations.title)
         }
     }
 
-    pub fn right_border_rect(&self) -> Rect {
-        if self.resizable {
-            Rect::new(self.x + self.width(), self.y, 8, self.height())
+    /// Rect of the window including its title bar
+    pub fn bounds(&self) -> Rect {
+        let title_rect = self.title_rect();
+        if title_rect.is_empty() {
+            self.rect()
         } else {
-            Rect::new(-1, -1, 0, 0)
+            title_rect.container(&self.rect())
         }
     }
 
-    pub fn bottom_border_rect(&self) -> Rect {
-        if self.resizable {
-            Rect::new(self.x, self.y + self.height(), self.width(), 8)
+    /// Position to move the window to so that enough of its title bar, or of the window if it
+    /// has none, is inside a work area to grab it, or None if it already is
+    pub fn reachable_position(&self, work_rects: &[Rect]) -> Option<(i32, i32)> {
+        let title_rect = self.title_rect();
+        let (grab_rect, min_w, min_h) = if title_rect.is_empty() {
+            let rect = self.rect();
+            (rect, min(REACHABLE_WIDTH, rect.width()), min(REACHABLE_WIDTH, rect.height()))
         } else {
-            Rect::new(-1, -1, 0, 0)
+            (title_rect, min(REACHABLE_WIDTH, title_rect.width()), title_rect.height())
+        };
+
+        let reachable = work_rects.iter().any(|work_rect| {
+            let visible = grab_rect.intersection(work_rect);
+            visible.width() >= min_w && visible.height() >= min_h
+        });
+        if reachable {
+            return None;
         }
+
+        // Move onto the nearest work area
+        let center_x = grab_rect.left() + grab_rect.width()/2;
+        let center_y = grab_rect.top() + grab_rect.height()/2;
+        let work_rect = match work_rects.iter().min_by_key(|work_rect| {
+            let dx = max(0, max(work_rect.left() - center_x, center_x - work_rect.right()));
+            let dy = max(0, max(work_rect.top() - center_y, center_y - work_rect.bottom()));
+            dx.saturating_add(dy)
+        }) {
+            Some(work_rect) => work_rect,
+            None => return None
+        };
+        let left = max(work_rect.left() - grab_rect.width() + min_w, min(work_rect.right() - min_w, grab_rect.left()));
+        let top = max(work_rect.top() - grab_rect.height() + min_h, min(work_rect.bottom() - min_h, grab_rect.top()));
+        Some((self.x + left - grab_rect.left(), self.y + top - grab_rect.top()))
     }
 
-    pub fn bottom_right_border_rect(&self) -> Rect {
-        if self.resizable {
-            Rect::new(self.x + self.width(), self.y + self.height(), 8, 8)
+    /// A resize border, or an empty rect if the window can not be resized by its borders
+    fn border_rect(&self, x: i32, y: i32, w: i32, h: i32) -> Rect {
+        if self.resizable && ! self.borderless && self.fullscreen.is_none() {
+            Rect::new(x, y, w, h)
         } else {
             Rect::new(-1, -1, 0, 0)
         }
     }
 
+    /// Left border, beside the window and its title bar
+    pub fn left_border_rect(&self) -> Rect {
+        let bounds = self.bounds();
+        self.border_rect(self.x - self.decorations.border, bounds.top(), self.decorations.border, bounds.height())
+    }
+
+    /// Right border, beside the window and its title bar
+    pub fn right_border_rect(&self) -> Rect {
+        let bounds = self.bounds();
+        self.border_rect(self.x + self.width(), bounds.top(), self.decorations.border, bounds.height())
+    }
+
+    /// Top border, above the title bar
+    pub fn top_border_rect(&self) -> Rect {
+        let bounds = self.bounds();
+        self.border_rect(self.x, bounds.top() - self.decorations.border, self.width(), self.decorations.border)
+    }
+
+    pub fn bottom_border_rect(&self) -> Rect {
+        self.border_rect(self.x, self.y + self.height(), self.width(), self.decorations.border)
+    }
+
+    pub fn top_left_border_rect(&self) -> Rect {
+        let bounds = self.bounds();
+        self.border_rect(self.x - self.decorations.border, bounds.top() - self.decorations.border, self.decorations.border, self.decorations.border)
+    }
+
+    pub fn top_right_border_rect(&self) -> Rect {
+        let bounds = self.bounds();
+        self.border_rect(self.x + self.width(), bounds.top() - self.decorations.border, self.decorations.border, self.decorations.border)
+    }
+
+    pub fn bottom_left_border_rect(&self) -> Rect {
+        self.border_rect(self.x - self.decorations.border, self.y + self.height(), self.decorations.border, self.decorations.border)
+    }
+
+    pub fn bottom_right_border_rect(&self) -> Rect {
+        self.border_rect(self.x + self.width(), self.y + self.height(), self.decorations.border, self.decorations.border)
+    }
+
+    /// Rect of a title bar button, counting from the right
+    pub fn button_rect(&self, i: i32) -> Rect {
+        let button = self.decorations.button;
+        let x = max(self.x + 6, self.x + self.width() - button * (i + 1));
+        let w = max(self.x + 6, self.x + self.width() - button * i) - x;
+        Rect::new(x, self.y - self.decorations.title, w, self.decorations.title)
+    }
+
+    /// Does the window take the mouse at a point, inside its input region
+    pub fn input_contains(&self, x: i32, y: i32) -> bool {
+        self.rect().contains(x, y) && (self.input_region.is_empty()
+            || self.input_region.iter().any(|rect| rect.contains(x - self.x, y - self.y)))
+    }
+
     pub fn exit_contains(&self, x: i32, y: i32) -> bool {
-        ! self.title.is_empty() && x >= max(self.x + 6, self.x + self.width() - 18)  && y >= self.y - 28 && x < self.x + self.width() && y < self.y
+        ! self.title.is_empty() && self.button_rect(0).contains(x, y) && x < self.x + self.width() && y < self.y
     }
 
-    pub fn draw_title(&mut self, image: &mut ImageRef, rect: &Rect, focused: bool, window_close: &mut Image,window_minimize: &mut Image) {
-        let title_rect = self.title_rect();
-        let title_intersect = rect.intersection(&title_rect);
-        if ! title_intersect.is_empty() {
-            image.rect(title_intersect.left(), title_intersect.top(),
-                       title_intersect.width() as u32, title_intersect.height() as u32,
-                       if focused { BAR_HIGHLIGHT_COLOR } else { BAR_COLOR });
-
-            let mut x = self.x + 6;
-            let w = max(self.x + 6, self.x + self.width() - 18) - x;
-            if w > 0 {
-                let mut title_image = if focused { &mut self.title_image } else { &mut self.title_image_unfocused };
-                let image_rect = Rect::new(x, title_rect.top() + 6, min(w, title_image.width()), title_image.height());
-                let image_intersect = rect.intersection(&image_rect);
-                if ! image_intersect.is_empty() {
-                    image.roi(&image_intersect).blend(&title_image.roi(&image_intersect.offset(-image_rect.left(), -image_rect.top())));
-                }
-            }
+    pub fn maximize_contains(&self, x: i32, y: i32) -> bool {
+        ! self.title.is_empty() && self.resizable && self.button_rect(1).contains(x, y)
+        && x < self.button_rect(0).left() && y < self.y
+    }
 
-            x = max(self.x + 6, self.x + self.width() - 18);
-            if x + 18 <= self.x + self.width() {
-                let image_rect = Rect::new(x, title_rect.top() + 7, window_close.width(), window_close.height());
-                let image_intersect = rect.intersection(&image_rect);
-                if ! image_intersect.is_empty() {
-                    image.roi(&image_intersect).blend(&window_close.roi(&image_intersect.offset(-image_rect.left(), -image_rect.top())));
-                }
-            }
-            x = max(self.x + 6, self.x + self.width() - 18)-7-7-14-7;
-            if x + 18 <= self.x + self.width() {
-                let image_rect = Rect::new(x, title_rect.top() + 7, window_minimize.width(), window_minimize.height());
-                let image_intersect = rect.intersection(&image_rect);
-                if ! image_intersect.is_empty() {
-                    image.roi(&image_intersect).blend(&window_minimize.roi(&image_intersect.offset(-image_rect.left(), -image_rect.top())));
-                }
+    pub fn minimize_contains(&self, x: i32, y: i32) -> bool {
+        ! self.title.is_empty() && self.button_rect(2).contains(x, y)
+        && x < self.button_rect(1).left() && y < self.y
+    }
+
+    /// Pre-rendered title text, on the focused or unfocused title bar color
+    pub fn title_image(&self, focused: bool) -> &Image {
+        if focused { &self.title_image } else { &self.title_image_unfocused }
+    }
+
+    pub fn draw(&self, image: &mut ImageRef, rect: &Rect) {
+        let opacity = (self.opacity as u32 * self.client_opacity as u32 / 255) as u8;
+        if self.zoom > 1 {
+            self.draw_zoomed(image, rect, Some(opacity));
+            return;
+        }
+
+        let self_rect = self.rect();
+        let intersect = self_rect.intersection(&rect);
+        if ! intersect.is_empty() {
+            let contents = self.image.roi(&intersect.offset(self.viewport.left() - self_rect.left(), self.viewport.top() - self_rect.top()));
+            if self.transparent {
+                image.roi_mut(&intersect).blend_opacity(&contents, opacity);
+            } else if opacity < 255 {
+                image.roi_mut(&intersect).fade(&contents, opacity);
+            } else {
+                image.roi_mut(&intersect).blit(&contents);
             }
         }
     }
 
-    pub fn draw(&mut self, image: &mut ImageRef, rect: &Rect) {
+    /// Copy the window contents without blending
+    pub fn draw_direct(&self, image: &mut ImageRef, rect: &Rect) {
+        if self.zoom > 1 {
+            self.draw_zoomed(image, rect, None);
+            return;
+        }
+
         let self_rect = self.rect();
         let intersect = self_rect.intersection(&rect);
         if ! intersect.is_empty() {
-            image.roi(&intersect).blend(&self.image.roi(&intersect.offset(-self_rect.left(), -self_rect.top())));
+            image.roi_mut(&intersect).blit(&self.image.roi(&intersect.offset(self.viewport.left() - self_rect.left(), self.viewport.top() - self_rect.top())));
+        }
+    }
+
+    /// Draw the magnified contents, blended at an opacity as `draw` does, or copied without one
+    fn draw_zoomed(&self, image: &mut ImageRef, rect: &Rect, opacity: Option<u8>) {
+        let self_rect = self.rect();
+        let intersect = self_rect.intersection(&rect).intersection(&image.bounds());
+        if intersect.is_empty() {
+            return;
+        }
+
+        // The origin may be outside of a window that was resized since it was panned
+        let w = self.width();
+        let h = self.height();
+        let origin = image.bounds();
+        let stride = image.width();
+        let src_stride = self.image.width();
+        let src = self.image.data();
+        let dst = image.data_mut();
+        for y in intersect.top()..intersect.bottom() {
+            let sy = min(h - 1, self.zoom_origin.1 + (y - self_rect.top()) / self.zoom);
+            for x in intersect.left()..intersect.right() {
+                let sx = min(w - 1, self.zoom_origin.0 + (x - self_rect.left()) / self.zoom);
+                let pixel = src[((self.viewport.top() + sy) * src_stride + self.viewport.left() + sx) as usize];
+                let alpha = match opacity {
+                    Some(opacity) if self.transparent => ((pixel.data >> 24) & 0xFF) * opacity as u32 / 255,
+                    Some(opacity) => opacity as u32,
+                    None => 255
+                };
+                blend_pixel(&mut dst[((y - origin.top()) * stride + x - origin.left()) as usize], pixel, alpha);
+            }
+        }
+    }
+
+    /// Change the magnification, panning to the cursor at x and y inside the window
+    pub fn set_zoom(&mut self, zoom: i32, x: i32, y: i32) {
+        self.zoom = max(1, min(MAX_ZOOM, zoom));
+        self.zoom_pan(x, y);
+    }
+
+    /// Pan a zoomed window so the cursor, at x and y inside the window, shows the same part of the
+    /// contents, reaching the edges of the contents at the edges of the window. Returns true if
+    /// the contents moved.
+    pub fn zoom_pan(&mut self, x: i32, y: i32) -> bool {
+        let w = self.width();
+        let h = self.height();
+        let origin = if self.zoom > 1 && w > 0 && h > 0 {
+            let x = max(0, min(w - 1, x));
+            let y = max(0, min(h - 1, y));
+            (x * (w - w / self.zoom) / w, y * (h - h / self.zoom) / h)
+        } else {
+            (0, 0)
+        };
+        let moved = origin != self.zoom_origin;
+        self.zoom_origin = origin;
+        moved
+    }
+
+    /// Area inside the window showing an area of the contents, magnified when zoomed
+    pub fn zoom_rect(&self, rect: &Rect) -> Rect {
+        if self.zoom > 1 {
+            Rect::new(rect.left().saturating_sub(self.zoom_origin.0).saturating_mul(self.zoom),
+                      rect.top().saturating_sub(self.zoom_origin.1).saturating_mul(self.zoom),
+                      rect.width().saturating_mul(self.zoom), rect.height().saturating_mul(self.zoom))
+                .intersection(&Rect::new(0, 0, self.width(), self.height()))
+        } else {
+            *rect
+        }
+    }
+
+    /// Position in the contents shown at x and y inside the window
+    pub fn zoom_point(&self, x: i32, y: i32) -> (i32, i32) {
+        (self.zoom_origin.0 + x / self.zoom, self.zoom_origin.1 + y / self.zoom)
+    }
+
+    /// Copy of the contents shown in the window
+    pub fn contents(&self) -> Image {
+        let mut image = Image::new(self.width(), self.height());
+        let bounds = image.bounds();
+        image.roi_mut(&bounds).blit(&self.image.roi(&self.viewport));
+        image
+    }
+
+    /// Draw a scaled down copy of the window contents, centered in a rect, inside a clip rect,
+    /// filtered if smooth
+    pub fn draw_thumbnail(&self, image: &mut ImageRef, rect: &Rect, clip: &Rect, smooth: bool) {
+        let w = self.width();
+        let h = self.height();
+        if w <= 0 || h <= 0 || rect.is_empty() {
+            return;
+        }
+
+        // Keep the aspect ratio, never scaling up
+        let (tw, th) = if w * rect.height() > h * rect.width() {
+            (min(w, rect.width()), max(1, min(h, h * rect.width() / w)))
+        } else {
+            (max(1, min(w, w * rect.height() / h)), min(h, rect.height()))
+        };
+        let thumbnail_rect = Rect::new(rect.left() + (rect.width() - tw)/2, rect.top() + (rect.height() - th)/2, tw, th);
+        let origin = image.bounds();
+        let intersect = thumbnail_rect.intersection(clip).intersection(&origin);
+
+        let stride = image.width();
+        let dst = image.data_mut();
+        for y in intersect.top()..intersect.bottom() {
+            let sy = scale_position(y - thumbnail_rect.top(), h, th);
+            for x in intersect.left()..intersect.right() {
+                let sx = scale_position(x - thumbnail_rect.left(), w, tw);
+                dst[((y - origin.top()) * stride + x - origin.left()) as usize] = self.sample(sx, sy, smooth);
+            }
+        }
+    }
+
+    /// Draw the window contents stretched to fill a rect, blended as `draw` does and faded
+    /// further to an opacity, for animations, filtered if smooth
+    pub fn draw_scaled(&self, image: &mut ImageRef, rect: &Rect, opacity: u8, smooth: bool) {
+        let w = self.width();
+        let h = self.height();
+        if w <= 0 || h <= 0 || rect.is_empty() {
+            return;
+        }
+
+        let origin = image.bounds();
+        let intersect = rect.intersection(&origin);
+        let opacity = opacity as u32 * self.opacity as u32 * self.client_opacity as u32 / (255 * 255);
+        let stride = image.width();
+        let dst = image.data_mut();
+        for y in intersect.top()..intersect.bottom() {
+            let sy = scale_position(y - rect.top(), h, rect.height());
+            for x in intersect.left()..intersect.right() {
+                let sx = scale_position(x - rect.left(), w, rect.width());
+                let pixel = self.sample(sx, sy, smooth);
+                let alpha = if self.transparent {
+                    ((pixel.data >> 24) & 0xFF) * opacity / 255
+                } else {
+                    opacity
+                };
+                blend_pixel(&mut dst[((y - origin.top()) * stride + x - origin.left()) as usize], pixel, alpha);
+            }
         }
     }
 
+    /// Pixel of the contents at a position in 256ths of a pixel, blending the four pixels around
+    /// it if smooth, or the one it is in if not
+    fn sample(&self, fx: i32, fy: i32, smooth: bool) -> Color {
+        let src_stride = self.image.width();
+        let src = self.image.data();
+        let pixel = |x: i32, y: i32| src[((self.viewport.top() + y) * src_stride + self.viewport.left() + x) as usize].data;
+
+        let (x0, y0) = (fx >> 8, fy >> 8);
+        if ! smooth {
+            return Color { data: pixel(x0, y0) };
+        }
+
+        let x1 = min(x0 + 1, self.width() - 1);
+        let y1 = min(y0 + 1, self.height() - 1);
+        let (wx, wy) = ((fx & 0xFF) as u32, (fy & 0xFF) as u32);
+        let corners = [
+            (pixel(x0, y0), (256 - wx) * (256 - wy)),
+            (pixel(x1, y0), wx * (256 - wy)),
+            (pixel(x0, y1), (256 - wx) * wy),
+            (pixel(x1, y1), wx * wy)
+        ];
+        let mut data = 0;
+        for shift in [0, 8, 16, 24].iter() {
+            let channel = corners.iter().fold(0, |sum, &(pixel, weight)| sum + ((pixel >> shift) & 0xFF) * weight) >> 16;
+            data |= channel << shift;
+        }
+        Color { data: data }
+    }
+
     pub fn event(&mut self, event: Event) {
+        match event.to_option() {
+            EventOption::Key(_) | EventOption::Mouse(_) | EventOption::Scroll(_) => self.latency.input(),
+            _ => ()
+        }
         self.events.push_back(event);
     }
 
+    /// Bytes used by the window buffers
+    pub fn buffer_bytes(&self) -> usize {
+        self.image.bytes() + self.back.as_ref().map_or(0, |back| back.bytes())
+            + self.pending_resize.as_ref().map_or(0, |pending| {
+                pending.image.bytes() + pending.back.as_ref().map_or(0, |back| back.bytes())
+            })
+    }
+
+    /// Bytes used by the rendered title images
+    pub fn title_bytes(&self) -> usize {
+        self.title_image.bytes() + self.title_image_unfocused.bytes()
+    }
+
+    /// Bytes used by queued events
+    pub fn event_bytes(&self) -> usize {
+        self.events.len() * size_of::<Event>()
+    }
+
+    /// Map part of one buffer, the second of a double buffered window at offsets past the end of
+    /// the first, as a mapping may not span both
     pub fn map(&mut self, offset: usize, size: usize) -> Result<usize> {
-        if offset + size <= self.image.data().len() * 4 {
-            Ok(self.image.data_mut().as_mut_ptr() as usize + offset)
-        } else {
-            Err(Error::new(EINVAL))
+        // A synchronized resize is drawn into its own buffers until the fsync
+        if let Some(ref mut pending) = self.pending_resize {
+            return map_buffer(&mut pending.image, pending.back.as_mut(), offset, size);
+        }
+
+        match self.back {
+            Some(ref mut back) => if self.flipped {
+                map_buffer(back, Some(&mut self.image), offset, size)
+            } else {
+                map_buffer(&mut self.image, Some(back), offset, size)
+            },
+            None => map_buffer(&mut self.image, None, offset, size)
         }
     }
 
-    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        if buf.len() >= size_of::<Event>() {
-            let mut i = 0;
-            while i <= buf.len() - size_of::<Event>() {
-                if let Some(event) = self.events.pop_front() {
-                    unsafe { ptr::write(buf.as_mut_ptr().offset(i as isize) as *mut Event, event) };
-                    i += size_of::<Event>();
-                } else {
-                    break;
+    /// Use a second buffer, so the window is never composited while the client draws
+    pub fn set_double_buffered(&mut self, double_buffered: bool) {
+        if double_buffered {
+            if self.back.is_none() {
+                self.back = Some(resized(&self.image, self.image.width(), self.image.height()));
+            }
+            if let Some(ref mut pending) = self.pending_resize {
+                if pending.back.is_none() {
+                    pending.back = Some(resized(&pending.image, pending.image.width(), pending.image.height()));
                 }
             }
-            Ok(i)
+        } else if self.flipped {
+            // Keep showing the first buffer, the second is kept as the client may have it mapped
+            self.flip();
+        }
+        self.double_buffered = double_buffered;
+    }
+
+    /// Show the buffer the client finished drawing, returns false if not double buffered
+    pub fn flip(&mut self) -> bool {
+        if ! self.double_buffered {
+            return false;
+        }
+        match self.back {
+            Some(ref mut back) => {
+                mem::swap(&mut self.image, back);
+                self.flipped = ! self.flipped;
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Read queued events, failing with EAGAIN if there are none
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        read_events(&mut self.events, buf)
+    }
+
+    /// Areas changed since the last sync, or the whole window if the client did not say,
+    /// remembering them for the inspector
+    pub fn take_damage(&mut self) -> Vec<Rect> {
+        let damage = if self.damage.is_empty() {
+            vec![Rect::new(0, 0, self.width(), self.height())]
         } else {
-            Err(Error::new(EINVAL))
+            self.damage.drain(..).collect()
+        };
+        for rect in damage.iter() {
+            if self.damage_history.len() >= DAMAGE_HISTORY {
+                self.damage_history.pop_front();
+            }
+            self.damage_history.push_back(*rect);
         }
+        damage
     }
 
-    pub fn path(&self, buf: &mut [u8]) -> Result<usize> {
-        let mut i = 0;
-        let path_str = format!(
-            "orbital:{}{}/{}/{}/{}/{}/{}",
+    /// Flags the window was opened with, and its parent, as written in its path
+    pub fn flags(&self) -> String {
+        format!(
+            "{}{}{}{}{}{}{}{}{}{}",
             if self.async { "a" } else { "" },
             if self.resizable { "r" } else { "" },
-            self.x, self.y, self.width(), self.height(), self.title
-        );
+            if self.transparent { "t" } else { "" },
+            if self.borderless { "b" } else { "" },
+            if self.fullscreen.is_some() { "f" } else { "" },
+            if self.popup { "p" } else { "" },
+            if self.stylus { "s" } else { "" },
+            if self.click_through { "c" } else { "" },
+            if self.sync_resize { "y" } else { "" },
+            match self.parent { Some(parent) => format!("@{}", parent), None => String::new() }
+        )
+    }
+
+    /// Everything known about the window, for the inspector
+    pub fn inspect(&self, id: usize) -> String {
+        let mut report = String::new();
+        report.push_str(&format!("id={}\n", id));
+        report.push_str(&format!("title={}\n", self.title.replace('\n', " ")));
+        report.push_str(&format!("flags={}\n", self.flags()));
+        report.push_str(&format!("owner={} uid={}\n", self.owner, self.owner_uid));
+        report.push_str(&format!("rect={} {} {} {}\n", self.x, self.y, self.width(), self.height()));
+        let bounds = self.bounds();
+        report.push_str(&format!("bounds={} {} {} {}\n", bounds.left(), bounds.top(), bounds.width(), bounds.height()));
+        report.push_str(&format!("buffer={}x{} bytes={} double_buffered={}\n", self.image.width(), self.image.height(),
+                                 self.buffer_bytes(), self.double_buffered));
+        report.push_str(&format!("viewport={} {} {} {}\n", self.viewport.left(), self.viewport.top(),
+                                 self.viewport.width(), self.viewport.height()));
+        report.push_str(&format!("workspace={} minimized={} zoom={}\n", self.workspace, self.minimized, self.zoom));
+        report.push_str(&format!("opacity={} client_opacity={}\n", self.opacity, self.client_opacity));
+        report.push_str(&format!("min_size={}x{} max_size={}x{}\n", self.min_size.0, self.min_size.1,
+                                 self.max_size.0, self.max_size.1));
+        report.push_str(&format!("events={}\n", self.events.len()));
+        report.push_str(&format!("latency={} {} {}\n", self.latency.last, self.latency.average(), self.latency.max));
+        for rect in self.damage.iter() {
+            report.push_str(&format!("damage pending {} {} {} {}\n", rect.left(), rect.top(), rect.width(), rect.height()));
+        }
+        for rect in self.damage_history.iter() {
+            report.push_str(&format!("damage synced {} {} {} {}\n", rect.left(), rect.top(), rect.width(), rect.height()));
+        }
+        report
+    }
+
+    /// Path of the window, with its id after the flags so clients can verify their handle
+    pub fn path(&self, id: usize, buf: &mut [u8]) -> Result<usize> {
+        let mut i = 0;
+        let path_str = format!("orbital:{}#{}/{}/{}/{}/{}/{}", self.flags(),
+                               id, self.x, self.y, self.width(), self.height(), self.title);
         let path = path_str.as_bytes();
         while i < buf.len() && i < path.len() {
             buf[i] = path[i];
@@ -182,26 +782,292 @@ impl Window {
         Ok(i)
     }
 
+    /// Use the colors of a theme, with the client's accent color if it set one
+    pub fn set_theme(&mut self, theme: Theme, font: &Font) {
+        self.theme = match self.accent {
+            Some(accent) => theme.with_accent(accent),
+            None => theme
+        };
+        self.render_title(font);
+    }
+
     pub fn render_title(&mut self, font: &Font) {
         let title_render = font.render(&self.title, 16.0);
 
-        self.title_image = Image::from_color(title_render.width() as i32, title_render.height() as i32, BAR_HIGHLIGHT_COLOR);
-        title_render.draw(&mut self.title_image, 0, 0, TEXT_HIGHLIGHT_COLOR);
+        self.title_image = Image::from_color(title_render.width() as i32, title_render.height() as i32, self.theme.bar_highlight);
+        title_render.draw(&mut self.title_image, 0, 0, self.theme.text_highlight);
 
-        self.title_image_unfocused = Image::from_color(title_render.width() as i32, title_render.height() as i32, BAR_COLOR);
-        title_render.draw(&mut self.title_image_unfocused, 0, 0, TEXT_COLOR);
+        self.title_image_unfocused = Image::from_color(title_render.width() as i32, title_render.height() as i32, self.theme.bar);
+        title_render.draw(&mut self.title_image_unfocused, 0, 0, self.theme.text);
     }
 
+    /// Resize the window and its buffer, showing the start of the buffer
     pub fn set_size(&mut self, w: i32, h: i32) {
-        let mut new_image = Image::from_color(w, h, Color::rgba(0, 0, 0, 0));
-        let new_rect = Rect::new(0, 0, w, h);
+        self.pending_resize = None;
+        self.resize_buffer(w, h);
+        self.viewport = Rect::new(0, 0, w, h);
+    }
 
-        let rect = Rect::new(0, 0, self.image.width(), self.image.height());
-        let intersect = new_rect.intersection(&rect);
-        if ! intersect.is_empty() {
-            new_image.roi(&intersect).blit(&self.image.roi(&intersect));
+    /// Ask the client to resize, moving the window too once the new size is drawn if it has
+    /// synchronized resizes, and otherwise moving it at once
+    pub fn request_resize(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.resize_target = Rect::new(x, y, w, h);
+        if self.sync_resize {
+            self.resize_serial = self.resize_serial.wrapping_add(1);
+            if self.resize_requests.len() >= MAX_RESIZE_REQUESTS {
+                self.resize_requests.pop_front();
+            }
+            self.resize_requests.push_back((self.resize_serial, x, y));
+            let serial_event = resize_serial_event(self.resize_serial);
+            self.event(serial_event);
+        } else {
+            self.move_to(x, y);
+        }
+        self.event(ResizeEvent {
+            width: w as u32,
+            height: h as u32
+        }.to_event());
+    }
+
+    /// Geometry the window is going to have, the last one requested while a synchronized resize
+    /// is not shown yet, so dragging does not ask for the same size again before it is drawn
+    pub fn target_rect(&self) -> Rect {
+        if self.resize_requests.is_empty() && self.pending_resize.is_none() {
+            self.rect()
+        } else {
+            self.resize_target
+        }
+    }
+
+    /// Start drawing a size the client acknowledged, which is shown at the next fsync, returns
+    /// false for a serial that was not sent
+    pub fn begin_resize(&mut self, serial: u32, w: i32, h: i32) -> bool {
+        let (x, y) = match self.resize_requests.iter().position(|&(request, _, _)| request == serial) {
+            Some(i) => {
+                let (_, x, y) = self.resize_requests[i];
+                // Older requests were overtaken by this one
+                self.resize_requests.drain(.. i + 1);
+                (x, y)
+            },
+            None => return false
+        };
+
+        // Start from the current contents, for any parts the client does not draw
+        let image = {
+            let viewport = self.viewport;
+            let mut image = Image::from_color(w, h, Color::rgba(0, 0, 0, 0));
+            let intersect = Rect::new(0, 0, w, h).intersection(&Rect::new(0, 0, viewport.width(), viewport.height()));
+            if ! intersect.is_empty() {
+                image.roi_mut(&intersect).blit(&self.image.roi(&intersect.offset(viewport.left(), viewport.top())));
+            }
+            image
+        };
+        let back = if self.double_buffered { Some(resized(&image, w, h)) } else { None };
+        self.pending_resize = Some(PendingResize {
+            x: x,
+            y: y,
+            image: image,
+            back: back
+        });
+        true
+    }
+
+    /// Show a synchronized resize the client finished drawing, returns false if there is none
+    pub fn commit_resize(&mut self) -> bool {
+        let pending = match self.pending_resize.take() {
+            Some(pending) => pending,
+            None => return false
+        };
+
+        let (w, h) = (pending.image.width(), pending.image.height());
+        // The client mapped the new buffers, the first of which is shown
+        self.image = pending.image;
+        self.back = pending.back;
+        self.flipped = false;
+        self.viewport = Rect::new(0, 0, w, h);
+        self.move_to(pending.x, pending.y);
+        true
+    }
+
+    /// Move the window, telling the client if it moved
+    fn move_to(&mut self, x: i32, y: i32) {
+        if x != self.x || y != self.y {
+            self.x = x;
+            self.y = y;
+            self.event(MoveEvent {
+                x: x,
+                y: y
+            }.to_event());
+        }
+    }
+
+    /// Resize the buffer without resizing the window, keeping it at least as large as the window
+    pub fn set_buffer_size(&mut self, w: i32, h: i32) {
+        let w = max(w, self.viewport.width());
+        let h = max(h, self.viewport.height());
+        self.resize_buffer(w, h);
+        let (x, y) = (self.viewport.left(), self.viewport.top());
+        self.set_viewport(x, y);
+    }
+
+    /// Move the area of the buffer shown in the window, keeping it inside the buffer
+    pub fn set_viewport(&mut self, x: i32, y: i32) {
+        let x = max(0, min(self.image.width() - self.viewport.width(), x));
+        let y = max(0, min(self.image.height() - self.viewport.height(), y));
+        self.viewport = Rect::new(x, y, self.viewport.width(), self.viewport.height());
+    }
+
+    fn resize_buffer(&mut self, w: i32, h: i32) {
+        self.image = resized(&self.image, w, h);
+        self.back = if self.double_buffered { self.back.as_ref().map(|back| resized(back, w, h)) } else { None };
+    }
+}
+
+/// Position in the source of the middle of a scaled pixel, in 256ths of a pixel
+fn scale_position(scaled: i32, size: i32, scaled_size: i32) -> i32 {
+    let position = ((2 * scaled as i64 + 1) * size as i64 * 128) / scaled_size as i64 - 128;
+    min(max(position, 0), (size as i64 - 1) * 256) as i32
+}
+
+/// Address of part of one buffer, the second of a double buffered window at offsets past the end
+/// of the first, failing if the part spans both
+fn map_buffer(first: &mut Image, second: Option<&mut Image>, offset: usize, size: usize) -> Result<usize> {
+    let len = first.data().len() * 4;
+    let (image, offset) = match (second, offset >= len) {
+        (Some(second), true) => (second, offset - len),
+        (_, _) => (first, offset)
+    };
+    if offset + size > len {
+        return Err(Error::new(EINVAL));
+    }
+    Ok(image.data_mut().as_mut_ptr() as usize + offset)
+}
+
+/// Copy of a buffer at another size, keeping its top left contents
+fn resized(image: &Image, w: i32, h: i32) -> Image {
+    let mut new_image = Image::from_color(w, h, Color::rgba(0, 0, 0, 0));
+    let new_rect = Rect::new(0, 0, w, h);
+
+    let rect = Rect::new(0, 0, image.width(), image.height());
+    let intersect = new_rect.intersection(&rect);
+    if ! intersect.is_empty() {
+        new_image.roi_mut(&intersect).blit(&image.roi(&intersect));
+    }
+
+    new_image
+}
+
+/// Move as many events as fit from a queue into a buffer, failing with EAGAIN if there are none
+fn read_events(events: &mut VecDeque<Event>, buf: &mut [u8]) -> Result<usize> {
+    if buf.len() >= size_of::<Event>() {
+        if events.is_empty() {
+            return Err(Error::new(EAGAIN));
         }
 
-        self.image = new_image;
+        let mut i = 0;
+        while i <= buf.len() - size_of::<Event>() {
+            if let Some(event) = events.pop_front() {
+                unsafe { ptr::write(buf.as_mut_ptr().offset(i as isize) as *mut Event, event) };
+                i += size_of::<Event>();
+            } else {
+                break;
+            }
+        }
+        Ok(i)
+    } else {
+        Err(Error::new(EINVAL))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use orbclient::Event;
+    use std::collections::VecDeque;
+    use std::mem::size_of;
+    use std::ptr;
+
+    use syscall::error::{Error, EAGAIN, EINVAL};
+
+    use image::Image;
+
+    use super::{map_buffer, read_events, scale_position};
+
+    fn event(a: i64) -> Event {
+        Event {
+            code: 1,
+            a: a,
+            b: 0
+        }
+    }
+
+    fn read_back(buf: &[u8], count: usize) -> Vec<i64> {
+        (0..count).map(|i| unsafe {
+            ptr::read(buf.as_ptr().offset((i * size_of::<Event>()) as isize) as *const Event).a
+        }).collect()
+    }
+
+    #[test]
+    fn empty_queue_fails_with_eagain() {
+        let mut events = VecDeque::new();
+        let mut buf = vec![0; 4 * size_of::<Event>()];
+        assert_eq!(read_events(&mut events, &mut buf), Err(Error::new(EAGAIN)));
+    }
+
+    #[test]
+    fn reads_events_in_order() {
+        let mut events: VecDeque<Event> = (1..4).map(event).collect();
+        let mut buf = vec![0; 8 * size_of::<Event>()];
+        assert_eq!(read_events(&mut events, &mut buf), Ok(3 * size_of::<Event>()));
+        assert_eq!(read_back(&buf, 3), vec![1, 2, 3]);
+        assert!(events.is_empty());
+
+        // Once the queue is drained the next read fails again
+        assert_eq!(read_events(&mut events, &mut buf), Err(Error::new(EAGAIN)));
+    }
+
+    #[test]
+    fn leaves_events_that_do_not_fit() {
+        let mut events: VecDeque<Event> = (1..6).map(event).collect();
+        // Space for two events and part of a third
+        let mut buf = vec![0; 2 * size_of::<Event>() + 1];
+        assert_eq!(read_events(&mut events, &mut buf), Ok(2 * size_of::<Event>()));
+        assert_eq!(read_back(&buf, 2), vec![1, 2]);
+        assert_eq!(events.len(), 3);
+    }
+
+    #[test]
+    fn rejects_buffers_smaller_than_an_event() {
+        let mut events: VecDeque<Event> = (1..2).map(event).collect();
+        let mut buf = vec![0; size_of::<Event>() - 1];
+        assert_eq!(read_events(&mut events, &mut buf), Err(Error::new(EINVAL)));
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn maps_each_buffer_separately() {
+        let mut first = Image::new(4, 2);
+        let mut second = Image::new(4, 2);
+        let len = 4 * 2 * 4;
+        let first_addr = first.data_mut().as_mut_ptr() as usize;
+        let second_addr = second.data_mut().as_mut_ptr() as usize;
+
+        assert_eq!(map_buffer(&mut first, Some(&mut second), 4, len - 4), Ok(first_addr + 4));
+        assert_eq!(map_buffer(&mut first, Some(&mut second), len, len), Ok(second_addr));
+        // A mapping may not span both buffers
+        assert_eq!(map_buffer(&mut first, Some(&mut second), 4, len), Err(Error::new(EINVAL)));
+        assert_eq!(map_buffer(&mut first, None, len, 4), Err(Error::new(EINVAL)));
+    }
+
+    #[test]
+    fn scaled_positions_stay_inside_the_source() {
+        for &(size, scaled_size) in [(1, 1), (1, 7), (7, 1), (640, 97), (97, 640), (4096, 3)].iter() {
+            for scaled in 0..scaled_size {
+                let position = scale_position(scaled, size, scaled_size);
+                assert!(position >= 0 && position <= (size - 1) * 256);
+            }
+        }
+        // Halving samples between each pair of pixels
+        assert_eq!(scale_position(0, 4, 2), 128);
+        assert_eq!(scale_position(1, 4, 2), 640);
     }
 }
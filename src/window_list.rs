@@ -0,0 +1,70 @@
+use std::cmp::min;
+use syscall::error::{Error, Result, EAGAIN};
+
+use window::Window;
+
+/// A line describing a window: `<id> <x> <y> <width> <height> <workspace> <state> <title>`,
+/// where the state is `focused`, `minimized` or `normal`
+pub fn describe(id: usize, window: &Window, focused: bool) -> String {
+    let state = if focused {
+        "focused"
+    } else if window.minimized {
+        "minimized"
+    } else {
+        "normal"
+    };
+    format!("{} {} {} {} {} {} {} {}", id, window.x, window.y, window.width(), window.height(),
+            window.workspace, state, window.title.replace('\n', " "))
+}
+
+/// A handle to the list of windows, opened with `orbital:windows` or `orbital:windows/watch`, for
+/// shells such as task bars and launchers
+///
+/// Reading returns a line for each window in the format of `describe`. Handles opened with
+/// `windows/watch` then wait for changes, returning `create` followed by the same fields when a
+/// window opens, `destroy <id>` when one closes, and `title <id> <title>` when a title changes.
+pub struct WindowList {
+    pub watch: bool,
+    data: Vec<u8>,
+}
+
+impl WindowList {
+    pub fn new(list: String, watch: bool) -> WindowList {
+        WindowList {
+            watch: watch,
+            data: list.into_bytes()
+        }
+    }
+
+    /// Queue a change for a watching handle
+    pub fn push(&mut self, line: &str) {
+        if self.watch {
+            self.data.extend_from_slice(line.as_bytes());
+            self.data.push(b'\n');
+        }
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.data.is_empty() {
+            return if self.watch {
+                Err(Error::new(EAGAIN))
+            } else {
+                Ok(0)
+            };
+        }
+
+        let count = min(buf.len(), self.data.len());
+        buf[..count].copy_from_slice(&self.data[..count]);
+        self.data.drain(..count);
+        Ok(count)
+    }
+
+    /// Path of the handle, without the scheme
+    pub fn path(&self) -> &'static str {
+        if self.watch {
+            "windows/watch"
+        } else {
+            "windows"
+        }
+    }
+}